@@ -0,0 +1,209 @@
+use actix_web::{web, HttpResponse, Result};
+use aws_sdk_athena::model::QueryExecutionState;
+use std::time::Duration;
+
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+#[derive(serde::Deserialize)]
+struct AdvanceParam {
+    seconds: u64,
+}
+
+#[derive(serde::Deserialize)]
+struct StateParam {
+    state: String,
+    reason: Option<String>,
+}
+
+pub async fn advance(
+    body: web::Bytes,
+    data: web::Data<crate::model::AppData>,
+) -> Result<HttpResponse> {
+    let input = serde_json::from_slice::<AdvanceParam>(&body).map_err(|e| {
+        crate::operation::invalid_request(format!("invalid advance request: {:}", e))
+    })?;
+    let now = data
+        .clock
+        .advance(Duration::from_secs(input.seconds))
+        .ok_or_else(|| {
+            crate::operation::invalid_request(
+                "the clock can only be advanced with MINERVA_VIRTUAL_CLOCK".to_string(),
+            )
+        })?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "now": now as f64 / 1000.0 })))
+}
+
+pub async fn set_state(
+    query_execution_id: web::Path<String>,
+    body: web::Bytes,
+    data: web::Data<crate::model::AppData>,
+) -> Result<HttpResponse> {
+    let input = serde_json::from_slice::<StateParam>(&body)
+        .map_err(|e| crate::operation::invalid_request(format!("invalid state request: {:}", e)))?;
+    let state = match QueryExecutionState::from(input.state.to_uppercase().as_ref()) {
+        QueryExecutionState::Unknown(_) => {
+            return Ok(crate::operation::invalid_request(format!(
+                "unknown state: {:}",
+                input.state
+            )))
+        }
+        state => state,
+    };
+    crate::operation::force_state(&data, &query_execution_id, state, input.reason)
+}
+
+// Note: forgets everything about the queries, as if the server was restarted.
+// The locks are taken one at a time, the token and waiting queue ones before `processes` as
+// StartQueryExecution does, and the result files are removed once they're all released.
+pub async fn reset(data: web::Data<crate::model::AppData>) -> Result<HttpResponse> {
+    data.client_request_tokens.lock().unwrap().clear();
+    data.waiting_queries.lock().unwrap().clear();
+    for (_, handle) in data.process_handles.lock().unwrap().drain() {
+        handle.abort();
+    }
+    let query_processes = data
+        .processes
+        .write()
+        .unwrap()
+        .drain()
+        .map(|(_, v)| v)
+        .collect::<Vec<_>>();
+    for query_process in &query_processes {
+        crate::operation::remove_output(data.output_dir.as_deref(), query_process);
+    }
+    data.throttle_rngs.lock().unwrap().clear();
+    if let Some(counter) = &data.query_execution_id_counter {
+        counter.store(0, std::sync::atomic::Ordering::SeqCst);
+    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "cleared": query_processes.len() })))
+}
+
+// Note: Prometheus text exposition format, every state is reported even when no query is in it.
+pub async fn metrics(data: web::Data<crate::model::AppData>) -> Result<HttpResponse> {
+    let states = [
+        QueryExecutionState::Queued,
+        QueryExecutionState::Running,
+        QueryExecutionState::Succeeded,
+        QueryExecutionState::Failed,
+        QueryExecutionState::Cancelled,
+    ];
+    let mut counts = states.iter().map(|_| 0).collect::<Vec<usize>>();
+    let processes = data.processes.read().unwrap();
+    for query_process in processes.values() {
+        if let Some(i) = states
+            .iter()
+            .position(|v| v.as_str() == query_process.state)
+        {
+            counts[i] += 1;
+        }
+    }
+    let mut body = String::new();
+    body.push_str("# HELP minerva_queries Query executions tracked by minerva, by state.\n");
+    body.push_str("# TYPE minerva_queries gauge\n");
+    for (state, count) in states.iter().zip(counts) {
+        body.push_str(&format!(
+            "minerva_queries{{state=\"{:}\"}} {:}\n",
+            state.as_str(),
+            count
+        ));
+    }
+    body.push_str("# HELP minerva_queries_tracked Query executions tracked by minerva.\n");
+    body.push_str("# TYPE minerva_queries_tracked gauge\n");
+    body.push_str(&format!("minerva_queries_tracked {:}\n", processes.len()));
+    Ok(HttpResponse::Ok()
+        .content_type(METRICS_CONTENT_TYPE)
+        .body(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{post, post_admin, start};
+    use actix_web::http::StatusCode;
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[actix_rt::test]
+    async fn set_state_pins_an_unfinished_query_and_rejects_a_finished_one() {
+        let handle =
+            start(crate::MinervaServer::builder().process_interval(Duration::from_secs(60)));
+        let (_, body) = post(
+            &handle,
+            "StartQueryExecution",
+            json!({ "QueryString": "SELECT * FROM users" }),
+        )
+        .await;
+        let query_execution_id = body["QueryExecutionId"].as_str().unwrap();
+        let path = format!("/admin/queries/{:}/state", query_execution_id);
+
+        let (status, body) = post_admin(&handle, &path, json!({ "state": "RUNNING" })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["QueryExecution"]["Status"]["State"], "RUNNING");
+        assert!(handle.process_handles.lock().unwrap().is_empty());
+
+        let (status, body) = post_admin(
+            &handle,
+            &path,
+            json!({ "state": "failed", "reason": "boom" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["QueryExecution"]["Status"]["State"], "FAILED");
+        assert_eq!(
+            body["QueryExecution"]["Status"]["StateChangeReason"],
+            "boom"
+        );
+
+        let (status, body) = post_admin(&handle, &path, json!({ "state": "SUCCEEDED" })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["Message"],
+            "Query has already finished. Current state: FAILED"
+        );
+
+        let (status, _) = post_admin(&handle, &path, json!({ "state": "DONE" })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, body) = post_admin(
+            &handle,
+            "/admin/queries/foo/state",
+            json!({ "state": "FAILED" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["__type"], "InvalidRequestException");
+        handle.stop().await;
+    }
+
+    #[actix_rt::test]
+    async fn reset_clears_the_queries_their_tasks_and_the_id_counter() {
+        let handle = start(
+            crate::MinervaServer::builder()
+                .process_interval(Duration::from_secs(60))
+                .deterministic_ids(true),
+        );
+        let start_query = || {
+            post(
+                &handle,
+                "StartQueryExecution",
+                json!({ "QueryString": "SELECT * FROM users" }),
+            )
+        };
+        let (_, first) = start_query().await;
+        start_query().await;
+
+        let (status, body) = post_admin(&handle, "/admin/reset", json!({})).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "cleared": 2 }));
+        assert!(handle.process_handles.lock().unwrap().is_empty());
+        let (status, _) = post(
+            &handle,
+            "GetQueryExecution",
+            json!({ "QueryExecutionId": first["QueryExecutionId"] }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, again) = start_query().await;
+        assert_eq!(again["QueryExecutionId"], first["QueryExecutionId"]);
+        handle.stop().await;
+    }
+}
@@ -0,0 +1,88 @@
+use actix_rt::time;
+use futures::channel::oneshot;
+use futures::future::{self, BoxFuture};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Time source of query state transitions and timestamps, in epoch millis.
+pub trait Clock: Send + Sync {
+    fn now_millis(&self) -> u64;
+
+    fn sleep_until(&self, deadline: u64) -> BoxFuture<'static, ()>;
+
+    /// Moves the clock forward, returns the new time or `None` if the clock can't be advanced.
+    fn advance(&self, _duration: Duration) -> Option<u64> {
+        None
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|v| v.as_millis() as u64)
+            .unwrap_or(0)
+    }
+
+    fn sleep_until(&self, deadline: u64) -> BoxFuture<'static, ()> {
+        let remaining = deadline.saturating_sub(self.now_millis());
+        Box::pin(time::delay_for(Duration::from_millis(remaining)))
+    }
+}
+
+/// A clock which only moves when advanced, e.g. by `POST /admin/advance`.
+pub struct VirtualClock {
+    state: Mutex<VirtualClockState>,
+}
+
+struct VirtualClockState {
+    now: u64,
+    sleepers: Vec<(u64, oneshot::Sender<()>)>,
+}
+
+impl VirtualClock {
+    pub fn new(now: u64) -> Self {
+        VirtualClock {
+            state: Mutex::new(VirtualClockState {
+                now,
+                sleepers: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now_millis(&self) -> u64 {
+        self.state.lock().unwrap().now
+    }
+
+    fn sleep_until(&self, deadline: u64) -> BoxFuture<'static, ()> {
+        let mut state = self.state.lock().unwrap();
+        if deadline <= state.now {
+            return Box::pin(future::ready(()));
+        }
+        let (sender, receiver) = oneshot::channel();
+        state.sleepers.push((deadline, sender));
+        Box::pin(async move {
+            let _ = receiver.await;
+        })
+    }
+
+    fn advance(&self, duration: Duration) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        state.now += duration.as_millis() as u64;
+        let now = state.now;
+        let (woken, sleepers) = state
+            .sleepers
+            .drain(..)
+            .partition::<Vec<_>, _>(|(deadline, _)| *deadline <= now);
+        state.sleepers = sleepers;
+        for (_, sender) in woken {
+            // Note: the sleeper is gone if its process_query task was aborted.
+            let _ = sender.send(());
+        }
+        Some(now)
+    }
+}
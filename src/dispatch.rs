@@ -0,0 +1,256 @@
+use actix_web::http::{HeaderName, HeaderValue, StatusCode};
+use actix_web::{HttpResponse, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+pub const UNKNOWN_OPERATION_EXCEPTION: &str = "UnknownOperationException";
+
+pub const SERIALIZATION_EXCEPTION: &str = "SerializationException";
+const THROTTLING_EXCEPTION: &str = "ThrottlingException";
+
+const RETRY_AFTER_HEADER: &str = "retry-after";
+const THROTTLING_RETRY_AFTER_SECS: u64 = 1;
+
+/// An Athena operation, answering the body of a request on the state of the server.
+// Note: the operations only touch the state behind the locks of `AppData` and never wait, so
+// `root` calls them inline.
+pub type Handler =
+    Box<dyn Fn(serde_json::Value, &crate::model::AppData) -> Result<HttpResponse> + Send + Sync>;
+
+// Note: the body is read as the input of the operation, a required member it lacks is an
+// InvalidRequestException as the operations report an empty one.
+fn handler<T: serde::de::DeserializeOwned + 'static>(
+    operation: fn(&T, &crate::model::AppData) -> Result<HttpResponse>,
+) -> Handler {
+    Box::new(move |body, data| match serde_json::from_value::<T>(body) {
+        Ok(input) => operation(&input, data),
+        Err(e) => {
+            let message = e.to_string();
+            Ok(match missing_member(&message) {
+                Some(member) => {
+                    crate::operation::invalid_request(format!("{:} is required", member))
+                }
+                None => crate::operation::error_response(
+                    StatusCode::BAD_REQUEST,
+                    SERIALIZATION_EXCEPTION,
+                    message,
+                ),
+            })
+        }
+    })
+}
+
+fn missing_member(message: &str) -> Option<&str> {
+    let (member, _) = message.strip_prefix("missing field `")?.split_once('`')?;
+    Some(member)
+}
+
+/// The operations by their `X-Amz-Target`, built once when the server starts.
+pub struct Registry {
+    handlers: HashMap<&'static str, Handler>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        let handlers: [(&'static str, Handler); 33] = [
+            (
+                "AmazonAthena.StartQueryExecution",
+                handler(crate::operation::start_query_execution),
+            ),
+            (
+                "AmazonAthena.GetQueryExecution",
+                handler(crate::operation::get_query_execution),
+            ),
+            (
+                "AmazonAthena.BatchGetQueryExecution",
+                handler(crate::operation::batch_get_query_execution),
+            ),
+            (
+                "AmazonAthena.GetQueryResults",
+                handler(crate::operation::get_query_results),
+            ),
+            (
+                "AmazonAthena.GetQueryRuntimeStatistics",
+                handler(crate::operation::get_query_runtime_statistics),
+            ),
+            (
+                "AmazonAthena.StopQueryExecution",
+                handler(crate::operation::stop_query_execution),
+            ),
+            (
+                "AmazonAthena.ListQueryExecutions",
+                handler(crate::operation::list_query_executions),
+            ),
+            (
+                "AmazonAthena.CreateNamedQuery",
+                handler(crate::operation::create_named_query),
+            ),
+            (
+                "AmazonAthena.GetNamedQuery",
+                handler(crate::operation::get_named_query),
+            ),
+            (
+                "AmazonAthena.ListNamedQueries",
+                handler(crate::operation::list_named_queries),
+            ),
+            (
+                "AmazonAthena.UpdateNamedQuery",
+                handler(crate::operation::update_named_query),
+            ),
+            (
+                "AmazonAthena.DeleteNamedQuery",
+                handler(crate::operation::delete_named_query),
+            ),
+            (
+                "AmazonAthena.BatchGetNamedQuery",
+                handler(crate::operation::batch_get_named_query),
+            ),
+            (
+                "AmazonAthena.CreateWorkGroup",
+                handler(crate::operation::create_work_group),
+            ),
+            (
+                "AmazonAthena.GetWorkGroup",
+                handler(crate::operation::get_work_group),
+            ),
+            (
+                "AmazonAthena.ListWorkGroups",
+                handler(crate::operation::list_work_groups),
+            ),
+            (
+                "AmazonAthena.UpdateWorkGroup",
+                handler(crate::operation::update_work_group),
+            ),
+            (
+                "AmazonAthena.DeleteWorkGroup",
+                handler(crate::operation::delete_work_group),
+            ),
+            (
+                "AmazonAthena.CreateDataCatalog",
+                handler(crate::operation::create_data_catalog),
+            ),
+            (
+                "AmazonAthena.GetDataCatalog",
+                handler(crate::operation::get_data_catalog),
+            ),
+            (
+                "AmazonAthena.ListDataCatalogs",
+                handler(crate::operation::list_data_catalogs),
+            ),
+            (
+                "AmazonAthena.ListDatabases",
+                handler(crate::operation::list_databases),
+            ),
+            (
+                "AmazonAthena.GetDatabase",
+                handler(crate::operation::get_database),
+            ),
+            (
+                "AmazonAthena.ListTableMetadata",
+                handler(crate::operation::list_table_metadata),
+            ),
+            (
+                "AmazonAthena.GetTableMetadata",
+                handler(crate::operation::get_table_metadata),
+            ),
+            (
+                "AmazonAthena.CreatePreparedStatement",
+                handler(crate::operation::create_prepared_statement),
+            ),
+            (
+                "AmazonAthena.GetPreparedStatement",
+                handler(crate::operation::get_prepared_statement),
+            ),
+            (
+                "AmazonAthena.ListPreparedStatements",
+                handler(crate::operation::list_prepared_statements),
+            ),
+            (
+                "AmazonAthena.DeletePreparedStatement",
+                handler(crate::operation::delete_prepared_statement),
+            ),
+            (
+                "AmazonAthena.TagResource",
+                handler(crate::operation::tag_resource),
+            ),
+            (
+                "AmazonAthena.UntagResource",
+                handler(crate::operation::untag_resource),
+            ),
+            (
+                "AmazonAthena.ListTagsForResource",
+                handler(crate::operation::list_tags_for_resource),
+            ),
+            (
+                "AmazonAthenaStreamingService.GetQueryResultsStream",
+                handler(get_query_results_stream),
+            ),
+        ];
+        Registry {
+            handlers: HashMap::from(handlers),
+        }
+    }
+
+    /// Answers the request with the operation of `target`, or an `UnknownOperationException`.
+    pub fn dispatch(
+        &self,
+        target: &str,
+        body: serde_json::Value,
+        data: &crate::model::AppData,
+    ) -> Result<HttpResponse> {
+        match self.handlers.get(target) {
+            Some(_) if throttled(target, data) => {
+                let mut response = crate::operation::error_response(
+                    StatusCode::BAD_REQUEST,
+                    THROTTLING_EXCEPTION,
+                    "Rate exceeded".to_string(),
+                );
+                response.headers_mut().insert(
+                    HeaderName::from_static(RETRY_AFTER_HEADER),
+                    HeaderValue::from(THROTTLING_RETRY_AFTER_SECS),
+                );
+                Ok(response)
+            }
+            Some(handler) => handler(body, data),
+            None => Ok(crate::operation::error_response(
+                StatusCode::BAD_REQUEST,
+                UNKNOWN_OPERATION_EXCEPTION,
+                format!("unexpected target: {:?}", target),
+            )),
+        }
+    }
+}
+
+// Note: with DETERMINISTIC_IDS the generator of each operation is seeded from its name, so the
+// same requests are throttled on every run.
+fn throttled(target: &str, data: &crate::model::AppData) -> bool {
+    if data.throttle_rate <= 0.0 {
+        return false;
+    }
+    let mut throttle_rngs = data.throttle_rngs.lock().unwrap();
+    let rng = throttle_rngs.entry(target.to_string()).or_insert_with(|| {
+        if data.deterministic_throttling {
+            let seed = target
+                .bytes()
+                .fold(0u64, |seed, v| seed.wrapping_mul(31).wrapping_add(v as u64));
+            StdRng::seed_from_u64(seed)
+        } else {
+            StdRng::from_entropy()
+        }
+    });
+    rng.gen_bool(data.throttle_rate)
+}
+
+// Note: the framing of the streaming API of the drivers isn't documented, they page
+// GetQueryResults instead with streaming turned off.
+fn get_query_results_stream(
+    _: &serde::de::IgnoredAny,
+    _: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    Ok(crate::operation::error_response(
+        StatusCode::BAD_REQUEST,
+        UNKNOWN_OPERATION_EXCEPTION,
+        "GetQueryResultsStream is not supported, set UseResultsetStreaming=0 on the JDBC/ODBC driver to fetch the results with GetQueryResults".to_string(),
+    ))
+}
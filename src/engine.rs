@@ -0,0 +1,168 @@
+/// Runs queries for real, so GetQueryResults returns their actual results.
+pub trait QueryEngine: Send + Sync {
+    /// Returns the result of the query, or why it failed.
+    fn execute(&self, query_string: &str) -> Result<crate::model::QueryResult, String>;
+}
+
+/// An in-memory SQLite database, seeded from a SQL file.
+#[cfg(feature = "sqlite")]
+pub struct SqliteEngine {
+    connection: std::sync::Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteEngine {
+    pub fn open(seed_file: &str) -> std::io::Result<Self> {
+        let seed = std::fs::read_to_string(seed_file)?;
+        let connection = rusqlite::Connection::open_in_memory()
+            .and_then(|connection| connection.execute_batch(&seed).map(|_| connection))
+            .map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("failed to seed SQLite from {:}: {:}", seed_file, e),
+                )
+            })?;
+        Ok(SqliteEngine {
+            connection: std::sync::Mutex::new(connection),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl QueryEngine for SqliteEngine {
+    // Note: statements other than queries (DDL, DML) have no columns, so their result is empty.
+    fn execute(&self, query_string: &str) -> Result<crate::model::QueryResult, String> {
+        use rusqlite::types::ValueRef;
+
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare(query_string)
+            .map_err(|e| e.to_string())?;
+        let column_names = statement
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        // Note: expressions have no declared type, so their type follows their first value.
+        let mut column_types = statement
+            .columns()
+            .iter()
+            .map(|v| v.decl_type().map(declared_type))
+            .collect::<Vec<_>>();
+        let mut rows = Vec::new();
+        let mut query = statement.raw_query();
+        while let Some(row) = query.next().map_err(|e| e.to_string())? {
+            let mut values = Vec::new();
+            for (i, column_type) in column_types.iter_mut().enumerate() {
+                let value = row.get_ref(i).map_err(|e| e.to_string())?;
+                let (value_type, value) = match value {
+                    ValueRef::Null => (None, None),
+                    // Note: SQLite has no boolean, it stores them as 0 and 1.
+                    ValueRef::Integer(v) if *column_type == Some("boolean") => {
+                        (Some("boolean"), Some((v != 0).to_string()))
+                    }
+                    ValueRef::Integer(v) => (Some("bigint"), Some(v.to_string())),
+                    ValueRef::Real(v) => (Some("double"), Some(v.to_string())),
+                    ValueRef::Text(v) => (
+                        Some("varchar"),
+                        Some(String::from_utf8_lossy(v).to_string()),
+                    ),
+                    ValueRef::Blob(v) => (
+                        Some("varbinary"),
+                        Some(String::from_utf8_lossy(v).to_string()),
+                    ),
+                };
+                *column_type = column_type.or(value_type);
+                values.push(value);
+            }
+            rows.push(values);
+        }
+        let columns = column_names
+            .into_iter()
+            .zip(column_types)
+            .map(|(name, column_type)| crate::model::Column {
+                name,
+                column_type: column_type
+                    .unwrap_or(crate::model::VARCHAR_TYPE)
+                    .to_string(),
+            })
+            .collect();
+        Ok(crate::model::QueryResult { columns, rows })
+    }
+}
+
+// Note: follows the type affinity rules of SQLite, with the Athena types it can hold.
+#[cfg(feature = "sqlite")]
+fn declared_type(decl_type: &str) -> &'static str {
+    let decl_type = decl_type.to_uppercase();
+    if decl_type.contains("BOOL") {
+        "boolean"
+    } else if decl_type.contains("INT") {
+        "bigint"
+    } else if decl_type.contains("CHAR") || decl_type.contains("CLOB") || decl_type.contains("TEXT")
+    {
+        "varchar"
+    } else if decl_type.contains("BLOB") {
+        "varbinary"
+    } else if decl_type.starts_with("DATETIME") || decl_type.starts_with("TIMESTAMP") {
+        "timestamp"
+    } else if decl_type.starts_with("DATE") {
+        "date"
+    } else if decl_type.contains("REAL")
+        || decl_type.contains("FLOA")
+        || decl_type.contains("DOUB")
+        || decl_type.contains("NUMERIC")
+        || decl_type.contains("DECIMAL")
+    {
+        "double"
+    } else {
+        "varchar"
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sqlite_columns_have_the_athena_types_of_their_declared_types_or_values() {
+        let seed_file = std::env::temp_dir().join(format!("minerva-{:}.sql", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &seed_file,
+            "CREATE TABLE t (id INTEGER, name VARCHAR(10), price DECIMAL(10,2), active BOOLEAN, \
+             created_at DATETIME, born_on DATE, data BLOB);
+             INSERT INTO t VALUES (1, 'foo', 1.5, 1, '2022-01-01 00:00:00', '2022-01-01', x'00');
+             INSERT INTO t VALUES (2, 'bar', 2.5, 0, '2022-01-02 00:00:00', '2022-01-02', x'01');",
+        )
+        .unwrap();
+        let engine = SqliteEngine::open(seed_file.to_str().unwrap()).unwrap();
+
+        let result = engine
+            .execute("SELECT *, id * 2.5 AS score, NULL AS missing FROM t")
+            .unwrap();
+        let column_types = result
+            .columns
+            .iter()
+            .map(|v| (v.name.as_str(), v.column_type.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            column_types,
+            [
+                ("id", "bigint"),
+                ("name", "varchar"),
+                ("price", "double"),
+                ("active", "boolean"),
+                ("created_at", "timestamp"),
+                ("born_on", "date"),
+                ("data", "varbinary"),
+                ("score", "double"),
+                ("missing", "varchar"),
+            ]
+        );
+        assert_eq!(result.rows[0][0].as_deref(), Some("1"));
+        assert_eq!(result.rows[0][3].as_deref(), Some("true"));
+        assert_eq!(result.rows[1][3].as_deref(), Some("false"));
+        assert_eq!(result.rows[0][8], None);
+        std::fs::remove_file(seed_file).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+use actix_web::{web, HttpResponse};
+use aws_sdk_athena::model::QueryExecutionState;
+use futures::channel::mpsc;
+use futures::StreamExt;
+use std::sync::Mutex;
+
+// Note: events beyond this are dropped for a subscriber which doesn't keep up.
+const SUBSCRIBER_BUFFER_SIZE: usize = 256;
+
+const EVENT_START_QUERY_EXECUTION: &str = "StartQueryExecution";
+const EVENT_STATE_CHANGE: &str = "StateChange";
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StateChangeEvent<'a> {
+    query_execution_id: &'a str,
+    previous_state: Option<&'a str>,
+    state: &'a str,
+    timestamp: f64,
+}
+
+/// Fans out query state transitions to the `GET /admin/events` subscribers.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<mpsc::Sender<String>>>,
+}
+
+impl EventBus {
+    pub fn publish(
+        &self,
+        query_execution_id: &str,
+        previous_state: Option<QueryExecutionState>,
+        state: &str,
+        timestamp: u64,
+    ) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        if subscribers.is_empty() {
+            return;
+        }
+        let event = StateChangeEvent {
+            query_execution_id,
+            previous_state: previous_state.as_ref().map(|v| v.as_str()),
+            state,
+            timestamp: timestamp as f64 / 1000.0,
+        };
+        let message = format!(
+            "event: {:}\ndata: {:}\n\n",
+            if previous_state.is_none() {
+                EVENT_START_QUERY_EXECUTION
+            } else {
+                EVENT_STATE_CHANGE
+            },
+            serde_json::to_string(&event).unwrap_or_default()
+        );
+        // Note: never wait for a subscriber, a full buffer drops the event
+        // and a disconnected subscriber is forgotten.
+        subscribers.retain_mut(|subscriber| match subscriber.try_send(message.clone()) {
+            Ok(()) => true,
+            Err(e) => e.is_full(),
+        });
+    }
+
+    fn subscribe(&self) -> mpsc::Receiver<String> {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+pub async fn events(data: web::Data<crate::model::AppData>) -> HttpResponse {
+    let receiver = data.events.subscribe();
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .streaming(receiver.map(|message| Ok::<_, actix_web::Error>(web::Bytes::from(message))))
+}
@@ -0,0 +1,95 @@
+/// Columns of a generated result, `c0`..`cN`: a `bigint` for even columns, a `varchar` for odd ones.
+pub fn columns(cols: usize) -> Vec<crate::model::Column> {
+    (0..cols)
+        .map(|c| crate::model::Column {
+            name: format!("c{:}", c),
+            column_type: if c % 2 == 0 { "bigint" } else { "varchar" }.to_string(),
+        })
+        .collect()
+}
+
+/// Rows from `offset` on, made up from their index `i`: `i * (n + 1)` for an even column `cn`
+/// (so `c0` is `i` itself), and like `row-42-c1` for an odd one.
+// Note: each row is made up on its own, so a page doesn't need the rows before it.
+pub fn rows(rows: u64, cols: usize, offset: u64) -> impl Iterator<Item = crate::model::Record> {
+    (offset..rows).map(move |i| {
+        (0..cols)
+            .map(|c| {
+                Some(if c % 2 == 0 {
+                    i.wrapping_mul(c as u64 + 1).to_string()
+                } else {
+                    format!("row-{:}-c{:}", i, c)
+                })
+            })
+            .collect()
+    })
+}
+
+/// Total length of the values of `rows(rows, cols, 0)`, counted without making up the rows.
+pub fn byte_count(rows: u64, cols: usize) -> u64 {
+    (0..cols as u64)
+        .map(|c| {
+            if c % 2 == 0 {
+                digit_count(rows, c + 1)
+            } else {
+                // Note: `row-` and `-c` around the row index, then the column index.
+                rows * (6 + decimal_len(c)) + digit_count(rows, 1)
+            }
+        })
+        .sum()
+}
+
+// Note: the digits of `i * step` for each `i` below `rows`, each of them has one digit plus one
+// more for each power of ten it reaches.
+fn digit_count(rows: u64, step: u64) -> u64 {
+    let mut count = rows;
+    let mut power = 10u64;
+    loop {
+        let first = power.div_ceil(step);
+        if first >= rows {
+            return count;
+        }
+        count += rows - first;
+        match power.checked_mul(10) {
+            Some(v) => power = v,
+            None => return count,
+        }
+    }
+}
+
+fn decimal_len(value: u64) -> u64 {
+    value.to_string().len() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_count_is_the_length_of_the_values_of_the_rows() {
+        for total in [0, 1, 9, 10, 11, 99, 100, 101, 999, 1000, 1001, 12345] {
+            for cols in [0, 1, 2, 3, 8, 12] {
+                let expected = rows(total, cols, 0)
+                    .flatten()
+                    .map(|v| v.unwrap().len() as u64)
+                    .sum::<u64>();
+                assert_eq!(byte_count(total, cols), expected, "{:} x {:}", total, cols);
+            }
+        }
+    }
+
+    #[test]
+    fn rows_from_an_offset_are_the_rest_of_all_the_rows() {
+        let all = rows(25, 3, 0).collect::<Vec<_>>();
+        assert_eq!(rows(25, 3, 10).collect::<Vec<_>>(), all[10..]);
+        assert_eq!(rows(25, 3, 25).count(), 0);
+        assert_eq!(
+            all[7],
+            vec![
+                Some("7".to_string()),
+                Some("row-7-c1".to_string()),
+                Some("21".to_string())
+            ]
+        );
+    }
+}
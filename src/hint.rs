@@ -0,0 +1,247 @@
+use crate::model::StateDurations;
+use std::time::Duration;
+
+const HINT_PREFIX: &str = "minerva:";
+
+const DEFAULT_GENERATED_ROWS: u64 = 1000;
+const DEFAULT_GENERATED_COLS: usize = 4;
+
+/// Per-query settings embedded in the query string, e.g. `-- minerva: queued=2s running=30s`.
+/// Malformed values are ignored.
+#[derive(Debug, Default)]
+pub struct Hint {
+    pub delay: Option<Duration>,
+    pub queued: Option<Duration>,
+    pub running: Option<Duration>,
+    pub outcome: Option<String>,
+    pub data_scanned_in_bytes: Option<u64>,
+    pub update_count: Option<u64>,
+    pub generate: bool,
+    pub rows: Option<u64>,
+    pub cols: Option<usize>,
+    pub error_category: Option<u64>,
+    pub error_type: Option<u64>,
+    pub retryable: Option<bool>,
+    pub error_message: Option<String>,
+}
+
+impl Hint {
+    pub fn parse(query_string: &str) -> Self {
+        let mut hint = Hint::default();
+        for (key, value) in directives(query_string) {
+            let duration = value.as_deref().and_then(parse_duration);
+            match key.as_str() {
+                "delay" => hint.delay = duration.or(hint.delay),
+                "queued" => hint.queued = duration.or(hint.queued),
+                "running" => hint.running = duration.or(hint.running),
+                "data_scanned" => {
+                    hint.data_scanned_in_bytes = value
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .or(hint.data_scanned_in_bytes)
+                }
+                "generate" => hint.generate = true,
+                "rows" => hint.rows = value.and_then(|v| v.parse::<u64>().ok()).or(hint.rows),
+                "cols" => {
+                    hint.cols = value
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .filter(|v| *v > 0)
+                        .or(hint.cols)
+                }
+                "update_count" => {
+                    hint.update_count = value
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .or(hint.update_count)
+                }
+                "outcome" => hint.outcome = value.map(|v| v.to_uppercase()).or(hint.outcome),
+                "error_category" => {
+                    hint.error_category = value
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .filter(|v| (1..=3).contains(v))
+                        .or(hint.error_category)
+                }
+                "error_type" => {
+                    hint.error_type = value
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .or(hint.error_type)
+                }
+                "retryable" => {
+                    hint.retryable = value
+                        .and_then(|v| v.parse::<bool>().ok())
+                        .or(hint.retryable)
+                }
+                "error_message" => hint.error_message = value.or(hint.error_message),
+                _ => {}
+            }
+        }
+        hint
+    }
+
+    /// Rows and columns of the result to generate, e.g. `-- minerva:generate rows=1000000 cols=8`.
+    pub fn generated(&self) -> Option<(u64, usize)> {
+        self.generate.then(|| {
+            (
+                self.rows.unwrap_or(DEFAULT_GENERATED_ROWS),
+                self.cols.unwrap_or(DEFAULT_GENERATED_COLS),
+            )
+        })
+    }
+
+    pub fn state_durations(&self, default: StateDurations) -> StateDurations {
+        StateDurations {
+            queued: self.queued.or(self.delay).unwrap_or(default.queued),
+            running: self.running.or(self.delay).unwrap_or(default.running),
+        }
+    }
+}
+
+fn directives(query_string: &str) -> Vec<(String, Option<String>)> {
+    let mut directives = Vec::new();
+    for line in query_string.lines() {
+        let mut rest = line;
+        while let Some(i) = rest.find(HINT_PREFIX) {
+            rest = &rest[i + HINT_PREFIX.len()..];
+            let end = rest.find("*/").unwrap_or(rest.len());
+            directives.extend(tokens(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    directives
+}
+
+// Note: values may be double-quoted to contain whitespace, e.g. `error_message="table not found"`.
+fn tokens(directive: &str) -> Vec<(String, Option<String>)> {
+    let mut tokens = Vec::new();
+    let mut rest = directive.trim_start();
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = rest[..key_end].to_lowercase();
+        rest = &rest[key_end..];
+        let value = match rest.strip_prefix('=') {
+            Some(value) => match value.strip_prefix('"') {
+                Some(quoted) => {
+                    let value_end = quoted.find('"').unwrap_or(quoted.len());
+                    rest = quoted.get(value_end + 1..).unwrap_or("");
+                    Some(quoted[..value_end].to_string())
+                }
+                None => {
+                    let value_end = value.find(char::is_whitespace).unwrap_or(value.len());
+                    rest = &value[value_end..];
+                    Some(value[..value_end].to_string())
+                }
+            },
+            None => None,
+        };
+        tokens.push((key, value));
+        rest = rest.trim_start();
+    }
+    tokens
+}
+
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    if let Some(millis) = value.strip_suffix("ms") {
+        millis.parse::<u64>().ok().map(Duration::from_millis)
+    } else {
+        value
+            .strip_suffix('s')
+            .unwrap_or(value)
+            .parse::<u64>()
+            .ok()
+            .map(Duration::from_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULT: StateDurations = StateDurations {
+        queued: Duration::from_secs(1),
+        running: Duration::from_secs(1),
+    };
+
+    #[test]
+    fn parse_reads_durations_in_seconds() {
+        let hint = Hint::parse("SELECT * FROM users -- minerva: running=30s queued=2");
+        assert_eq!(hint.queued, Some(Duration::from_secs(2)));
+        assert_eq!(hint.running, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn parse_reads_durations_in_milliseconds() {
+        let hint = Hint::parse("/* minerva: queued=250ms running=1500ms */ SELECT * FROM users");
+        assert_eq!(hint.queued, Some(Duration::from_millis(250)));
+        assert_eq!(hint.running, Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn parse_leaves_missing_values_unset() {
+        let hint = Hint::parse("SELECT * FROM users -- minerva: running=30s");
+        assert_eq!(hint.queued, None);
+        assert_eq!(hint.running, Some(Duration::from_secs(30)));
+
+        let hint = Hint::parse("SELECT * FROM users");
+        assert_eq!(hint.queued, None);
+        assert_eq!(hint.running, None);
+        assert_eq!(hint.state_durations(DEFAULT), DEFAULT);
+    }
+
+    #[test]
+    fn parse_ignores_malformed_values() {
+        let hint = Hint::parse("SELECT * FROM users -- minerva: queued=soon running= delay=-1s");
+        assert_eq!(hint.queued, None);
+        assert_eq!(hint.running, None);
+        assert_eq!(hint.delay, None);
+        assert_eq!(hint.state_durations(DEFAULT), DEFAULT);
+    }
+
+    #[test]
+    fn state_durations_fall_back_to_the_delay_then_the_default() {
+        let hint = Hint::parse("SELECT * FROM users -- minerva: delay=3s running=30s");
+        assert_eq!(
+            hint.state_durations(DEFAULT),
+            StateDurations {
+                queued: Duration::from_secs(3),
+                running: Duration::from_secs(30),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_reads_the_athena_error_overrides() {
+        let hint = Hint::parse(
+            r#"SELECT 1 -- minerva:fail error_category=1 error_type=1001 retryable=true error_message="table not found""#,
+        );
+        assert_eq!(hint.error_category, Some(1));
+        assert_eq!(hint.error_type, Some(1001));
+        assert_eq!(hint.retryable, Some(true));
+        assert_eq!(hint.error_message.as_deref(), Some("table not found"));
+
+        // Note: categories are 1 (system), 2 (user) and 3 (other) only.
+        let hint = Hint::parse("SELECT 1 -- minerva: error_category=4 retryable=maybe");
+        assert_eq!(hint.error_category, None);
+        assert_eq!(hint.retryable, None);
+    }
+
+    #[test]
+    fn generated_reads_the_rows_and_columns_or_their_defaults() {
+        let hint = Hint::parse("SELECT * FROM big -- minerva:generate rows=1000000 cols=8");
+        assert_eq!(hint.generated(), Some((1000000, 8)));
+
+        let hint = Hint::parse("/* minerva:generate */ SELECT * FROM big");
+        assert_eq!(
+            hint.generated(),
+            Some((DEFAULT_GENERATED_ROWS, DEFAULT_GENERATED_COLS))
+        );
+
+        let hint = Hint::parse("SELECT * FROM big -- minerva:generate rows=many cols=0");
+        assert_eq!(
+            hint.generated(),
+            Some((DEFAULT_GENERATED_ROWS, DEFAULT_GENERATED_COLS))
+        );
+
+        let hint = Hint::parse("SELECT * FROM big -- minerva: rows=10 cols=2");
+        assert_eq!(hint.generated(), None);
+    }
+}
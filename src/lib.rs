@@ -0,0 +1,1246 @@
+mod admin;
+mod clock;
+mod dispatch;
+mod engine;
+mod events;
+mod generate;
+mod hint;
+mod metadata;
+mod model;
+mod operation;
+mod output;
+mod s3;
+mod statement;
+mod syntax;
+mod transition;
+#[cfg(feature = "s3")]
+mod upload;
+mod webhook;
+
+use actix_web::dev::{Server, Service};
+use actix_web::error::InternalError;
+use actix_web::http::{HeaderName, HeaderValue, StatusCode};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Result};
+use clock::Clock;
+use futures::future::AbortHandle;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use regex::Regex;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::SocketAddr;
+use std::ops::Deref;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{field, Instrument};
+use uuid::Uuid;
+
+pub use transition::{
+    DefaultTransitionPlan, LinearTransitionPlan, TransitionContext, TransitionPlan,
+};
+
+const OPERATION_TARGET_HEADER: &str = "X-Amz-Target";
+const OPERATION_TARGET_PREFIX: &str = "AmazonAthena.";
+const REQUEST_ID_HEADER: &str = "x-amzn-requestid";
+
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:5050";
+const DEFAULT_PROCESS_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_CSV_FIXTURE_DIR: &str = ".";
+const DEFAULT_FAIL_QUERY_MARKER: &str = "-- minerva:fail";
+// Note: OutputLocation of queries which don't give one, unless OUTPUT_LOCATION is set.
+const DEFAULT_OUTPUT_LOCATION: &str = "s3://minerva-results/";
+// Note: the database of queries which give none, as Athena takes it.
+pub(crate) const DEFAULT_DATABASE: &str = "default";
+const DEFAULT_ENGINE_VERSION: &str = "Athena engine version 3";
+const DEFAULT_QUERY_TIMEOUT_MESSAGE: &str = "Query exhausted resources at this scale factor";
+
+// Note: a user error (category 2), like a SYNTAX_ERROR reported by Athena.
+const DEFAULT_ERROR_CATEGORY: u64 = 2;
+const DEFAULT_ERROR_TYPE: u64 = 1000;
+
+async fn root(
+    req: HttpRequest,
+    body: web::Json<serde_json::Value>,
+    data: web::Data<crate::model::AppData>,
+    registry: web::Data<dispatch::Registry>,
+) -> Result<HttpResponse> {
+    let target = req.headers().get(OPERATION_TARGET_HEADER).ok_or_else(|| {
+        operation::error_response(
+            StatusCode::BAD_REQUEST,
+            dispatch::UNKNOWN_OPERATION_EXCEPTION,
+            format!("'{:}' not found", OPERATION_TARGET_HEADER),
+        )
+    })?;
+    // Note: recorded on the span of the request, which is logged once it has been handled.
+    let span = tracing::Span::current();
+    let target_name = target.to_str().unwrap_or_default();
+    span.record("target", &field::display(target_name));
+    if let Some(operation) = target_name.strip_prefix(OPERATION_TARGET_PREFIX) {
+        span.record("operation", &field::display(operation));
+    }
+    if let Some(query_execution_id) = body.get("QueryExecutionId").and_then(|v| v.as_str()) {
+        span.record("query_execution_id", &field::display(query_execution_id));
+    }
+    tracing::debug!(body = %body.deref(), "request body");
+
+    registry.dispatch(target_name, body.into_inner(), data.get_ref())
+}
+
+// Note: a liveness probe, independent of the Athena operations dispatched by `root`.
+async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+/// An embeddable Minerva server, e.g.
+/// `MinervaServer::builder().process_interval(d).bind("127.0.0.1:0").start()`.
+pub struct MinervaServer;
+
+impl MinervaServer {
+    pub fn builder() -> MinervaServerBuilder {
+        MinervaServerBuilder::default()
+    }
+}
+
+pub struct MinervaServerBuilder {
+    bind_address: String,
+    process_interval: Duration,
+    queued_duration: Option<Duration>,
+    running_duration: Option<Duration>,
+    transition_plan: Arc<dyn TransitionPlan>,
+    immediate: bool,
+    validate_sql: bool,
+    virtual_clock: bool,
+    query_ttl: Option<Duration>,
+    query_retention: Option<Duration>,
+    query_timeout: Option<Duration>,
+    query_timeout_message: String,
+    csv_fixture_dir: String,
+    fixtures_dir: Option<String>,
+    databases: Vec<String>,
+    databases_file: Option<String>,
+    data_scanned_in_bytes: Option<u64>,
+    include_header_row: bool,
+    output_dir: Option<String>,
+    default_output_location: String,
+    result_encryption_option: Option<String>,
+    result_kms_key: Option<String>,
+    output_metadata: bool,
+    deterministic_ids: bool,
+    engine_version: String,
+    max_concurrent_queries: Option<usize>,
+    max_running_queries: Option<usize>,
+    max_tracked_queries: Option<usize>,
+    completion_webhook_url: Option<String>,
+    #[cfg(feature = "sqlite")]
+    sqlite_seed_file: Option<String>,
+    #[cfg(feature = "s3")]
+    result_s3_endpoint: Option<String>,
+    #[cfg(feature = "s3")]
+    result_s3_bucket: Option<String>,
+    fail_query_marker: String,
+    fail_query_pattern: Option<Regex>,
+    failure: crate::model::FailureSettings,
+    chaos_failure_rate: f64,
+    chaos_seed: Option<u64>,
+    throttle_rate: f64,
+}
+
+impl Default for MinervaServerBuilder {
+    fn default() -> Self {
+        MinervaServerBuilder {
+            bind_address: DEFAULT_BIND_ADDRESS.to_string(),
+            process_interval: DEFAULT_PROCESS_INTERVAL,
+            queued_duration: None,
+            running_duration: None,
+            transition_plan: Arc::new(DefaultTransitionPlan),
+            immediate: false,
+            validate_sql: true,
+            virtual_clock: false,
+            query_ttl: None,
+            query_retention: None,
+            query_timeout: None,
+            query_timeout_message: DEFAULT_QUERY_TIMEOUT_MESSAGE.to_string(),
+            csv_fixture_dir: DEFAULT_CSV_FIXTURE_DIR.to_string(),
+            fixtures_dir: None,
+            databases: vec![DEFAULT_DATABASE.to_string()],
+            databases_file: None,
+            data_scanned_in_bytes: None,
+            include_header_row: true,
+            output_dir: None,
+            default_output_location: DEFAULT_OUTPUT_LOCATION.to_string(),
+            result_encryption_option: None,
+            result_kms_key: None,
+            output_metadata: true,
+            deterministic_ids: false,
+            engine_version: DEFAULT_ENGINE_VERSION.to_string(),
+            max_concurrent_queries: None,
+            max_running_queries: None,
+            max_tracked_queries: None,
+            completion_webhook_url: None,
+            #[cfg(feature = "sqlite")]
+            sqlite_seed_file: None,
+            #[cfg(feature = "s3")]
+            result_s3_endpoint: None,
+            #[cfg(feature = "s3")]
+            result_s3_bucket: None,
+            fail_query_marker: DEFAULT_FAIL_QUERY_MARKER.to_string(),
+            fail_query_pattern: None,
+            failure: crate::model::FailureSettings {
+                error_category: DEFAULT_ERROR_CATEGORY,
+                error_type: DEFAULT_ERROR_TYPE,
+                retryable: false,
+                error_message: None,
+            },
+            chaos_failure_rate: 0.0,
+            chaos_seed: None,
+            throttle_rate: 0.0,
+        }
+    }
+}
+
+impl MinervaServerBuilder {
+    /// Address to listen on, use port `0` to pick a free port.
+    pub fn bind(mut self, bind_address: impl Into<String>) -> Self {
+        self.bind_address = bind_address.into();
+        self
+    }
+
+    /// Default duration of both `QUEUED` and `RUNNING`.
+    pub fn process_interval(mut self, process_interval: Duration) -> Self {
+        self.process_interval = process_interval;
+        self
+    }
+
+    pub fn queued_duration(mut self, queued_duration: Duration) -> Self {
+        self.queued_duration = Some(queued_duration);
+        self
+    }
+
+    pub fn running_duration(mut self, running_duration: Duration) -> Self {
+        self.running_duration = Some(running_duration);
+        self
+    }
+
+    /// Lifecycle of the queries, `QUEUED` → `RUNNING` → the outcome by default.
+    pub fn transition_plan(mut self, transition_plan: impl TransitionPlan + 'static) -> Self {
+        self.transition_plan = Arc::new(transition_plan);
+        self
+    }
+
+    /// Number the QueryExecutionIds `query-0001`, `query-0002`, ... instead of random UUIDs,
+    /// only meant for tests which compare them.
+    pub fn deterministic_ids(mut self, deterministic_ids: bool) -> Self {
+        self.deterministic_ids = deterministic_ids;
+        self
+    }
+
+    /// Record queries in their final state as soon as they are started.
+    pub fn immediate(mut self, immediate: bool) -> Self {
+        self.immediate = immediate;
+        self
+    }
+
+    /// Fail queries which don't parse, `true` by default.
+    pub fn validate_sql(mut self, validate_sql: bool) -> Self {
+        self.validate_sql = validate_sql;
+        self
+    }
+
+    /// Drive state transitions by a clock which only moves on `POST /admin/advance`.
+    pub fn virtual_clock(mut self, virtual_clock: bool) -> Self {
+        self.virtual_clock = virtual_clock;
+        self
+    }
+
+    /// Forget finished queries submitted longer ago than this.
+    pub fn query_ttl(mut self, query_ttl: Duration) -> Self {
+        self.query_ttl = Some(query_ttl);
+        self
+    }
+
+    /// Forget queries which finished longer ago than this.
+    pub fn query_retention(mut self, query_retention: Duration) -> Self {
+        self.query_retention = Some(query_retention);
+        self
+    }
+
+    /// Fail queries which haven't finished this long after they were submitted.
+    pub fn query_timeout(mut self, query_timeout: Duration) -> Self {
+        self.query_timeout = Some(query_timeout);
+        self
+    }
+
+    pub fn query_timeout_message(mut self, query_timeout_message: impl Into<String>) -> Self {
+        self.query_timeout_message = query_timeout_message.into();
+        self
+    }
+
+    pub fn csv_fixture_dir(mut self, csv_fixture_dir: impl Into<String>) -> Self {
+        self.csv_fixture_dir = csv_fixture_dir.into();
+        self
+    }
+
+    /// Pick the result of each query from the fixtures in this directory, keyed by the query
+    /// string or listed in its `mappings.json`.
+    pub fn fixtures_dir(mut self, fixtures_dir: impl Into<String>) -> Self {
+        self.fixtures_dir = Some(fixtures_dir.into());
+        self
+    }
+
+    /// The databases of `AwsDataCatalog`, `default` only by default.
+    pub fn databases(mut self, databases: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.databases = databases.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Also seed the databases of this json file, like `[{"Name": "sales", "Description": "..."}]`.
+    pub fn databases_file(mut self, databases_file: impl Into<String>) -> Self {
+        self.databases_file = Some(databases_file.into());
+        self
+    }
+
+    pub fn data_scanned_in_bytes(mut self, data_scanned_in_bytes: u64) -> Self {
+        self.data_scanned_in_bytes = Some(data_scanned_in_bytes);
+        self
+    }
+
+    /// Return the column names as the first row of the first `GetQueryResults` page, as Athena
+    /// does, `true` by default.
+    pub fn include_header_row(mut self, include_header_row: bool) -> Self {
+        self.include_header_row = include_header_row;
+        self
+    }
+
+    /// Write the result of each succeeded query under this directory, where its OutputLocation
+    /// `s3://bucket/prefix/{id}.csv` is `{output_dir}/bucket/prefix/{id}.csv`.
+    pub fn output_dir(mut self, output_dir: impl Into<String>) -> Self {
+        self.output_dir = Some(output_dir.into());
+        self
+    }
+
+    /// OutputLocation of the queries which give none, under which their result is written to
+    /// `output_dir` if any, `s3://minerva-results/` by default.
+    pub fn default_output_location(mut self, default_output_location: impl Into<String>) -> Self {
+        self.default_output_location = default_output_location.into();
+        self
+    }
+
+    /// `EncryptionConfiguration.EncryptionOption` reported for the queries which give none,
+    /// `SSE_S3`, `SSE_KMS` or `CSE_KMS`.
+    pub fn result_encryption_option(mut self, result_encryption_option: impl Into<String>) -> Self {
+        self.result_encryption_option = Some(result_encryption_option.into());
+        self
+    }
+
+    /// With `result_encryption_option`, the `EncryptionConfiguration.KmsKey` reported for the
+    /// queries which give none.
+    pub fn result_kms_key(mut self, result_kms_key: impl Into<String>) -> Self {
+        self.result_kms_key = Some(result_kms_key.into());
+        self
+    }
+
+    /// Write a `.metadata` file next to each result file, `true` by default.
+    pub fn output_metadata(mut self, output_metadata: bool) -> Self {
+        self.output_metadata = output_metadata;
+        self
+    }
+
+    /// `EngineVersion` reported for queries which don't select one.
+    pub fn engine_version(mut self, engine_version: impl Into<String>) -> Self {
+        self.engine_version = engine_version.into();
+        self
+    }
+
+    pub fn max_concurrent_queries(mut self, max_concurrent_queries: usize) -> Self {
+        self.max_concurrent_queries = Some(max_concurrent_queries);
+        self
+    }
+
+    /// At most this many queries are `RUNNING` at once, the rest wait in `QUEUED`.
+    pub fn max_running_queries(mut self, max_running_queries: usize) -> Self {
+        self.max_running_queries = Some(max_running_queries);
+        self
+    }
+
+    /// Keep at most this many queries, evicting the oldest finished ones first.
+    pub fn max_tracked_queries(mut self, max_tracked_queries: usize) -> Self {
+        self.max_tracked_queries = Some(max_tracked_queries);
+        self
+    }
+
+    /// POST an `Athena Query State Change` like event here when a query finishes.
+    pub fn completion_webhook_url(mut self, completion_webhook_url: impl Into<String>) -> Self {
+        self.completion_webhook_url = Some(completion_webhook_url.into());
+        self
+    }
+
+    /// Run the queries on an in-memory SQLite database, seeded by this SQL file.
+    #[cfg(feature = "sqlite")]
+    pub fn sqlite_seed_file(mut self, sqlite_seed_file: impl Into<String>) -> Self {
+        self.sqlite_seed_file = Some(sqlite_seed_file.into());
+        self
+    }
+
+    /// Upload the result of each succeeded query to this S3-compatible endpoint, e.g.
+    /// `http://localhost:9000`, and report its object as the OutputLocation; a failed upload
+    /// fails the query.
+    #[cfg(feature = "s3")]
+    pub fn result_s3_endpoint(mut self, result_s3_endpoint: impl Into<String>) -> Self {
+        self.result_s3_endpoint = Some(result_s3_endpoint.into());
+        self
+    }
+
+    /// With `result_s3_endpoint`, the bucket the results are uploaded to instead of the one of
+    /// the OutputLocation.
+    #[cfg(feature = "s3")]
+    pub fn result_s3_bucket(mut self, result_s3_bucket: impl Into<String>) -> Self {
+        self.result_s3_bucket = Some(result_s3_bucket.into());
+        self
+    }
+
+    pub fn fail_query_marker(mut self, fail_query_marker: impl Into<String>) -> Self {
+        self.fail_query_marker = fail_query_marker.into();
+        self
+    }
+
+    pub fn fail_query_pattern(mut self, fail_query_pattern: Regex) -> Self {
+        self.fail_query_pattern = Some(fail_query_pattern);
+        self
+    }
+
+    pub fn failure_error_category(mut self, error_category: u64) -> Self {
+        self.failure.error_category = error_category;
+        self
+    }
+
+    pub fn failure_error_type(mut self, error_type: u64) -> Self {
+        self.failure.error_type = error_type;
+        self
+    }
+
+    pub fn failure_retryable(mut self, retryable: bool) -> Self {
+        self.failure.retryable = retryable;
+        self
+    }
+
+    pub fn failure_error_message(mut self, error_message: impl Into<String>) -> Self {
+        self.failure.error_message = Some(error_message.into());
+        self
+    }
+
+    /// Probability that a query ends in `FAILED`, clamped to `0.0`–`1.0`.
+    pub fn chaos_failure_rate(mut self, chaos_failure_rate: f64) -> Self {
+        self.chaos_failure_rate = chaos_failure_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn chaos_seed(mut self, chaos_seed: u64) -> Self {
+        self.chaos_seed = Some(chaos_seed);
+        self
+    }
+
+    /// Probability that a request is rejected with a `ThrottlingException`, clamped to
+    /// `0.0`–`1.0`.
+    pub fn throttle_rate(mut self, throttle_rate: f64) -> Self {
+        self.throttle_rate = throttle_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    // Note: the state the operations share, built apart from the listener so the tests can call
+    // the operations on it.
+    pub(crate) fn app_data(self) -> io::Result<crate::model::AppData> {
+        let processes = Arc::new(RwLock::new(HashMap::new()));
+        let clock: Arc<dyn crate::clock::Clock> = if self.virtual_clock {
+            Arc::new(crate::clock::VirtualClock::new(
+                crate::clock::SystemClock.now_millis(),
+            ))
+        } else {
+            Arc::new(crate::clock::SystemClock)
+        };
+        #[cfg(feature = "sqlite")]
+        let engine = match &self.sqlite_seed_file {
+            Some(sqlite_seed_file) => Some(Arc::new(crate::engine::SqliteEngine::open(
+                sqlite_seed_file,
+            )?) as Arc<dyn crate::engine::QueryEngine>),
+            None => None,
+        };
+        #[cfg(not(feature = "sqlite"))]
+        let engine = None;
+        #[cfg(feature = "s3")]
+        let result_uploader = match &self.result_s3_endpoint {
+            Some(result_s3_endpoint) => Some(Arc::new(crate::upload::ResultUploader::new(
+                result_s3_endpoint,
+                self.result_s3_bucket.clone(),
+            )?)),
+            None => None,
+        };
+        let default_encryption_configuration =
+            self.result_encryption_option
+                .as_ref()
+                .map(|v| crate::model::EncryptionConfiguration {
+                    encryption_option: v.clone(),
+                    kms_key: self.result_kms_key.clone(),
+                });
+        if let Some(message) = default_encryption_configuration
+            .as_ref()
+            .and_then(|v| operation::encryption_configuration_error(v, "resultConfiguration"))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid default result encryption: {:}", message),
+            ));
+        }
+        let result_fixtures = self
+            .fixtures_dir
+            .as_deref()
+            .map(crate::model::fixtures::ResultFixtures::load)
+            .transpose()?
+            .map(Arc::new);
+
+        let mut databases = self
+            .databases
+            .iter()
+            .map(|name| crate::model::fixtures::DatabaseFixture {
+                catalog_name: None,
+                name: name.clone(),
+                database: Default::default(),
+            })
+            .collect::<Vec<_>>();
+        if let Some(databases_file) = &self.databases_file {
+            databases.extend(crate::model::fixtures::read_database_fixtures(
+                databases_file,
+            )?);
+        }
+
+        let work_groups = operation::initial_work_groups(&self.engine_version, clock.now_millis());
+        Ok(crate::model::AppData {
+            immediate: self.immediate,
+            validate_sql: self.validate_sql,
+            state_durations: crate::model::StateDurations {
+                queued: self.queued_duration.unwrap_or(self.process_interval),
+                running: self.running_duration.unwrap_or(self.process_interval),
+            },
+            transition_plan: self.transition_plan,
+            csv_fixture_dir: self.csv_fixture_dir,
+            fail_query_marker: self.fail_query_marker,
+            fail_query_pattern: self.fail_query_pattern,
+            failure: self.failure,
+            chaos_failure_rate: self.chaos_failure_rate,
+            chaos_rng: Arc::new(Mutex::new(
+                self.chaos_seed
+                    .map(StdRng::seed_from_u64)
+                    .unwrap_or_else(StdRng::from_entropy),
+            )),
+            query_timeout: self.query_timeout,
+            query_timeout_message: self.query_timeout_message,
+            throttle_rate: self.throttle_rate,
+            throttle_rngs: Arc::new(Mutex::new(HashMap::new())),
+            deterministic_throttling: self.deterministic_ids,
+            data_scanned_in_bytes: self.data_scanned_in_bytes,
+            include_header_row: self.include_header_row,
+            output_dir: self.output_dir,
+            default_output_location: self.default_output_location,
+            default_encryption_configuration,
+            output_metadata: self.output_metadata,
+            query_execution_id_counter: self.deterministic_ids.then(|| Arc::new(AtomicU64::new(0))),
+            engine_version: self.engine_version,
+            max_concurrent_queries: self.max_concurrent_queries,
+            max_running_queries: self.max_running_queries,
+            max_tracked_queries: self.max_tracked_queries,
+            completion_webhook_url: self.completion_webhook_url,
+            engine,
+            result_fixtures,
+            clock,
+            events: Arc::new(crate::events::EventBus::default()),
+            processes,
+            process_handles: Arc::new(Mutex::new(HashMap::new())),
+            client_request_tokens: Arc::new(Mutex::new(HashMap::new())),
+            client_request_tokens_changed: Arc::new(Condvar::new()),
+            waiting_queries: Arc::new(Mutex::new(VecDeque::new())),
+            named_queries: Arc::new(RwLock::new(HashMap::new())),
+            work_groups: Arc::new(RwLock::new(work_groups)),
+            data_catalogs: Arc::new(RwLock::new(operation::initial_data_catalogs(databases))),
+            prepared_statements: Arc::new(RwLock::new(HashMap::new())),
+            tags: Arc::new(RwLock::new(HashMap::new())),
+            #[cfg(feature = "s3")]
+            result_uploader,
+        })
+    }
+
+    /// Binds the listener and starts serving, must be called from within an actix system.
+    pub fn start(self) -> io::Result<MinervaHandle> {
+        let bind_address = self.bind_address.clone();
+        let (query_ttl, query_retention) = (self.query_ttl, self.query_retention);
+        let data = self.app_data()?;
+        let (output_dir, clock, processes, client_request_tokens) = (
+            data.output_dir.clone(),
+            data.clock.clone(),
+            data.processes.clone(),
+            data.client_request_tokens.clone(),
+        );
+        let process_handles = data.process_handles.clone();
+        let registry = web::Data::new(dispatch::Registry::new());
+        let server = HttpServer::new(move || {
+            App::new()
+                .wrap_fn(|req, srv| {
+                    let request_id = Uuid::new_v4().to_string();
+                    let span = tracing::info_span!(
+                        "request",
+                        request_id = %request_id,
+                        target = field::Empty,
+                        operation = field::Empty,
+                        query_execution_id = field::Empty,
+                    );
+                    let (method, path) = (req.method().clone(), req.path().to_string());
+                    let started_at = Instant::now();
+                    let response = span.in_scope(|| srv.call(req));
+                    async move {
+                        let mut response = response.await?;
+                        tracing::info!(
+                            %method,
+                            %path,
+                            status = response.status().as_u16(),
+                            elapsed_ms = started_at.elapsed().as_millis() as u64,
+                            "handled request"
+                        );
+                        if let Ok(value) = HeaderValue::from_str(&request_id) {
+                            response
+                                .headers_mut()
+                                .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                        }
+                        Ok(response)
+                    }
+                    .instrument(span)
+                })
+                .app_data(web::Data::new(data.clone()))
+                .app_data(registry.clone())
+                .app_data(
+                    web::JsonConfig::default()
+                        .content_type(|mime| {
+                            mime.type_() == mime::APPLICATION
+                                && mime.subtype().to_string().starts_with("x-amz-json-")
+                        })
+                        .error_handler(|err, _| {
+                            let message = err.to_string();
+                            InternalError::from_response(
+                                err,
+                                operation::error_response(
+                                    StatusCode::BAD_REQUEST,
+                                    dispatch::SERIALIZATION_EXCEPTION,
+                                    message,
+                                ),
+                            )
+                            .into()
+                        }),
+                )
+                .route("/", web::post().to(root))
+                .route("/healthz", web::get().to(healthz))
+                .route("/metrics", web::get().to(admin::metrics))
+                .route("/admin/reset", web::post().to(admin::reset))
+                .route(
+                    "/admin/queries/{id}/state",
+                    web::post().to(admin::set_state),
+                )
+                .route("/admin/advance", web::post().to(admin::advance))
+                .route("/admin/events", web::get().to(events::events))
+                // Note: the paths of the admin endpoints before they moved under `/admin`.
+                .route("/_minerva/advance", web::post().to(admin::advance))
+                .route("/_minerva/events", web::get().to(events::events))
+                // Note: after every other route, which would be taken for a bucket otherwise.
+                .route("/{bucket}/{key:.+}", web::get().to(s3::get_object))
+                .route("/{bucket}/{key:.+}", web::head().to(s3::head_object))
+        })
+        .bind(&bind_address)?;
+        let addr = server.addrs().into_iter().next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!("failed to bind {:}", bind_address),
+            )
+        })?;
+
+        let eviction_handle = if query_ttl.is_some() || query_retention.is_some() {
+            Some(operation::evict_queries(
+                query_ttl,
+                query_retention,
+                output_dir,
+                clock,
+                processes,
+                client_request_tokens,
+            ))
+        } else {
+            None
+        };
+
+        Ok(MinervaHandle {
+            addr,
+            server: server.run(),
+            process_handles,
+            eviction_handle,
+        })
+    }
+}
+
+/// A running Minerva server.
+pub struct MinervaHandle {
+    addr: SocketAddr,
+    server: Server,
+    process_handles: Arc<Mutex<HashMap<String, AbortHandle>>>,
+    eviction_handle: Option<AbortHandle>,
+}
+
+impl MinervaHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Stops accepting connections, waits for in-flight requests to finish
+    /// and aborts the outstanding state transitions.
+    pub async fn stop(&self) {
+        self.server.stop(true).await;
+        self.abort_tasks();
+    }
+
+    /// Waits until the server is stopped, e.g. by SIGINT or SIGTERM.
+    pub async fn wait(self) -> io::Result<()> {
+        let result = self.server.clone().await;
+        self.abort_tasks();
+        result
+    }
+
+    fn abort_tasks(&self) {
+        for (_, handle) in self.process_handles.lock().unwrap().drain() {
+            handle.abort();
+        }
+        if let Some(handle) = &self.eviction_handle {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use actix_web::client::Client;
+    use serde_json::json;
+
+    // Note: on a port of its own, so the tests can run side by side.
+    pub(crate) fn start(builder: MinervaServerBuilder) -> MinervaHandle {
+        builder.bind("127.0.0.1:0").start().unwrap()
+    }
+
+    // Note: in a thread and an actix system of its own, for the clients which run on tokio 1,
+    // served until the test ends.
+    pub(crate) fn serve(builder: MinervaServerBuilder) -> SocketAddr {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let mut system = actix_rt::System::new("minerva");
+            let handle = system.block_on(async { start(builder) });
+            sender.send(handle.addr()).unwrap();
+            system.block_on(handle.wait())
+        });
+        receiver.recv().unwrap()
+    }
+
+    pub(crate) fn athena_client(addr: SocketAddr) -> aws_sdk_athena::Client {
+        let config = aws_sdk_athena::Config::builder()
+            .region(aws_sdk_athena::Region::new("us-east-1"))
+            .credentials_provider(aws_sdk_athena::Credentials::new(
+                "minerva", "minerva", None, None, "minerva",
+            ))
+            .endpoint_resolver(aws_sdk_athena::Endpoint::immutable(
+                format!("http://{:}", addr).parse().unwrap(),
+            ))
+            .retry_config(aws_sdk_athena::RetryConfig::disabled())
+            .build();
+        aws_sdk_athena::Client::from_conf(config)
+    }
+
+    pub(crate) async fn post(
+        handle: &MinervaHandle,
+        operation: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        post_to(handle.addr(), operation, body).await
+    }
+
+    pub(crate) async fn post_to(
+        addr: SocketAddr,
+        operation: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut response = Client::default()
+            .post(format!("http://{:}/", addr))
+            .header(
+                OPERATION_TARGET_HEADER,
+                format!("{:}{:}", OPERATION_TARGET_PREFIX, operation),
+            )
+            .content_type(operation::AMZ_JSON_CONTENT_TYPE)
+            .send_body(body.to_string())
+            .await
+            .unwrap();
+        let body = response.body().await.unwrap();
+        (response.status(), serde_json::from_slice(&body).unwrap())
+    }
+
+    pub(crate) async fn post_admin(
+        handle: &MinervaHandle,
+        path: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        post_admin_to(handle.addr(), path, body).await
+    }
+
+    pub(crate) async fn post_admin_to(
+        addr: SocketAddr,
+        path: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut response = Client::default()
+            .post(format!("http://{:}{:}", addr, path))
+            .send_json(&body)
+            .await
+            .unwrap();
+        let body = response.body().await.unwrap();
+        (response.status(), serde_json::from_slice(&body).unwrap())
+    }
+
+    #[actix_rt::test]
+    async fn get_query_execution_of_an_unknown_id_is_an_invalid_request() {
+        let handle = start(MinervaServer::builder());
+        for query_execution_id in [Uuid::new_v4().to_string(), String::new(), "foo".to_string()] {
+            let (status, body) = post(
+                &handle,
+                "GetQueryExecution",
+                json!({ "QueryExecutionId": query_execution_id }),
+            )
+            .await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["__type"], "InvalidRequestException");
+            assert_eq!(
+                body["Message"],
+                format!("QueryExecution {:} was not found", query_execution_id)
+            );
+        }
+        handle.stop().await;
+    }
+
+    #[actix_rt::test]
+    async fn get_query_execution_of_an_unknown_id_is_answered_with_a_json_error() {
+        let handle = start(MinervaServer::builder());
+        for _ in 0..2 {
+            let mut response = Client::default()
+                .post(format!("http://{:}/", handle.addr()))
+                .header(OPERATION_TARGET_HEADER, "AmazonAthena.GetQueryExecution")
+                .content_type(operation::AMZ_JSON_CONTENT_TYPE)
+                .send_body(json!({ "QueryExecutionId": Uuid::new_v4().to_string() }).to_string())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(
+                response.headers().get("content-type").unwrap(),
+                operation::AMZ_JSON_CONTENT_TYPE
+            );
+            let body: serde_json::Value =
+                serde_json::from_slice(&response.body().await.unwrap()).unwrap();
+            assert_eq!(body["__type"], "InvalidRequestException");
+        }
+        handle.stop().await;
+    }
+
+    #[test]
+    fn submission_and_completion_date_times_round_trip_through_the_sdk() {
+        let addr = serve(
+            MinervaServer::builder()
+                .virtual_clock(true)
+                .queued_duration(Duration::from_secs(1))
+                .running_duration(Duration::from_secs(1)),
+        );
+        let client = athena_client(addr);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let get_status = |query_execution_id: &str| {
+            runtime.block_on(async {
+                client
+                    .get_query_execution()
+                    .query_execution_id(query_execution_id)
+                    .send()
+                    .await
+                    .unwrap()
+                    .query_execution
+                    .unwrap()
+                    .status
+                    .unwrap()
+            })
+        };
+        let query_execution_id = runtime.block_on(async {
+            client
+                .start_query_execution()
+                .query_string("SELECT * FROM users")
+                .send()
+                .await
+                .unwrap()
+                .query_execution_id
+                .unwrap()
+        });
+
+        let status = get_status(&query_execution_id);
+        assert_eq!(
+            status.state,
+            Some(aws_sdk_athena::model::QueryExecutionState::Queued)
+        );
+        let submission_date_time = status.submission_date_time.unwrap();
+        assert!(status.completion_date_time.is_none());
+
+        actix_rt::System::new("client").block_on(post_admin_to(
+            addr,
+            "/admin/advance",
+            json!({ "seconds": 1 }),
+        ));
+        let status = get_status(&query_execution_id);
+        assert_eq!(
+            status.state,
+            Some(aws_sdk_athena::model::QueryExecutionState::Running)
+        );
+        assert!(status.completion_date_time.is_none());
+
+        actix_rt::System::new("client").block_on(post_admin_to(
+            addr,
+            "/admin/advance",
+            json!({ "seconds": 1 }),
+        ));
+        let status = get_status(&query_execution_id);
+        assert_eq!(
+            status.state,
+            Some(aws_sdk_athena::model::QueryExecutionState::Succeeded)
+        );
+        assert_eq!(status.submission_date_time, Some(submission_date_time));
+        let completion_date_time = status.completion_date_time.unwrap();
+        assert_eq!(
+            completion_date_time.as_secs_f64() - submission_date_time.as_secs_f64(),
+            2.0
+        );
+    }
+
+    #[test]
+    fn athena_error_round_trips_through_the_sdk() {
+        let addr = serve(
+            MinervaServer::builder()
+                .immediate(true)
+                .csv_fixture_dir(operation::tests::fixture_dir(&[("users.csv", "id\n1\n")])),
+        );
+        let client = athena_client(addr);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let cases = [
+            ("SELEC * FROM users", (2, 1000, false, None)),
+            (
+                "SELECT * FROM users -- minerva:fail",
+                (2, 1000, false, None),
+            ),
+            (
+                r#"SELECT * FROM users -- minerva:fail error_category=1 error_type=1001 retryable=true error_message="Query exhausted resources""#,
+                (1, 1001, true, Some("Query exhausted resources")),
+            ),
+        ];
+        for (query_string, (error_category, error_type, retryable, error_message)) in cases {
+            let (query_execution_id, status) = runtime.block_on(async {
+                let query_execution_id = client
+                    .start_query_execution()
+                    .query_string(query_string)
+                    .send()
+                    .await
+                    .unwrap()
+                    .query_execution_id
+                    .unwrap();
+                let output = client
+                    .get_query_execution()
+                    .query_execution_id(&query_execution_id)
+                    .send()
+                    .await
+                    .unwrap();
+                (
+                    query_execution_id,
+                    output.query_execution.unwrap().status.unwrap(),
+                )
+            });
+            assert_eq!(
+                status.state,
+                Some(aws_sdk_athena::model::QueryExecutionState::Failed),
+                "{:}",
+                query_string
+            );
+            let athena_error = status.athena_error.unwrap();
+            assert_eq!(athena_error.error_category(), Some(error_category));
+            assert_eq!(athena_error.error_type(), Some(error_type));
+
+            // Note: Retryable and ErrorMessage aren't read by this version of the SDK.
+            let (_, body) = actix_rt::System::new("client").block_on(post_to(
+                addr,
+                "GetQueryExecution",
+                json!({ "QueryExecutionId": query_execution_id }),
+            ));
+            let athena_error = &body["QueryExecution"]["Status"]["AthenaError"];
+            assert_eq!(athena_error["Retryable"], retryable);
+            let reason = status.state_change_reason.unwrap();
+            assert_eq!(athena_error["ErrorMessage"], reason.as_str());
+            if let Some(error_message) = error_message {
+                assert_eq!(reason, error_message);
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn stop_releases_the_port_and_aborts_the_transitions() {
+        let handle = start(MinervaServer::builder().process_interval(Duration::from_secs(60)));
+        let (status, _) = post(
+            &handle,
+            "StartQueryExecution",
+            json!({ "QueryString": "SELECT * FROM users" }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(handle.process_handles.lock().unwrap().len(), 1);
+
+        handle.stop().await;
+        assert!(handle.process_handles.lock().unwrap().is_empty());
+        std::net::TcpListener::bind(handle.addr()).unwrap();
+    }
+
+    #[test]
+    fn get_query_results_is_paginated_for_the_sdk_paginator() {
+        let rows = (0..2500)
+            .map(|v| format!("{:},name{:}\n", v, v))
+            .collect::<String>();
+        let csv_fixture_dir =
+            operation::tests::fixture_dir(&[("users.csv", &format!("id,name\n{:}", rows))]);
+        let addr = serve(
+            MinervaServer::builder()
+                .immediate(true)
+                .csv_fixture_dir(csv_fixture_dir),
+        );
+        let client = athena_client(addr);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let pages = runtime.block_on(async {
+            let query_execution_id = client
+                .start_query_execution()
+                .query_string("SELECT * FROM users")
+                .send()
+                .await
+                .unwrap()
+                .query_execution_id
+                .unwrap();
+            let paginator = client
+                .get_query_results()
+                .query_execution_id(query_execution_id)
+                .into_paginator()
+                .send();
+            futures::pin_mut!(paginator);
+            let mut pages = Vec::new();
+            while let Some(page) = futures::StreamExt::next(&mut paginator).await {
+                pages.push(page.unwrap());
+            }
+            pages
+        });
+
+        let row_counts = pages
+            .iter()
+            .map(|v| v.result_set.as_ref().unwrap().rows.as_ref().unwrap().len())
+            .collect::<Vec<_>>();
+        assert_eq!(row_counts, [1000, 1000, 501]);
+        for page in &pages {
+            let column_info = page
+                .result_set
+                .as_ref()
+                .and_then(|v| v.result_set_metadata.as_ref())
+                .and_then(|v| v.column_info.as_ref())
+                .unwrap();
+            assert_eq!(column_info.len(), 2);
+        }
+        let last_row = pages[2].result_set.as_ref().unwrap().rows.as_ref().unwrap()[500]
+            .data
+            .as_ref()
+            .unwrap()[0]
+            .var_char_value
+            .clone();
+        assert_eq!(last_row.as_deref(), Some("2499"));
+    }
+
+    #[test]
+    fn null_and_empty_values_round_trip_through_the_sdk() {
+        let csv_fixture_dir = operation::tests::fixture_dir(&[
+            ("users.csv", "id,name\n1,\n2,\"\"\n"),
+            ("sentinels.csv", "id,name\n1,\\N\n2,\n"),
+            (
+                "sentinels.csv.schema.json",
+                r#"{"columns": {}, "null": "\\N"}"#,
+            ),
+            (
+                "items.json",
+                r#"[{"id": 1, "name": null}, {"id": 2, "name": ""}]"#,
+            ),
+        ]);
+        let addr = serve(
+            MinervaServer::builder()
+                .immediate(true)
+                .csv_fixture_dir(csv_fixture_dir),
+        );
+        let client = athena_client(addr);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        for table_name in ["users", "sentinels", "items"] {
+            let names = runtime.block_on(async {
+                let query_execution_id = client
+                    .start_query_execution()
+                    .query_string(format!("SELECT * FROM {:}", table_name))
+                    .send()
+                    .await
+                    .unwrap()
+                    .query_execution_id
+                    .unwrap();
+                let output = client
+                    .get_query_results()
+                    .query_execution_id(query_execution_id)
+                    .send()
+                    .await
+                    .unwrap();
+                output
+                    .result_set
+                    .unwrap()
+                    .rows
+                    .unwrap()
+                    .into_iter()
+                    .skip(1)
+                    .map(|v| v.data.unwrap()[1].var_char_value.clone())
+                    .collect::<Vec<_>>()
+            });
+            assert!(names[0].is_none(), "{:}", table_name);
+            assert_eq!(names[1].as_deref(), Some(""), "{:}", table_name);
+        }
+    }
+
+    #[test]
+    fn named_queries_round_trip_through_the_sdk() {
+        let addr = serve(MinervaServer::builder());
+        let client = athena_client(addr);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let named_query_id = client
+                .create_named_query()
+                .name("users")
+                .description("all the users")
+                .database("default")
+                .query_string("SELECT * FROM users")
+                .work_group("foo")
+                .client_request_token("token-00000000000000000000000000000")
+                .send()
+                .await
+                .unwrap()
+                .named_query_id
+                .unwrap();
+
+            let named_query = client
+                .get_named_query()
+                .named_query_id(&named_query_id)
+                .send()
+                .await
+                .unwrap()
+                .named_query
+                .unwrap();
+            assert_eq!(
+                named_query.named_query_id.as_deref(),
+                Some(&*named_query_id)
+            );
+            assert_eq!(named_query.name.as_deref(), Some("users"));
+            assert_eq!(named_query.description.as_deref(), Some("all the users"));
+            assert_eq!(named_query.database.as_deref(), Some("default"));
+            assert_eq!(
+                named_query.query_string.as_deref(),
+                Some("SELECT * FROM users")
+            );
+            assert_eq!(named_query.work_group.as_deref(), Some("foo"));
+
+            let err = client
+                .get_named_query()
+                .named_query_id("foo")
+                .send()
+                .await
+                .unwrap_err();
+            match err {
+                aws_sdk_athena::types::SdkError::ServiceError { err, .. } => {
+                    assert!(err.is_invalid_request_exception(), "{:?}", err);
+                }
+                err => panic!("unexpected error: {:?}", err),
+            }
+        });
+    }
+
+    #[test]
+    fn list_named_queries_is_paginated_for_the_sdk_paginator() {
+        let addr = serve(MinervaServer::builder());
+        let client = athena_client(addr);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let create_named_query = |name: String, work_group: &'static str| {
+                client
+                    .create_named_query()
+                    .name(name)
+                    .database("default")
+                    .query_string("SELECT * FROM users")
+                    .work_group(work_group)
+                    .send()
+            };
+            let mut named_query_ids = Vec::new();
+            for v in 0..120 {
+                let output = create_named_query(format!("users{:}", v), "primary")
+                    .await
+                    .unwrap();
+                named_query_ids.push(output.named_query_id.unwrap());
+            }
+            client
+                .create_work_group()
+                .name("other")
+                .send()
+                .await
+                .unwrap();
+            let other = create_named_query("others".to_string(), "other")
+                .await
+                .unwrap()
+                .named_query_id
+                .unwrap();
+
+            let paginator = client.list_named_queries().into_paginator().send();
+            futures::pin_mut!(paginator);
+            let mut pages = Vec::new();
+            while let Some(page) = futures::StreamExt::next(&mut paginator).await {
+                pages.push(page.unwrap().named_query_ids.unwrap());
+            }
+            let page_lens = pages.iter().map(Vec::len).collect::<Vec<_>>();
+            assert_eq!(page_lens, [50, 50, 20]);
+            assert_eq!(pages.concat(), named_query_ids);
+
+            let output = client
+                .list_named_queries()
+                .work_group("other")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(output.named_query_ids.unwrap(), [other]);
+            assert!(output.next_token.is_none());
+
+            let err = client
+                .list_named_queries()
+                .next_token("foo")
+                .send()
+                .await
+                .unwrap_err();
+            match err {
+                aws_sdk_athena::types::SdkError::ServiceError { err, .. } => {
+                    assert!(err.is_invalid_request_exception(), "{:?}", err);
+                }
+                err => panic!("unexpected error: {:?}", err),
+            }
+        });
+    }
+}
@@ -1,68 +1,244 @@
-mod model;
-mod operation;
-
-use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Result};
 use dotenv::dotenv;
+use minerva::{LinearTransitionPlan, MinervaServer};
+use regex::Regex;
 use std::env;
-use std::ops::Deref;
-use std::sync::{Arc, Mutex};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
 use std::time::Duration;
+use tracing_subscriber::EnvFilter;
 
-const OPERATION_TARGET_HEADER: &str = "X-Amz-Target";
-
-const OPERATION_NAME_START_QUERY_EXECUTION: &str = "AmazonAthena.StartQueryExecution";
-const OPERATION_NAME_GET_QUERY_EXECUTION: &str = "AmazonAthena.GetQueryExecution";
-const OPERATION_NAME_GET_QUERY_RESULTS: &str = "AmazonAthena.GetQueryResults";
-
-async fn root(
-    req: HttpRequest,
-    input: web::Json<crate::model::Param>,
-    data: web::Data<crate::model::AppData>,
-) -> Result<HttpResponse> {
-    let target = req.headers().get(OPERATION_TARGET_HEADER).ok_or_else(|| {
-        HttpResponse::BadRequest().body(format!("'{:}' not found", OPERATION_TARGET_HEADER))
-    })?;
-
-    if target == OPERATION_NAME_START_QUERY_EXECUTION {
-        operation::start_query_execution(input.deref(), data.get_ref())
-    } else if target == OPERATION_NAME_GET_QUERY_EXECUTION {
-        operation::get_query_execution(input.deref(), data.get_ref())
-    } else if target == OPERATION_NAME_GET_QUERY_RESULTS {
-        operation::get_query_results(input.deref(), data.get_ref())
-    } else {
-        Ok(HttpResponse::BadRequest().body(format!("unexpected target: {:?}", target)))
-    }
-}
+const DEFAULT_LOG_FILTER: &str = "info";
 
 #[actix_rt::main]
-async fn main() -> std::io::Result<()> {
+async fn main() -> io::Result<()> {
     dotenv().ok();
+    // Note: MINERVA_LOG takes precedence over RUST_LOG, so the server can be told apart from the client.
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_env("MINERVA_LOG")
+                .or_else(|_| EnvFilter::try_from_default_env())
+                .unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER)),
+        )
+        .init();
 
+    let bind_address = env::var("BIND_ADDRESS").unwrap_or("127.0.0.1".to_string());
     let port = env::var("PORT").unwrap_or("5050".to_string());
+    let bind_address = SocketAddr::new(
+        bind_address.parse::<IpAddr>().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid BIND_ADDRESS '{:}': {:}", bind_address, e),
+            )
+        })?,
+        port.parse::<u16>().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid PORT '{:}': {:}", port, e),
+            )
+        })?,
+    );
     let process_interval = env::var("PROCESS_INTERVAL_SECS")
         .ok()
         .and_then(|v| v.parse::<u64>().ok())
         .unwrap_or(5);
-    let csv_fixture_dir = env::var("CSV_FIXTURE_DIR").unwrap_or(".".to_string());
-
-    let (processes_r, processes_w) = evmap::new();
-    let processes_w = Arc::new(Mutex::new(processes_w));
+    let mut builder = MinervaServer::builder()
+        .bind(bind_address.to_string())
+        .process_interval(Duration::from_secs(process_interval))
+        .csv_fixture_dir(env::var("CSV_FIXTURE_DIR").unwrap_or(".".to_string()))
+        .fail_query_marker(env::var("FAIL_QUERY_MARKER").unwrap_or("-- minerva:fail".to_string()));
+    if let Some(queued_duration) = env::var("QUEUED_DURATION_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        builder = builder.queued_duration(Duration::from_secs(queued_duration));
+    }
+    if let Some(running_duration) = env::var("RUNNING_DURATION_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        builder = builder.running_duration(Duration::from_secs(running_duration));
+    }
+    if let Ok(v) = env::var("TRANSITION_PLAN") {
+        let transition_plan = v.parse::<LinearTransitionPlan>().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid TRANSITION_PLAN '{:}': {:}", v, e),
+            )
+        })?;
+        builder = builder.transition_plan(transition_plan);
+    }
+    if let Some(immediate) = env::var("MINERVA_IMMEDIATE")
+        .ok()
+        .and_then(|v| v.parse::<bool>().ok())
+    {
+        builder = builder.immediate(immediate);
+    }
+    // Note: also takes `1` as most test setups do.
+    if let Ok(v) = env::var("DETERMINISTIC_IDS") {
+        builder = builder.deterministic_ids(v == "1" || v == "true");
+    }
+    if let Some(validate_sql) = env::var("VALIDATE_SQL")
+        .ok()
+        .and_then(|v| v.parse::<bool>().ok())
+    {
+        builder = builder.validate_sql(validate_sql);
+    }
+    if let Some(virtual_clock) = env::var("MINERVA_VIRTUAL_CLOCK")
+        .ok()
+        .and_then(|v| v.parse::<bool>().ok())
+    {
+        builder = builder.virtual_clock(virtual_clock);
+    }
+    if let Some(ttl) = env::var("QUERY_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        builder = builder.query_ttl(Duration::from_secs(ttl));
+    }
+    if let Some(retention) = env::var("QUERY_RETENTION_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        builder = builder.query_retention(Duration::from_secs(retention));
+    }
+    if let Some(timeout) = env::var("QUERY_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        builder = builder.query_timeout(Duration::from_secs(timeout));
+    }
+    if let Ok(timeout_message) = env::var("QUERY_TIMEOUT_MESSAGE") {
+        builder = builder.query_timeout_message(timeout_message);
+    }
+    if let Some(fail_query_pattern) = env::var("FAIL_QUERY_PATTERN")
+        .ok()
+        .map(|v| Regex::new(&v).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)))
+        .transpose()?
+    {
+        builder = builder.fail_query_pattern(fail_query_pattern);
+    }
+    if let Some(error_category) = env::var("FAILURE_ERROR_CATEGORY")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| (1..=3).contains(v))
+    {
+        builder = builder.failure_error_category(error_category);
+    }
+    if let Some(error_type) = env::var("FAILURE_ERROR_TYPE")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        builder = builder.failure_error_type(error_type);
+    }
+    if let Some(retryable) = env::var("FAILURE_RETRYABLE")
+        .ok()
+        .and_then(|v| v.parse::<bool>().ok())
+    {
+        builder = builder.failure_retryable(retryable);
+    }
+    if let Ok(error_message) = env::var("FAILURE_ERROR_MESSAGE") {
+        builder = builder.failure_error_message(error_message);
+    }
+    if let Some(chaos_failure_rate) = env::var("CHAOS_FAILURE_RATE")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+    {
+        builder = builder.chaos_failure_rate(chaos_failure_rate);
+    }
+    if let Some(chaos_seed) = env::var("CHAOS_SEED")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        builder = builder.chaos_seed(chaos_seed);
+    }
+    if let Some(throttle_rate) = env::var("THROTTLE_RATE")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+    {
+        builder = builder.throttle_rate(throttle_rate);
+    }
+    if let Some(data_scanned_in_bytes) = env::var("DATA_SCANNED_IN_BYTES")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        builder = builder.data_scanned_in_bytes(data_scanned_in_bytes);
+    }
+    if let Some(include_header_row) = env::var("INCLUDE_HEADER_ROW")
+        .ok()
+        .and_then(|v| v.parse::<bool>().ok())
+    {
+        builder = builder.include_header_row(include_header_row);
+    }
+    if let Ok(output_dir) = env::var("OUTPUT_DIR") {
+        builder = builder.output_dir(output_dir);
+    }
+    if let Ok(output_location) = env::var("OUTPUT_LOCATION") {
+        builder = builder.default_output_location(output_location);
+    }
+    if let Ok(result_encryption_option) = env::var("RESULT_ENCRYPTION_OPTION") {
+        builder = builder.result_encryption_option(result_encryption_option);
+    }
+    if let Ok(result_kms_key) = env::var("RESULT_KMS_KEY") {
+        builder = builder.result_kms_key(result_kms_key);
+    }
+    if let Some(output_metadata) = env::var("OUTPUT_METADATA")
+        .ok()
+        .and_then(|v| v.parse::<bool>().ok())
+    {
+        builder = builder.output_metadata(output_metadata);
+    }
+    // Note: RESULT_FIXTURES_DIR is an alias of FIXTURES_DIR, which wins when both are set.
+    if let Ok(fixtures_dir) = env::var("FIXTURES_DIR").or_else(|_| env::var("RESULT_FIXTURES_DIR"))
+    {
+        builder = builder.fixtures_dir(fixtures_dir);
+    }
+    if let Ok(databases) = env::var("DATABASES") {
+        builder = builder.databases(
+            databases
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty()),
+        );
+    }
+    if let Ok(databases_file) = env::var("DATABASES_FILE") {
+        builder = builder.databases_file(databases_file);
+    }
+    if let Ok(engine_version) = env::var("ENGINE_VERSION") {
+        builder = builder.engine_version(engine_version);
+    }
+    if let Some(max_concurrent_queries) = env::var("MAX_CONCURRENT_QUERIES")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+    {
+        builder = builder.max_concurrent_queries(max_concurrent_queries);
+    }
+    if let Some(max_running_queries) = env::var("MAX_RUNNING_QUERIES")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+    {
+        builder = builder.max_running_queries(max_running_queries);
+    }
+    if let Some(max_tracked_queries) = env::var("MAX_TRACKED_QUERIES")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+    {
+        builder = builder.max_tracked_queries(max_tracked_queries);
+    }
+    #[cfg(feature = "sqlite")]
+    if let Ok(sqlite_seed_file) = env::var("SQLITE_SEED_FILE") {
+        builder = builder.sqlite_seed_file(sqlite_seed_file);
+    }
+    #[cfg(feature = "s3")]
+    if let Ok(result_s3_endpoint) = env::var("RESULT_S3_ENDPOINT") {
+        builder = builder.result_s3_endpoint(result_s3_endpoint);
+    }
+    #[cfg(feature = "s3")]
+    if let Ok(result_s3_bucket) = env::var("RESULT_S3_BUCKET") {
+        builder = builder.result_s3_bucket(result_s3_bucket);
+    }
+    if let Ok(completion_webhook_url) = env::var("COMPLETION_WEBHOOK_URL") {
+        builder = builder.completion_webhook_url(completion_webhook_url);
+    }
 
-    HttpServer::new(move || {
-        App::new()
-            .app_data(web::Data::new(crate::model::AppData {
-                process_interval: Duration::from_secs(process_interval),
-                csv_fixture_dir: csv_fixture_dir.clone(),
-                processes_r: processes_r.clone(),
-                processes_w: processes_w.clone(),
-            }))
-            .app_data(web::JsonConfig::default().content_type(|mime| {
-                mime.type_() == mime::APPLICATION
-                    && mime.subtype().to_string().starts_with("x-amz-json-")
-            }))
-            .route("/", web::post().to(root))
-    })
-    .bind(format!("127.0.0.1:{:}", port))?
-    .run()
-    .await
+    builder.start()?.wait().await
 }
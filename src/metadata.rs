@@ -0,0 +1,186 @@
+/// A `SHOW`, `DESCRIBE` or `EXPLAIN` statement, answered without running a query: from the
+/// databases and tables of the data catalogs, or with a plan for `EXPLAIN`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataQuery {
+    /// `SHOW DATABASES [LIKE 'pattern']`, or `SHOW SCHEMAS`.
+    ShowDatabases { pattern: Option<String> },
+    /// `SHOW TABLES [IN database] ['pattern']`.
+    ShowTables {
+        database: Option<String>,
+        pattern: Option<String>,
+    },
+    /// `DESCRIBE [database.]table`, or `DESC`.
+    Describe {
+        database: Option<String>,
+        table: String,
+    },
+    /// `EXPLAIN [(option, ...)] statement`, or `EXPLAIN ANALYZE [VERBOSE] statement`.
+    Explain { analyze: bool, statement: String },
+}
+
+/// The column of the result of `SHOW DATABASES`.
+pub const DATABASE_NAME_COLUMN: &str = "database_name";
+/// The column of the result of `SHOW TABLES`.
+pub const TAB_NAME_COLUMN: &str = "tab_name";
+/// The column of the result of `DESCRIBE`, whose rows have the name, type and comment of a
+/// column in one value.
+pub const COL_NAME_COLUMN: &str = "col_name";
+/// The column of the result of `EXPLAIN`, whose rows are the lines of the plan.
+pub const QUERY_PLAN_COLUMN: &str = "Query Plan";
+
+// Note: the width Hive pads each field of a DESCRIBE row to.
+const DESCRIBE_ALIGNMENT: usize = 20;
+
+impl MetadataQuery {
+    /// `None` for other statements, including the `SHOW` and `DESCRIBE` statements which aren't
+    /// answered, e.g. `SHOW PARTITIONS`.
+    pub fn parse(query_string: &str) -> Option<Self> {
+        let tokens = crate::statement::tokens(query_string);
+        let keywords = tokens.iter().map(|v| v.to_uppercase()).collect::<Vec<_>>();
+        let keywords = keywords.iter().map(String::as_str).collect::<Vec<_>>();
+        match keywords.as_slice() {
+            ["SHOW", "DATABASES" | "SCHEMAS"] => {
+                Some(MetadataQuery::ShowDatabases { pattern: None })
+            }
+            ["SHOW", "DATABASES" | "SCHEMAS", "LIKE", _] => Some(MetadataQuery::ShowDatabases {
+                pattern: Some(unquote(&tokens[3])),
+            }),
+            ["SHOW", "TABLES", rest @ ..] => {
+                let (database, rest) = match rest {
+                    ["IN" | "FROM", _, rest @ ..] => (Some(identifier(&tokens[3])), rest),
+                    _ => (None, rest),
+                };
+                let pattern = match rest {
+                    [] => None,
+                    [_] => Some(unquote(tokens.last()?)),
+                    _ => return None,
+                };
+                Some(MetadataQuery::ShowTables { database, pattern })
+            }
+            ["DESCRIBE" | "DESC", rest @ ..] => {
+                let name = match rest {
+                    ["EXTENDED" | "FORMATTED", _] => &tokens[2],
+                    [_] => &tokens[1],
+                    _ => return None,
+                };
+                let (database, table) = match name.split_once('.') {
+                    Some((database, table)) => (Some(identifier(database)), identifier(table)),
+                    None => (None, identifier(name)),
+                };
+                Some(MetadataQuery::Describe { database, table })
+            }
+            ["EXPLAIN", rest @ ..] => {
+                let analyze = rest.first() == Some(&"ANALYZE");
+                let skipped = match rest {
+                    ["ANALYZE", "VERBOSE", ..] => 2,
+                    ["ANALYZE", ..] => 1,
+                    // Note: the options, e.g. `(FORMAT TEXT, TYPE DISTRIBUTED)`, are ignored.
+                    ["FORMAT" | "TYPE", ..] => rest.iter().position(|v| v.ends_with(')'))? + 1,
+                    _ => 0,
+                };
+                let statement = &tokens[1 + skipped..];
+                (!statement.is_empty()).then(|| MetadataQuery::Explain {
+                    analyze,
+                    statement: statement.join(" "),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The rows of `DESCRIBE` for the columns and partition keys of a table: a tab separated name,
+/// type and comment, each padded as Hive does, then the partition keys under
+/// `# Partition Information`.
+pub fn describe_rows(table: &crate::model::Table) -> Vec<crate::model::Record> {
+    let row = |fields: [&str; 3]| {
+        fields
+            .iter()
+            .map(|v| format!("{:<width$}", v, width = DESCRIBE_ALIGNMENT))
+            .collect::<Vec<_>>()
+            .join("\t")
+    };
+    let column_row = |column: &crate::model::TableColumn| {
+        row([
+            &column.name,
+            &column.column_type,
+            column.comment.as_deref().unwrap_or_default(),
+        ])
+    };
+    let mut rows = table.columns.iter().map(column_row).collect::<Vec<_>>();
+    rows.extend(table.partition_keys.iter().map(column_row));
+    if !table.partition_keys.is_empty() {
+        rows.push(String::new());
+        rows.push("# Partition Information".to_string());
+        rows.push(row(["# col_name", "data_type", "comment"]));
+        rows.push(String::new());
+        rows.extend(table.partition_keys.iter().map(column_row));
+    }
+    rows.into_iter().map(|v| vec![Some(v)]).collect()
+}
+
+/// The lines of a plan for `statement`, as Athena formats a distributed plan: the table it reads,
+/// from the catalog and database of the query unless it names them, is scanned in a source
+/// fragment. `EXPLAIN ANALYZE` adds the timings and statistics of each fragment, which are made
+/// up as the statement isn't run.
+pub fn explain_rows(
+    catalog: &str,
+    database: &str,
+    statement: &str,
+    analyze: bool,
+) -> Vec<crate::model::Record> {
+    let words = statement.split_whitespace().collect::<Vec<_>>();
+    let table = words
+        .iter()
+        .position(|v| v.eq_ignore_ascii_case("FROM"))
+        .and_then(|i| words.get(i + 1))
+        .map(|v| identifier(v.trim_end_matches([')', ','])));
+    let mut rows = Vec::new();
+    if analyze {
+        rows.push(
+            "Queued: 210.37us, Analysis: 18.44ms, Planning: 42.90ms, Execution: 391.05ms"
+                .to_string(),
+        );
+    }
+    let statistics = |rows: &mut Vec<String>| {
+        if analyze {
+            rows.push("    CPU: 12.21ms, Scheduled: 15.68ms, Blocked 0.00ns (Input: 0.00ns, Output: 0.00ns), Input: 3 rows (143B); per task: avg.: 3.00 std.dev.: 0.00, Output: 3 rows (143B)".to_string());
+        }
+    };
+    rows.push("Fragment 0 [SINGLE]".to_string());
+    statistics(&mut rows);
+    rows.push("    Output layout: [*]".to_string());
+    rows.push("    Output partitioning: SINGLE []".to_string());
+    rows.push("    Output[columnNames = [*]]".to_string());
+    match table {
+        Some(table) => {
+            let (database, table) = match table.split_once('.') {
+                Some((database, table)) => (identifier(database), identifier(table)),
+                None => (database.to_string(), table),
+            };
+            rows.push("    └─ RemoteSource[sourceFragmentIds = [1]]".to_string());
+            rows.push(String::new());
+            rows.push("Fragment 1 [SOURCE]".to_string());
+            statistics(&mut rows);
+            rows.push("    Output layout: [*]".to_string());
+            rows.push("    Output partitioning: SINGLE []".to_string());
+            rows.push(format!(
+                "    TableScan[table = {:}:{:}:{:}]",
+                catalog.to_lowercase(),
+                database,
+                table
+            ));
+        }
+        None => rows.push("    └─ Values[]".to_string()),
+    }
+    rows.into_iter().map(|v| vec![Some(v)]).collect()
+}
+
+// Note: quoted with backticks, as Hive quotes them, or double quotes.
+fn identifier(name: &str) -> String {
+    name.trim_matches(|c| c == '`' || c == '"').to_string()
+}
+
+fn unquote(value: &str) -> String {
+    value.trim_matches('\'').to_string()
+}
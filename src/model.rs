@@ -1,5 +1,12 @@
+pub mod fixtures;
+
 use aws_sdk_athena::model::QueryExecutionState;
-use std::sync::{Arc, Mutex};
+use futures::future::AbortHandle;
+use rand::rngs::StdRng;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::time::Duration;
 
 #[derive(serde::Serialize)]
@@ -10,8 +17,458 @@ pub struct StartQueryExecutionResponse {
 
 impl StartQueryExecutionResponse {
     pub fn new(query_execution_id: String) -> Self {
-        StartQueryExecutionResponse {
-            query_execution_id: query_execution_id,
+        StartQueryExecutionResponse { query_execution_id }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct StopQueryExecutionResponse {}
+
+#[derive(serde::Serialize)]
+pub struct ListQueryExecutionsResponse {
+    #[serde(rename = "QueryExecutionIds")]
+    query_execution_ids: Vec<String>,
+    #[serde(rename = "NextToken", skip_serializing_if = "Option::is_none")]
+    next_token: Option<String>,
+}
+
+impl ListQueryExecutionsResponse {
+    pub fn new(query_execution_ids: Vec<String>, next_token: Option<String>) -> Self {
+        ListQueryExecutionsResponse {
+            query_execution_ids,
+            next_token,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct CreateNamedQueryResponse {
+    #[serde(rename = "NamedQueryId")]
+    named_query_id: String,
+}
+
+impl CreateNamedQueryResponse {
+    pub fn new(named_query_id: String) -> Self {
+        CreateNamedQueryResponse { named_query_id }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct GetNamedQueryResponse {
+    #[serde(rename = "NamedQuery")]
+    named_query: NamedQueryResponse,
+}
+
+impl GetNamedQueryResponse {
+    pub fn new(named_query_id: String, named_query: &NamedQuery) -> Self {
+        GetNamedQueryResponse {
+            named_query: NamedQueryResponse::new(named_query_id, named_query),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct NamedQueryResponse {
+    #[serde(rename = "NamedQueryId")]
+    named_query_id: String,
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Description", skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(rename = "Database")]
+    database: String,
+    #[serde(rename = "QueryString")]
+    query_string: String,
+    #[serde(rename = "WorkGroup")]
+    work_group: String,
+}
+
+impl NamedQueryResponse {
+    pub fn new(named_query_id: String, named_query: &NamedQuery) -> Self {
+        NamedQueryResponse {
+            named_query_id,
+            name: named_query.name.clone(),
+            description: named_query.description.clone(),
+            database: named_query.database.clone(),
+            query_string: named_query.query_string.clone(),
+            work_group: named_query.work_group.clone(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct DeleteNamedQueryResponse {}
+
+#[derive(serde::Serialize)]
+pub struct UpdateNamedQueryResponse {}
+
+#[derive(serde::Serialize)]
+pub struct BatchGetNamedQueryResponse {
+    #[serde(rename = "NamedQueries")]
+    pub named_queries: Vec<NamedQueryResponse>,
+    #[serde(rename = "UnprocessedNamedQueryIds")]
+    pub unprocessed_named_query_ids: Vec<UnprocessedNamedQueryId>,
+}
+
+#[derive(serde::Serialize)]
+pub struct UnprocessedNamedQueryId {
+    #[serde(rename = "NamedQueryId")]
+    pub named_query_id: String,
+    #[serde(rename = "ErrorCode")]
+    pub error_code: String,
+    #[serde(rename = "ErrorMessage")]
+    pub error_message: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct ListNamedQueriesResponse {
+    #[serde(rename = "NamedQueryIds")]
+    named_query_ids: Vec<String>,
+    #[serde(rename = "NextToken", skip_serializing_if = "Option::is_none")]
+    next_token: Option<String>,
+}
+
+impl ListNamedQueriesResponse {
+    pub fn new(named_query_ids: Vec<String>, next_token: Option<String>) -> Self {
+        ListNamedQueriesResponse {
+            named_query_ids,
+            next_token,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct CreateWorkGroupResponse {}
+
+#[derive(serde::Serialize)]
+pub struct UpdateWorkGroupResponse {}
+
+#[derive(serde::Serialize)]
+pub struct DeleteWorkGroupResponse {}
+
+#[derive(serde::Serialize)]
+pub struct GetWorkGroupResponse {
+    #[serde(rename = "WorkGroup")]
+    work_group: WorkGroupResponse,
+}
+
+impl GetWorkGroupResponse {
+    pub fn new(name: String, work_group: &WorkGroup) -> Self {
+        GetWorkGroupResponse {
+            work_group: WorkGroupResponse {
+                name,
+                state: work_group.state.clone(),
+                configuration: work_group.configuration.clone(),
+                description: work_group.description.clone(),
+                creation_time: epoch_secs(work_group.creation_time),
+            },
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct WorkGroupResponse {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "State")]
+    state: String,
+    #[serde(rename = "Configuration")]
+    configuration: WorkGroupConfiguration,
+    #[serde(rename = "Description", skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(rename = "CreationTime")]
+    creation_time: f64,
+}
+
+#[derive(serde::Serialize)]
+pub struct ListWorkGroupsResponse {
+    #[serde(rename = "WorkGroups")]
+    work_groups: Vec<WorkGroupSummary>,
+    #[serde(rename = "NextToken", skip_serializing_if = "Option::is_none")]
+    next_token: Option<String>,
+}
+
+impl ListWorkGroupsResponse {
+    pub fn new(work_groups: Vec<(String, WorkGroup)>, next_token: Option<String>) -> Self {
+        ListWorkGroupsResponse {
+            work_groups: work_groups
+                .into_iter()
+                .map(|(name, work_group)| WorkGroupSummary {
+                    name,
+                    state: work_group.state,
+                    description: work_group.description,
+                    creation_time: epoch_secs(work_group.creation_time),
+                    engine_version: work_group.configuration.engine_version,
+                })
+                .collect(),
+            next_token,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct WorkGroupSummary {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "State")]
+    state: String,
+    #[serde(rename = "Description", skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(rename = "CreationTime")]
+    creation_time: f64,
+    #[serde(rename = "EngineVersion", skip_serializing_if = "Option::is_none")]
+    engine_version: Option<EngineVersion>,
+}
+
+#[derive(serde::Serialize)]
+pub struct CreateDataCatalogResponse {}
+
+#[derive(serde::Serialize)]
+pub struct GetDataCatalogResponse {
+    #[serde(rename = "DataCatalog")]
+    data_catalog: DataCatalogResponse,
+}
+
+impl GetDataCatalogResponse {
+    pub fn new(name: String, data_catalog: &DataCatalog) -> Self {
+        GetDataCatalogResponse {
+            data_catalog: DataCatalogResponse {
+                name,
+                catalog_type: data_catalog.catalog_type.clone(),
+                description: data_catalog.description.clone(),
+                parameters: data_catalog.parameters.clone(),
+            },
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct DataCatalogResponse {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Type")]
+    catalog_type: String,
+    #[serde(rename = "Description", skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(rename = "Parameters")]
+    parameters: BTreeMap<String, String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ListDataCatalogsResponse {
+    #[serde(rename = "DataCatalogsSummary")]
+    data_catalogs_summary: Vec<DataCatalogSummary>,
+    #[serde(rename = "NextToken", skip_serializing_if = "Option::is_none")]
+    next_token: Option<String>,
+}
+
+impl ListDataCatalogsResponse {
+    pub fn new(data_catalogs: Vec<(String, DataCatalog)>, next_token: Option<String>) -> Self {
+        ListDataCatalogsResponse {
+            data_catalogs_summary: data_catalogs
+                .into_iter()
+                .map(|(catalog_name, data_catalog)| DataCatalogSummary {
+                    catalog_name,
+                    catalog_type: data_catalog.catalog_type,
+                })
+                .collect(),
+            next_token,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct DataCatalogSummary {
+    #[serde(rename = "CatalogName")]
+    catalog_name: String,
+    #[serde(rename = "Type")]
+    catalog_type: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct CreatePreparedStatementResponse {}
+
+#[derive(serde::Serialize)]
+pub struct DeletePreparedStatementResponse {}
+
+#[derive(serde::Serialize)]
+pub struct GetPreparedStatementResponse {
+    #[serde(rename = "PreparedStatement")]
+    prepared_statement: PreparedStatementResponse,
+}
+
+impl GetPreparedStatementResponse {
+    pub fn new(
+        (work_group_name, statement_name): (String, String),
+        prepared_statement: &PreparedStatement,
+    ) -> Self {
+        GetPreparedStatementResponse {
+            prepared_statement: PreparedStatementResponse {
+                statement_name,
+                query_statement: prepared_statement.query_statement.clone(),
+                work_group_name,
+                description: prepared_statement.description.clone(),
+                last_modified_time: epoch_secs(prepared_statement.last_modified_time),
+            },
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct PreparedStatementResponse {
+    #[serde(rename = "StatementName")]
+    statement_name: String,
+    #[serde(rename = "QueryStatement")]
+    query_statement: String,
+    #[serde(rename = "WorkGroupName")]
+    work_group_name: String,
+    #[serde(rename = "Description", skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(rename = "LastModifiedTime")]
+    last_modified_time: f64,
+}
+
+#[derive(serde::Serialize)]
+pub struct ListPreparedStatementsResponse {
+    #[serde(rename = "PreparedStatements")]
+    prepared_statements: Vec<PreparedStatementSummary>,
+    #[serde(rename = "NextToken", skip_serializing_if = "Option::is_none")]
+    next_token: Option<String>,
+}
+
+impl ListPreparedStatementsResponse {
+    pub fn new(
+        prepared_statements: Vec<(String, PreparedStatement)>,
+        next_token: Option<String>,
+    ) -> Self {
+        ListPreparedStatementsResponse {
+            prepared_statements: prepared_statements
+                .into_iter()
+                .map(
+                    |(statement_name, prepared_statement)| PreparedStatementSummary {
+                        statement_name,
+                        last_modified_time: epoch_secs(prepared_statement.last_modified_time),
+                    },
+                )
+                .collect(),
+            next_token,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct PreparedStatementSummary {
+    #[serde(rename = "StatementName")]
+    statement_name: String,
+    #[serde(rename = "LastModifiedTime")]
+    last_modified_time: f64,
+}
+
+#[derive(serde::Serialize)]
+pub struct TagResourceResponse {}
+
+#[derive(serde::Serialize)]
+pub struct UntagResourceResponse {}
+
+#[derive(serde::Serialize)]
+pub struct ListTagsForResourceResponse {
+    #[serde(rename = "Tags")]
+    tags: Vec<Tag>,
+    #[serde(rename = "NextToken", skip_serializing_if = "Option::is_none")]
+    next_token: Option<String>,
+}
+
+impl ListTagsForResourceResponse {
+    pub fn new(tags: Vec<(String, String)>, next_token: Option<String>) -> Self {
+        ListTagsForResourceResponse {
+            tags: tags
+                .into_iter()
+                .map(|(key, value)| Tag { key, value })
+                .collect(),
+            next_token,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct GetDatabaseResponse {
+    #[serde(rename = "Database")]
+    database: DatabaseResponse,
+}
+
+impl GetDatabaseResponse {
+    pub fn new(name: String, database: &Database) -> Self {
+        GetDatabaseResponse {
+            database: DatabaseResponse::new(name, database.clone()),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct ListDatabasesResponse {
+    #[serde(rename = "DatabaseList")]
+    database_list: Vec<DatabaseResponse>,
+    #[serde(rename = "NextToken", skip_serializing_if = "Option::is_none")]
+    next_token: Option<String>,
+}
+
+impl ListDatabasesResponse {
+    pub fn new(databases: Vec<(String, Database)>, next_token: Option<String>) -> Self {
+        ListDatabasesResponse {
+            database_list: databases
+                .into_iter()
+                .map(|(name, database)| DatabaseResponse::new(name, database))
+                .collect(),
+            next_token,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct DatabaseResponse {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Description", skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(rename = "Parameters", skip_serializing_if = "BTreeMap::is_empty")]
+    parameters: BTreeMap<String, String>,
+}
+
+impl DatabaseResponse {
+    fn new(name: String, database: Database) -> Self {
+        DatabaseResponse {
+            name,
+            description: database.description,
+            parameters: database.parameters,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct GetTableMetadataResponse {
+    #[serde(rename = "TableMetadata")]
+    table_metadata: Table,
+}
+
+impl GetTableMetadataResponse {
+    pub fn new(table_metadata: Table) -> Self {
+        GetTableMetadataResponse { table_metadata }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct ListTableMetadataResponse {
+    #[serde(rename = "TableMetadataList")]
+    table_metadata_list: Vec<Table>,
+    #[serde(rename = "NextToken", skip_serializing_if = "Option::is_none")]
+    next_token: Option<String>,
+}
+
+impl ListTableMetadataResponse {
+    pub fn new(table_metadata_list: Vec<Table>, next_token: Option<String>) -> Self {
+        ListTableMetadataResponse {
+            table_metadata_list,
+            next_token,
         }
     }
 }
@@ -23,50 +480,62 @@ pub struct GetQueryExecutionResponse {
 }
 
 impl GetQueryExecutionResponse {
-    pub fn new(query_execution_id: String, state: QueryExecutionState) -> Self {
+    pub fn new(query_execution_id: String, query_process: &QueryProcess, now: u64) -> Self {
         GetQueryExecutionResponse {
-            query_execution: QueryExecutionResponse {
-                query_execution_id: query_execution_id,
-                status: StatusResponse { state: state },
-            },
+            query_execution: QueryExecutionResponse::new(query_execution_id, query_process, now),
         }
     }
 }
 
+#[derive(serde::Serialize)]
+pub struct BatchGetQueryExecutionResponse {
+    #[serde(rename = "QueryExecutions")]
+    pub query_executions: Vec<QueryExecutionResponse>,
+    #[serde(rename = "UnprocessedQueryExecutionIds")]
+    pub unprocessed_query_execution_ids: Vec<UnprocessedQueryExecutionId>,
+}
+
+#[derive(serde::Serialize)]
+pub struct UnprocessedQueryExecutionId {
+    #[serde(rename = "QueryExecutionId")]
+    pub query_execution_id: String,
+    #[serde(rename = "ErrorCode")]
+    pub error_code: String,
+    #[serde(rename = "ErrorMessage")]
+    pub error_message: String,
+}
+
+pub const VARCHAR_TYPE: &str = "varchar";
+pub const EXTERNAL_TABLE_TYPE: &str = "EXTERNAL_TABLE";
+
+// Note: Athena doesn't tell whether a column is nullable.
+const COLUMN_NULLABLE_UNKNOWN: &str = "UNKNOWN";
+
 #[derive(serde::Serialize)]
 pub struct GetQueryResultsResponse {
     #[serde(rename = "UpdateCount")]
-    update_count: u32,
+    update_count: u64,
     #[serde(rename = "ResultSet")]
     result_set: ResultSet,
-    #[serde(rename = "NextToken")]
+    #[serde(rename = "NextToken", skip_serializing_if = "Option::is_none")]
     next_token: Option<String>,
 }
 
 impl GetQueryResultsResponse {
     pub fn new(
         table_name: String,
-        column_names: Vec<String>,
+        columns: Vec<Column>,
         rows: Vec<Row>,
         next_token: Option<String>,
+        update_count: u64,
     ) -> Self {
-        let mut column_info = Vec::new();
-        for column_name in &column_names {
-            column_info.push(ColumnInfo {
-                table_name: table_name.clone(),
-                name: column_name.clone(),
-                label: column_name.clone(),
-            });
-        }
         GetQueryResultsResponse {
             result_set: ResultSet {
-                rows: rows,
-                result_set_metadata: ResultSetMetadata {
-                    column_info: column_info,
-                },
+                rows,
+                result_set_metadata: ResultSetMetadata::new(&table_name, &columns),
             },
-            next_token: next_token,
-            update_count: 0,
+            next_token,
+            update_count,
         }
     }
 }
@@ -80,19 +549,106 @@ struct ResultSet {
 }
 
 #[derive(serde::Serialize)]
-struct ResultSetMetadata {
+pub struct ResultSetMetadata {
     #[serde(rename = "ColumnInfo")]
     column_info: Vec<ColumnInfo>,
 }
 
+impl ResultSetMetadata {
+    pub fn new(table_name: &str, columns: &[Column]) -> Self {
+        let mut column_info = Vec::new();
+        for column in columns {
+            column_info.push(ColumnInfo::new(table_name, column));
+        }
+        ResultSetMetadata { column_info }
+    }
+}
+
 #[derive(serde::Serialize)]
-struct ColumnInfo {
+pub struct ColumnInfo {
     #[serde(rename = "TableName")]
-    table_name: String,
+    pub table_name: String,
     #[serde(rename = "Name")]
-    name: String,
+    pub name: String,
     #[serde(rename = "Label")]
-    label: String,
+    pub label: String,
+    #[serde(rename = "Type")]
+    pub column_type: String,
+    #[serde(rename = "Precision")]
+    pub precision: u32,
+    #[serde(rename = "Scale")]
+    pub scale: u32,
+    #[serde(rename = "Nullable")]
+    pub nullable: String,
+    #[serde(rename = "CaseSensitive")]
+    pub case_sensitive: bool,
+}
+
+impl ColumnInfo {
+    // Note: precision and scale are the ones Athena reports for each type, e.g. 19 for bigint.
+    pub fn new(table_name: &str, column: &Column) -> Self {
+        let column_type = column.column_type.to_lowercase();
+        let (base_type, parameters) = match column_type.split_once('(') {
+            Some((base_type, parameters)) => (
+                base_type.trim(),
+                parameters
+                    .trim_end_matches(')')
+                    .split(',')
+                    .filter_map(|v| v.trim().parse::<u32>().ok())
+                    .collect::<Vec<_>>(),
+            ),
+            None => (column_type.as_str(), Vec::new()),
+        };
+        let (precision, scale) = match base_type {
+            "boolean" => (0, 0),
+            "tinyint" => (3, 0),
+            "smallint" => (5, 0),
+            "integer" | "int" => (10, 0),
+            "bigint" => (19, 0),
+            "real" | "float" => (7, 0),
+            "double" => (17, 0),
+            "decimal" => (
+                parameters.first().copied().unwrap_or(38),
+                parameters.get(1).copied().unwrap_or(0),
+            ),
+            "date" => (0, 0),
+            "timestamp" => (3, 0),
+            "varchar" | "char" => (parameters.first().copied().unwrap_or(2147483647), 0),
+            "varbinary" => (1073741824, 0),
+            _ => (0, 0),
+        };
+        ColumnInfo {
+            table_name: table_name.to_string(),
+            name: column.name.clone(),
+            label: column.name.clone(),
+            case_sensitive: matches!(base_type, "varchar" | "char"),
+            column_type,
+            precision,
+            scale,
+            nullable: COLUMN_NULLABLE_UNKNOWN.to_string(),
+        }
+    }
+}
+
+/// A column of a query result, `column_type` is an Athena type like `varchar` or `bigint`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Column {
+    pub name: String,
+    #[serde(rename = "type", default = "varchar")]
+    pub column_type: String,
+}
+
+impl Column {
+    pub fn varchar(name: &str) -> Self {
+        Column {
+            name: name.to_string(),
+            column_type: varchar(),
+        }
+    }
+}
+
+fn varchar() -> String {
+    VARCHAR_TYPE.to_string()
 }
 
 #[derive(serde::Serialize)]
@@ -102,35 +658,250 @@ pub struct Row {
 }
 
 impl Row {
-    pub fn new(values: &Vec<String>) -> Self {
+    pub fn new(values: &[Option<String>]) -> Self {
         let mut data = Vec::new();
         for value in values {
             data.push(Datum {
                 var_char_value: value.clone(),
             });
         }
-        Row { data: data }
+        Row { data }
     }
 }
 
+// Note: a NULL has no VarCharValue, as Athena does, while an empty string has an empty one.
 #[derive(serde::Serialize)]
 pub struct Datum {
-    #[serde(rename = "VarCharValue")]
-    pub var_char_value: String,
+    #[serde(rename = "VarCharValue", skip_serializing_if = "Option::is_none")]
+    pub var_char_value: Option<String>,
 }
 
 #[derive(serde::Serialize)]
-struct QueryExecutionResponse {
+pub struct QueryExecutionResponse {
     #[serde(rename = "QueryExecutionId")]
     query_execution_id: String,
+    #[serde(rename = "Query")]
+    query: String,
+    #[serde(rename = "StatementType")]
+    statement_type: String,
+    #[serde(
+        rename = "ResultConfiguration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    result_configuration: Option<ResultConfiguration>,
+    #[serde(
+        rename = "QueryExecutionContext",
+        skip_serializing_if = "Option::is_none"
+    )]
+    query_execution_context: Option<QueryExecutionContext>,
     #[serde(rename = "Status")]
     status: StatusResponse,
+    #[serde(rename = "WorkGroup")]
+    work_group: String,
+    #[serde(rename = "EngineVersion")]
+    engine_version: EngineVersion,
+    #[serde(rename = "Statistics", skip_serializing_if = "Option::is_none")]
+    statistics: Option<StatisticsResponse>,
+}
+
+impl QueryExecutionResponse {
+    pub fn new(query_execution_id: String, query_process: &QueryProcess, now: u64) -> Self {
+        let state = QueryExecutionState::from(query_process.state.as_ref());
+        let state_change_reason = if state == QueryExecutionState::Failed {
+            query_process.failure_reason.clone()
+        } else {
+            None
+        };
+        let athena_error = state_change_reason
+            .as_ref()
+            .map(|error_message| AthenaErrorResponse {
+                error_category: query_process.error_category,
+                error_type: query_process.error_type,
+                retryable: query_process.retryable,
+                error_message: error_message.clone(),
+            });
+        let statistics = if state == QueryExecutionState::Queued {
+            None
+        } else {
+            let end = query_process.completion_date_time.unwrap_or(now);
+            // Note: a query cancelled while QUEUED never started running.
+            let running_date_time = query_process.running_date_time.unwrap_or(end);
+            Some(StatisticsResponse {
+                engine_execution_time_in_millis: end.saturating_sub(running_date_time),
+                data_scanned_in_bytes: query_process.data_scanned_in_bytes,
+                query_queue_time_in_millis: running_date_time
+                    .saturating_sub(query_process.submission_date_time),
+                total_execution_time_in_millis: end
+                    .saturating_sub(query_process.submission_date_time),
+                result_reuse_information: ResultReuseInformationResponse {
+                    reused_previous_result: query_process.reused_previous_result,
+                },
+            })
+        };
+        QueryExecutionResponse {
+            query_execution_id,
+            query: query_process.query_string.clone(),
+            statement_type: query_process
+                .statement_kind
+                .statement_type()
+                .as_str()
+                .to_string(),
+            result_configuration: Some(ResultConfiguration {
+                output_location: query_process.output_location.clone(),
+                encryption_configuration: query_process.encryption_configuration.clone(),
+                expected_bucket_owner: query_process.expected_bucket_owner.clone(),
+            }),
+            query_execution_context: if query_process.database.is_some()
+                || query_process.catalog.is_some()
+            {
+                Some(QueryExecutionContext {
+                    database: query_process.database.clone(),
+                    catalog: query_process.catalog.clone(),
+                })
+            } else {
+                None
+            },
+            statistics,
+            work_group: query_process.work_group.clone(),
+            engine_version: query_process.engine_version.clone(),
+            status: StatusResponse {
+                state,
+                state_change_reason,
+                athena_error,
+                submission_date_time: epoch_secs(query_process.submission_date_time),
+                completion_date_time: query_process.completion_date_time.map(epoch_secs),
+            },
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct StatisticsResponse {
+    #[serde(rename = "EngineExecutionTimeInMillis")]
+    engine_execution_time_in_millis: u64,
+    #[serde(rename = "DataScannedInBytes")]
+    data_scanned_in_bytes: u64,
+    #[serde(rename = "QueryQueueTimeInMillis")]
+    query_queue_time_in_millis: u64,
+    #[serde(rename = "TotalExecutionTimeInMillis")]
+    total_execution_time_in_millis: u64,
+    #[serde(rename = "ResultReuseInformation")]
+    result_reuse_information: ResultReuseInformationResponse,
+}
+
+#[derive(serde::Serialize)]
+struct ResultReuseInformationResponse {
+    #[serde(rename = "ReusedPreviousResult")]
+    reused_previous_result: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct GetQueryRuntimeStatisticsResponse {
+    #[serde(rename = "QueryRuntimeStatistics")]
+    query_runtime_statistics: QueryRuntimeStatisticsResponse,
+}
+
+impl GetQueryRuntimeStatisticsResponse {
+    /// The timeline known so far, e.g. only the queue time of a `QUEUED` query, and the `rows`
+    /// of a `SUCCEEDED` one.
+    pub fn new(
+        query_process: &QueryProcess,
+        rows: Option<QueryRuntimeStatisticsRows>,
+        now: u64,
+    ) -> Self {
+        let end = query_process.completion_date_time.unwrap_or(now);
+        let running_date_time = query_process.running_date_time;
+        let terminal = query_process.is_terminal();
+        GetQueryRuntimeStatisticsResponse {
+            query_runtime_statistics: QueryRuntimeStatisticsResponse {
+                timeline: QueryRuntimeStatisticsTimeline {
+                    query_queue_time_in_millis: running_date_time
+                        .unwrap_or(end)
+                        .saturating_sub(query_process.submission_date_time),
+                    // Note: planning and service processing take no time here.
+                    query_planning_time_in_millis: terminal.then_some(0),
+                    // Note: a query which finished without RUNNING, e.g. cancelled while QUEUED,
+                    // ran for no time.
+                    engine_execution_time_in_millis: running_date_time
+                        .or(terminal.then_some(end))
+                        .map(|v| end.saturating_sub(v)),
+                    service_processing_time_in_millis: terminal.then_some(0),
+                    total_execution_time_in_millis: end
+                        .saturating_sub(query_process.submission_date_time),
+                },
+                rows,
+            },
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct QueryRuntimeStatisticsResponse {
+    #[serde(rename = "Timeline")]
+    timeline: QueryRuntimeStatisticsTimeline,
+    #[serde(rename = "Rows", skip_serializing_if = "Option::is_none")]
+    rows: Option<QueryRuntimeStatisticsRows>,
+}
+
+#[derive(serde::Serialize)]
+struct QueryRuntimeStatisticsTimeline {
+    #[serde(rename = "QueryQueueTimeInMillis")]
+    query_queue_time_in_millis: u64,
+    #[serde(
+        rename = "QueryPlanningTimeInMillis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    query_planning_time_in_millis: Option<u64>,
+    #[serde(
+        rename = "EngineExecutionTimeInMillis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    engine_execution_time_in_millis: Option<u64>,
+    #[serde(
+        rename = "ServiceProcessingTimeInMillis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    service_processing_time_in_millis: Option<u64>,
+    #[serde(rename = "TotalExecutionTimeInMillis")]
+    total_execution_time_in_millis: u64,
+}
+
+#[derive(serde::Serialize)]
+pub struct QueryRuntimeStatisticsRows {
+    #[serde(rename = "InputRows")]
+    pub input_rows: u64,
+    #[serde(rename = "InputBytes")]
+    pub input_bytes: u64,
+    #[serde(rename = "OutputBytes")]
+    pub output_bytes: u64,
+    #[serde(rename = "OutputRows")]
+    pub output_rows: u64,
 }
 
 #[derive(serde::Serialize)]
 pub struct StatusResponse {
     #[serde(rename = "State", serialize_with = "serialize_state")]
     pub state: QueryExecutionState,
+    #[serde(rename = "StateChangeReason", skip_serializing_if = "Option::is_none")]
+    pub state_change_reason: Option<String>,
+    #[serde(rename = "AthenaError", skip_serializing_if = "Option::is_none")]
+    pub athena_error: Option<AthenaErrorResponse>,
+    #[serde(rename = "SubmissionDateTime")]
+    pub submission_date_time: f64,
+    #[serde(rename = "CompletionDateTime", skip_serializing_if = "Option::is_none")]
+    pub completion_date_time: Option<f64>,
+}
+
+#[derive(serde::Serialize)]
+pub struct AthenaErrorResponse {
+    #[serde(rename = "ErrorCategory")]
+    pub error_category: u64,
+    #[serde(rename = "ErrorType")]
+    pub error_type: u64,
+    #[serde(rename = "Retryable")]
+    pub retryable: bool,
+    #[serde(rename = "ErrorMessage")]
+    pub error_message: String,
 }
 
 fn serialize_state<S: serde::Serializer>(
@@ -140,27 +911,747 @@ fn serialize_state<S: serde::Serializer>(
     s.serialize_str(state.as_ref())
 }
 
-#[derive(serde::Deserialize)]
-pub struct Param {
+#[derive(serde::Serialize)]
+pub struct ErrorResponse {
+    #[serde(rename = "__type")]
+    error_type: String,
+    #[serde(rename = "Message")]
+    message: String,
+}
+
+impl ErrorResponse {
+    pub fn new(error_type: &str, message: String) -> Self {
+        ErrorResponse {
+            error_type: error_type.to_string(),
+            message,
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct StartQueryExecutionInput {
+    #[serde(rename = "QueryString")]
+    pub query_string: String,
+    #[serde(rename = "ClientRequestToken")]
+    pub client_request_token: Option<String>,
+    #[serde(rename = "QueryExecutionContext")]
+    pub query_execution_context: Option<QueryExecutionContext>,
+    #[serde(rename = "ResultConfiguration")]
+    pub result_configuration: Option<ResultConfiguration>,
+    #[serde(rename = "WorkGroup")]
+    pub work_group: Option<String>,
+    #[serde(rename = "EngineVersion")]
+    pub engine_version: Option<EngineVersion>,
+    #[serde(rename = "ResultReuseConfiguration")]
+    pub result_reuse_configuration: Option<ResultReuseConfiguration>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetQueryExecutionInput {
+    #[serde(rename = "QueryExecutionId")]
+    pub query_execution_id: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct BatchGetQueryExecutionInput {
+    #[serde(rename = "QueryExecutionIds")]
+    pub query_execution_ids: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetQueryResultsInput {
     #[serde(rename = "QueryExecutionId")]
-    pub query_execution_id: Option<String>,
+    pub query_execution_id: String,
+    #[serde(rename = "NextToken")]
+    pub next_token: Option<String>,
+    #[serde(rename = "MaxResults")]
+    pub max_results: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetQueryRuntimeStatisticsInput {
+    #[serde(rename = "QueryExecutionId")]
+    pub query_execution_id: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct StopQueryExecutionInput {
+    #[serde(rename = "QueryExecutionId")]
+    pub query_execution_id: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ListQueryExecutionsInput {
+    #[serde(rename = "NextToken")]
+    pub next_token: Option<String>,
+    #[serde(rename = "MaxResults")]
+    pub max_results: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CreateNamedQueryInput {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Description")]
+    pub description: Option<String>,
+    #[serde(rename = "Database")]
+    pub database: String,
+    #[serde(rename = "QueryString")]
+    pub query_string: String,
+    #[serde(rename = "WorkGroup")]
+    pub work_group: Option<String>,
+    #[serde(rename = "ClientRequestToken")]
+    pub client_request_token: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetNamedQueryInput {
+    #[serde(rename = "NamedQueryId")]
+    pub named_query_id: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DeleteNamedQueryInput {
+    #[serde(rename = "NamedQueryId")]
+    pub named_query_id: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct UpdateNamedQueryInput {
+    #[serde(rename = "NamedQueryId")]
+    pub named_query_id: String,
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Description")]
+    pub description: Option<String>,
     #[serde(rename = "QueryString")]
-    pub query_string: Option<String>,
+    pub query_string: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct BatchGetNamedQueryInput {
+    #[serde(rename = "NamedQueryIds")]
+    pub named_query_ids: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ListNamedQueriesInput {
+    #[serde(rename = "NextToken")]
+    pub next_token: Option<String>,
+    #[serde(rename = "MaxResults")]
+    pub max_results: Option<u64>,
+    #[serde(rename = "WorkGroup")]
+    pub work_group: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CreateWorkGroupInput {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Configuration")]
+    pub configuration: Option<WorkGroupConfiguration>,
+    #[serde(rename = "Description")]
+    pub description: Option<String>,
+    #[serde(rename = "Tags", default)]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetWorkGroupInput {
+    #[serde(rename = "WorkGroup")]
+    pub work_group: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ListWorkGroupsInput {
+    #[serde(rename = "NextToken")]
+    pub next_token: Option<String>,
+    #[serde(rename = "MaxResults")]
+    pub max_results: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct UpdateWorkGroupInput {
+    #[serde(rename = "WorkGroup")]
+    pub work_group: String,
+    #[serde(rename = "Description")]
+    pub description: Option<String>,
+    #[serde(rename = "ConfigurationUpdates")]
+    pub configuration_updates: Option<WorkGroupConfigurationUpdates>,
+    #[serde(rename = "State")]
+    pub state: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DeleteWorkGroupInput {
+    #[serde(rename = "WorkGroup")]
+    pub work_group: String,
+    #[serde(rename = "RecursiveDeleteOption")]
+    pub recursive_delete_option: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CreateDataCatalogInput {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Type")]
+    pub catalog_type: String,
+    #[serde(rename = "Description")]
+    pub description: Option<String>,
+    #[serde(rename = "Parameters")]
+    pub parameters: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetDataCatalogInput {
+    #[serde(rename = "Name")]
+    pub name: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ListDataCatalogsInput {
     #[serde(rename = "NextToken")]
     pub next_token: Option<String>,
     #[serde(rename = "MaxResults")]
     pub max_results: Option<u64>,
 }
 
+#[derive(Debug, serde::Deserialize)]
+pub struct ListDatabasesInput {
+    #[serde(rename = "CatalogName")]
+    pub catalog_name: String,
+    #[serde(rename = "NextToken")]
+    pub next_token: Option<String>,
+    #[serde(rename = "MaxResults")]
+    pub max_results: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetDatabaseInput {
+    #[serde(rename = "CatalogName")]
+    pub catalog_name: String,
+    #[serde(rename = "DatabaseName")]
+    pub database_name: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ListTableMetadataInput {
+    #[serde(rename = "CatalogName")]
+    pub catalog_name: String,
+    #[serde(rename = "DatabaseName")]
+    pub database_name: String,
+    #[serde(rename = "Expression")]
+    pub expression: Option<String>,
+    #[serde(rename = "NextToken")]
+    pub next_token: Option<String>,
+    #[serde(rename = "MaxResults")]
+    pub max_results: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetTableMetadataInput {
+    #[serde(rename = "CatalogName")]
+    pub catalog_name: String,
+    #[serde(rename = "DatabaseName")]
+    pub database_name: String,
+    #[serde(rename = "TableName")]
+    pub table_name: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CreatePreparedStatementInput {
+    #[serde(rename = "StatementName")]
+    pub statement_name: String,
+    #[serde(rename = "WorkGroup")]
+    pub work_group: String,
+    #[serde(rename = "QueryStatement")]
+    pub query_statement: String,
+    #[serde(rename = "Description")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GetPreparedStatementInput {
+    #[serde(rename = "StatementName")]
+    pub statement_name: String,
+    #[serde(rename = "WorkGroup")]
+    pub work_group: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ListPreparedStatementsInput {
+    #[serde(rename = "WorkGroup")]
+    pub work_group: String,
+    #[serde(rename = "NextToken")]
+    pub next_token: Option<String>,
+    #[serde(rename = "MaxResults")]
+    pub max_results: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DeletePreparedStatementInput {
+    #[serde(rename = "StatementName")]
+    pub statement_name: String,
+    #[serde(rename = "WorkGroup")]
+    pub work_group: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct TagResourceInput {
+    #[serde(rename = "ResourceARN")]
+    pub resource_arn: String,
+    #[serde(rename = "Tags")]
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct UntagResourceInput {
+    #[serde(rename = "ResourceARN")]
+    pub resource_arn: String,
+    #[serde(rename = "TagKeys")]
+    pub tag_keys: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ListTagsForResourceInput {
+    #[serde(rename = "ResourceARN")]
+    pub resource_arn: String,
+    #[serde(rename = "NextToken")]
+    pub next_token: Option<String>,
+    #[serde(rename = "MaxResults")]
+    pub max_results: Option<u64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Tag {
+    #[serde(rename = "Key")]
+    pub key: String,
+    #[serde(rename = "Value", default)]
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ResultConfiguration {
+    #[serde(rename = "OutputLocation", skip_serializing_if = "Option::is_none")]
+    pub output_location: Option<String>,
+    #[serde(
+        rename = "EncryptionConfiguration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub encryption_configuration: Option<EncryptionConfiguration>,
+    #[serde(
+        rename = "ExpectedBucketOwner",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expected_bucket_owner: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EncryptionConfiguration {
+    // Note: SSE_S3, SSE_KMS or CSE_KMS, validated by the operations which take it.
+    #[serde(rename = "EncryptionOption")]
+    pub encryption_option: String,
+    #[serde(rename = "KmsKey", skip_serializing_if = "Option::is_none")]
+    pub kms_key: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ResultReuseConfiguration {
+    #[serde(rename = "ResultReuseByAgeConfiguration")]
+    pub result_reuse_by_age_configuration: Option<ResultReuseByAgeConfiguration>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ResultReuseByAgeConfiguration {
+    #[serde(rename = "Enabled")]
+    pub enabled: bool,
+    #[serde(rename = "MaxAgeInMinutes")]
+    pub max_age_in_minutes: Option<u64>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct QueryExecutionContext {
+    #[serde(rename = "Database", skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    #[serde(rename = "Catalog", skip_serializing_if = "Option::is_none")]
+    pub catalog: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct WorkGroupConfiguration {
+    #[serde(
+        rename = "ResultConfiguration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub result_configuration: Option<ResultConfiguration>,
+    #[serde(rename = "EnforceWorkGroupConfiguration", default)]
+    pub enforce_work_group_configuration: bool,
+    #[serde(rename = "PublishCloudWatchMetricsEnabled", default)]
+    pub publish_cloud_watch_metrics_enabled: bool,
+    #[serde(
+        rename = "BytesScannedCutoffPerQuery",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bytes_scanned_cutoff_per_query: Option<u64>,
+    #[serde(rename = "RequesterPaysEnabled", default)]
+    pub requester_pays_enabled: bool,
+    #[serde(rename = "EngineVersion", skip_serializing_if = "Option::is_none")]
+    pub engine_version: Option<EngineVersion>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct WorkGroupConfigurationUpdates {
+    #[serde(rename = "ResultConfigurationUpdates")]
+    pub result_configuration_updates: Option<ResultConfigurationUpdates>,
+    #[serde(rename = "EnforceWorkGroupConfiguration")]
+    pub enforce_work_group_configuration: Option<bool>,
+    #[serde(rename = "PublishCloudWatchMetricsEnabled")]
+    pub publish_cloud_watch_metrics_enabled: Option<bool>,
+    #[serde(rename = "BytesScannedCutoffPerQuery")]
+    pub bytes_scanned_cutoff_per_query: Option<u64>,
+    #[serde(rename = "RemoveBytesScannedCutoffPerQuery")]
+    pub remove_bytes_scanned_cutoff_per_query: Option<bool>,
+    #[serde(rename = "RequesterPaysEnabled")]
+    pub requester_pays_enabled: Option<bool>,
+    #[serde(rename = "EngineVersion")]
+    pub engine_version: Option<EngineVersion>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ResultConfigurationUpdates {
+    #[serde(rename = "OutputLocation")]
+    pub output_location: Option<String>,
+    #[serde(rename = "RemoveOutputLocation")]
+    pub remove_output_location: Option<bool>,
+    #[serde(rename = "EncryptionConfiguration")]
+    pub encryption_configuration: Option<EncryptionConfiguration>,
+    #[serde(rename = "RemoveEncryptionConfiguration")]
+    pub remove_encryption_configuration: Option<bool>,
+    #[serde(rename = "ExpectedBucketOwner")]
+    pub expected_bucket_owner: Option<String>,
+    #[serde(rename = "RemoveExpectedBucketOwner")]
+    pub remove_expected_bucket_owner: Option<bool>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EngineVersion {
+    #[serde(
+        rename = "SelectedEngineVersion",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub selected_engine_version: Option<String>,
+    #[serde(
+        rename = "EffectiveEngineVersion",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub effective_engine_version: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StateDurations {
+    pub queued: Duration,
+    pub running: Duration,
+}
+
+/// AthenaError reported for failed queries, unless overridden by query hints.
+#[derive(Clone)]
+pub struct FailureSettings {
+    pub error_category: u64,
+    pub error_type: u64,
+    pub retryable: bool,
+    pub error_message: Option<String>,
+}
+
+#[derive(Clone)]
 pub struct AppData {
-    pub process_interval: Duration,
+    pub immediate: bool,
+    pub validate_sql: bool,
+    pub state_durations: StateDurations,
+    pub transition_plan: Arc<dyn crate::transition::TransitionPlan>,
     pub csv_fixture_dir: String,
-    pub processes_r: evmap::ReadHandle<String, QueryProcess>,
-    pub processes_w: Arc<Mutex<evmap::WriteHandle<String, QueryProcess>>>,
+    pub fail_query_marker: String,
+    pub fail_query_pattern: Option<Regex>,
+    pub failure: FailureSettings,
+    pub chaos_failure_rate: f64,
+    pub chaos_rng: Arc<Mutex<StdRng>>,
+    pub query_timeout: Option<Duration>,
+    pub query_timeout_message: String,
+    pub throttle_rate: f64,
+    // Note: a generator for each operation, so whether a request of one is throttled doesn't
+    // depend on the requests of the others.
+    pub throttle_rngs: Arc<Mutex<HashMap<String, StdRng>>>,
+    pub deterministic_throttling: bool,
+    pub data_scanned_in_bytes: Option<u64>,
+    pub include_header_row: bool,
+    pub output_dir: Option<String>,
+    pub default_output_location: String,
+    pub default_encryption_configuration: Option<EncryptionConfiguration>,
+    pub output_metadata: bool,
+    pub query_execution_id_counter: Option<Arc<AtomicU64>>,
+    pub engine_version: String,
+    pub max_concurrent_queries: Option<usize>,
+    pub max_running_queries: Option<usize>,
+    pub max_tracked_queries: Option<usize>,
+    pub completion_webhook_url: Option<String>,
+    pub engine: Option<Arc<dyn crate::engine::QueryEngine>>,
+    pub result_fixtures: Option<Arc<crate::model::fixtures::ResultFixtures>>,
+    pub clock: Arc<dyn crate::clock::Clock>,
+    pub events: Arc<crate::events::EventBus>,
+    pub processes: QueryProcesses,
+    pub process_handles: Arc<Mutex<HashMap<String, AbortHandle>>>,
+    pub client_request_tokens: ClientRequestTokens,
+    pub client_request_tokens_changed: Arc<Condvar>,
+    // Note: QUEUED executions waiting for a running slot, in FIFO order.
+    pub waiting_queries: Arc<Mutex<VecDeque<String>>>,
+    pub named_queries: NamedQueries,
+    pub work_groups: WorkGroups,
+    pub data_catalogs: DataCatalogs,
+    pub prepared_statements: PreparedStatements,
+    pub tags: Tags,
+    #[cfg(feature = "s3")]
+    pub result_uploader: Option<Arc<crate::upload::ResultUploader>>,
+}
+
+/// Values of a row of a query result, `None` for a NULL.
+pub type Record = Vec<Option<String>>;
+
+/// Columns and rows of a query run by a `QueryEngine`.
+#[derive(Debug)]
+pub struct QueryResult {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Record>,
+}
+
+pub type QueryProcesses = Arc<RwLock<HashMap<String, QueryProcess>>>;
+
+// Note: ClientRequestToken -> (QueryString, QueryExecutionId), the id is `None` while the
+// execution is being started.
+pub type ClientRequestTokens = Arc<Mutex<HashMap<String, (String, Option<String>)>>>;
+
+pub type NamedQueries = Arc<RwLock<HashMap<String, NamedQuery>>>;
+
+pub type WorkGroups = Arc<RwLock<HashMap<String, WorkGroup>>>;
+
+/// A workgroup, `primary` is there from the start.
+#[derive(Debug, Clone)]
+pub struct WorkGroup {
+    pub description: Option<String>,
+    pub state: String,
+    pub configuration: WorkGroupConfiguration,
+    pub creation_time: u64,
+}
+
+// Note: keyed by (WorkGroup, StatementName), a statement name is unique within its workgroup.
+pub type PreparedStatements = Arc<RwLock<HashMap<(String, String), PreparedStatement>>>;
+
+/// A prepared statement of a workgroup.
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+    pub query_statement: String,
+    pub description: Option<String>,
+    pub last_modified_time: u64,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, evmap_derive::ShallowCopy)]
+// Note: `workgroup/name` or `datacatalog/name` -> the tags of the resource, by key.
+pub type Tags = Arc<RwLock<HashMap<String, BTreeMap<String, String>>>>;
+
+pub type DataCatalogs = Arc<RwLock<HashMap<String, DataCatalog>>>;
+
+/// A data catalog, `AwsDataCatalog` is there from the start.
+#[derive(Debug, Clone)]
+pub struct DataCatalog {
+    pub catalog_type: String,
+    pub description: Option<String>,
+    pub parameters: BTreeMap<String, String>,
+    pub databases: BTreeMap<String, Database>,
+}
+
+/// A database of a data catalog, as seeded by `DATABASES` or `DATABASES_FILE`.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct Database {
+    #[serde(rename = "Description")]
+    pub description: Option<String>,
+    #[serde(rename = "Parameters", default)]
+    pub parameters: BTreeMap<String, String>,
+    #[serde(rename = "Tables", default)]
+    pub tables: Vec<Table>,
+}
+
+/// A table of a database, given in `DATABASES_FILE` or made of a fixture of `CSV_FIXTURE_DIR`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Table {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "TableType", default = "external_table")]
+    pub table_type: String,
+    #[serde(rename = "Columns", default)]
+    pub columns: Vec<TableColumn>,
+    #[serde(rename = "PartitionKeys", default)]
+    pub partition_keys: Vec<TableColumn>,
+    #[serde(rename = "Parameters", default)]
+    pub parameters: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TableColumn {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Type", default = "varchar")]
+    pub column_type: String,
+    #[serde(rename = "Comment", skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+impl From<&Column> for TableColumn {
+    fn from(column: &Column) -> Self {
+        TableColumn {
+            name: column.name.clone(),
+            column_type: column.column_type.clone(),
+            comment: None,
+        }
+    }
+}
+
+fn external_table() -> String {
+    EXTERNAL_TABLE_TYPE.to_string()
+}
+
+/// A query saved by CreateNamedQuery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedQuery {
+    pub name: String,
+    pub description: Option<String>,
+    pub database: String,
+    pub query_string: String,
+    pub work_group: String,
+    pub client_request_token: Option<String>,
+    // Note: the order it was created in, as ListNamedQueries lists them.
+    pub sequence: u64,
+}
+
+#[derive(Debug, Clone)]
 pub struct QueryProcess {
+    pub query_string: String,
     pub table_name: String,
     pub state: String,
+    pub outcome: String,
+    pub failure_reason: Option<String>,
+    pub error_category: u64,
+    pub error_type: u64,
+    pub retryable: bool,
+    pub submission_date_time: u64,
+    pub running_date_time: Option<u64>,
+    pub completion_date_time: Option<u64>,
+    pub data_scanned_in_bytes: u64,
+    pub output_location: Option<String>,
+    pub encryption_configuration: Option<EncryptionConfiguration>,
+    pub expected_bucket_owner: Option<String>,
+    pub database: Option<String>,
+    pub catalog: Option<String>,
+    pub work_group: String,
+    pub engine_version: EngineVersion,
+    pub result: Option<Arc<QueryResult>>,
+    pub result_fixture: Option<String>,
+    pub statement_kind: crate::statement::StatementKind,
+    pub update_count: Option<u64>,
+    // Note: rows and columns of the result made up by `minerva:generate`.
+    pub generated: Option<(u64, usize)>,
+    // Note: set when the result of an earlier execution of the query is reused.
+    pub reused_previous_result: bool,
+}
+
+impl QueryProcess {
+    pub fn final_state(&self) -> QueryExecutionState {
+        QueryExecutionState::from(self.outcome.as_ref())
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            QueryExecutionState::from(self.state.as_ref()),
+            QueryExecutionState::Succeeded
+                | QueryExecutionState::Failed
+                | QueryExecutionState::Cancelled
+        )
+    }
+
+    pub fn with_state(&self, state: QueryExecutionState, now: u64) -> Self {
+        let running_date_time = match state {
+            QueryExecutionState::Running => Some(now),
+            _ => self.running_date_time,
+        };
+        let completion_date_time = match state {
+            QueryExecutionState::Succeeded
+            | QueryExecutionState::Failed
+            | QueryExecutionState::Cancelled => Some(now),
+            _ => None,
+        };
+        // Note: a query failed later on, e.g. by the admin API, scans nothing either.
+        let data_scanned_in_bytes = match state {
+            QueryExecutionState::Failed => 0,
+            _ => self.data_scanned_in_bytes,
+        };
+        QueryProcess {
+            state: state.as_str().to_string(),
+            running_date_time,
+            completion_date_time,
+            data_scanned_in_bytes,
+            ..self.clone()
+        }
+    }
+}
+
+// Note: the AWS JSON protocol encodes timestamps as epoch seconds.
+fn epoch_secs(millis: u64) -> f64 {
+    millis as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn result_set_metadata_describes_each_column_like_athena() {
+        let columns = [
+            "bigint",
+            "varchar",
+            "double",
+            "boolean",
+            "decimal(10,2)",
+            "timestamp",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, column_type)| Column {
+            name: format!("c{:}", i),
+            column_type: column_type.to_string(),
+        })
+        .collect::<Vec<_>>();
+        let metadata = serde_json::to_value(ResultSetMetadata::new("t", &columns)).unwrap();
+        let column_info = |name: &str, column_type: &str, precision: u32, scale: u32| {
+            json!({
+                "TableName": "t",
+                "Name": name,
+                "Label": name,
+                "Type": column_type,
+                "Precision": precision,
+                "Scale": scale,
+                "Nullable": "UNKNOWN",
+                "CaseSensitive": column_type == "varchar",
+            })
+        };
+        assert_eq!(
+            metadata,
+            json!({
+                "ColumnInfo": [
+                    column_info("c0", "bigint", 19, 0),
+                    column_info("c1", "varchar", 2147483647, 0),
+                    column_info("c2", "double", 17, 0),
+                    column_info("c3", "boolean", 0, 0),
+                    column_info("c4", "decimal(10,2)", 10, 2),
+                    column_info("c5", "timestamp", 3, 0),
+                ]
+            })
+        );
+    }
 }
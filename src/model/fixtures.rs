@@ -0,0 +1,631 @@
+use regex::Regex;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::rc::Rc;
+use std::sync::Arc;
+
+const MAPPINGS_FILE: &str = "mappings.json";
+
+const SCHEMA_SUFFIX: &str = ".schema.json";
+pub const TYPE_INFERENCE_SAMPLE_ROWS: usize = 1000;
+
+const QUERY_KEY: &str = "query:";
+const PATTERN_KEY: &str = "pattern:";
+
+#[derive(serde::Deserialize)]
+struct Mapping {
+    pattern: String,
+    fixture: String,
+}
+
+// Note: a column is either its name, a varchar, or `{"name": "id", "type": "bigint"}`.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum JsonColumn {
+    Name(String),
+    Column(crate::model::Column),
+}
+
+#[derive(serde::Deserialize)]
+struct JsonFixture {
+    query: Option<String>,
+    pattern: Option<String>,
+    columns: Vec<JsonColumn>,
+    rows: Vec<Vec<Option<String>>>,
+}
+
+/// The result of a query picked from the fixtures: loaded at startup, or a fixture file read when
+/// the results are fetched.
+#[derive(Clone)]
+pub enum ResultFixture {
+    Result(Arc<crate::model::QueryResult>),
+    File(String),
+}
+
+/// Results picked by the query string from the fixtures of a directory:
+///
+/// - a `.json` file like `{"query": "SELECT 1", "columns": [{"name": "_col0", "type": "integer"}], "rows": [["1"]]}`,
+///   or `"pattern"` for a regular expression, a column without a type is a varchar and a `null`
+///   value is a NULL
+/// - a csv file whose first line is `# query: SELECT * FROM users` or `# pattern: FROM users`
+/// - the fixture files listed in `mappings.json`, e.g. `[{"pattern": "FROM users", "fixture": "users.csv"}]`
+///
+/// The exact query string wins over the patterns, which are tried in the order of the file names
+/// and then in the order of `mappings.json`.
+pub struct ResultFixtures {
+    queries: HashMap<String, Arc<crate::model::QueryResult>>,
+    patterns: Vec<(Regex, ResultFixture)>,
+}
+
+impl ResultFixtures {
+    pub fn load(dir: &str) -> io::Result<Self> {
+        let mappings = load_mappings(dir)?;
+        let mut paths = fs::read_dir(dir)
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to read {:}: {:}", dir, e)))?
+            .map(|entry| entry.map(|v| v.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        paths.sort();
+
+        let mut fixtures = ResultFixtures {
+            queries: HashMap::new(),
+            patterns: Vec::new(),
+        };
+        for path in paths {
+            // Note: the files of `mappings.json` are results only, read when they're fetched.
+            let file_name = path
+                .file_name()
+                .and_then(|v| v.to_str())
+                .unwrap_or_default();
+            if file_name.ends_with(SCHEMA_SUFFIX)
+                || file_name == MAPPINGS_FILE
+                || mappings.iter().any(|(_, v)| {
+                    matches!(v, ResultFixture::File(fixture) if std::path::Path::new(fixture) == path)
+                })
+            {
+                continue;
+            }
+            let (query, pattern, result) = match path.extension().and_then(|v| v.to_str()) {
+                Some("json") => {
+                    let fixture = serde_json::from_reader::<_, JsonFixture>(BufReader::new(
+                        File::open(&path)?,
+                    ))
+                    .map_err(|e| invalid_data(format!("invalid {:}: {:}", path.display(), e)))?;
+                    (
+                        fixture.query,
+                        fixture.pattern,
+                        crate::model::QueryResult {
+                            columns: fixture
+                                .columns
+                                .into_iter()
+                                .map(|v| match v {
+                                    JsonColumn::Name(name) => crate::model::Column::varchar(&name),
+                                    JsonColumn::Column(column) => column,
+                                })
+                                .collect(),
+                            rows: fixture.rows,
+                        },
+                    )
+                }
+                Some("csv") => {
+                    load_csv_fixture(&path.to_string_lossy(), &fs::read_to_string(&path)?)
+                        .map_err(|e| invalid_data(format!("invalid {:}: {:}", path.display(), e)))?
+                }
+                _ => continue,
+            };
+            let result = Arc::new(result);
+            match (query, pattern) {
+                (Some(query), None) => {
+                    fixtures.queries.insert(query.trim().to_string(), result);
+                }
+                (None, Some(pattern)) => fixtures.patterns.push((
+                    Regex::new(&pattern).map_err(|e| {
+                        invalid_data(format!("invalid pattern in {:}: {:}", path.display(), e))
+                    })?,
+                    ResultFixture::Result(result),
+                )),
+                _ => {
+                    return Err(invalid_data(format!(
+                        "{:} needs either a query or a pattern",
+                        path.display()
+                    )))
+                }
+            }
+        }
+        fixtures.patterns.extend(mappings);
+        Ok(fixtures)
+    }
+
+    pub fn find(&self, query_string: &str) -> Option<ResultFixture> {
+        match self.queries.get(query_string.trim()) {
+            Some(result) => Some(ResultFixture::Result(result.clone())),
+            None => self
+                .patterns
+                .iter()
+                .find(|(pattern, _)| pattern.is_match(query_string))
+                .map(|(_, result)| result.clone()),
+        }
+    }
+}
+
+// Note: `mappings.json` is optional, the directory may only have the fixtures keyed by the query.
+fn load_mappings(dir: &str) -> io::Result<Vec<(Regex, ResultFixture)>> {
+    let path = format!("{:}/{:}", dir, MAPPINGS_FILE);
+    let f = match File::open(&path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(io::Error::new(
+                e.kind(),
+                format!("failed to open {:}: {:}", path, e),
+            ))
+        }
+    };
+    let mut mappings = Vec::new();
+    for mapping in serde_json::from_reader::<_, Vec<Mapping>>(BufReader::new(f))
+        .map_err(|e| invalid_data(format!("invalid {:}: {:}", path, e)))?
+    {
+        let pattern = Regex::new(&mapping.pattern)
+            .map_err(|e| invalid_data(format!("invalid pattern in {:}: {:}", path, e)))?;
+        mappings.push((
+            pattern,
+            ResultFixture::File(format!("{:}/{:}", dir, mapping.fixture)),
+        ));
+    }
+    Ok(mappings)
+}
+
+// Note: returns the query, the pattern and the result of a csv fixture.
+fn load_csv_fixture(
+    path: &str,
+    content: &str,
+) -> Result<(Option<String>, Option<String>, crate::model::QueryResult), String> {
+    let (first_line, rest) = content.split_once('\n').unwrap_or((content, ""));
+    let key = first_line.strip_prefix('#').map(str::trim).ok_or_else(|| {
+        format!(
+            "the first line must be '# {:} ...' or '# {:} ...'",
+            QUERY_KEY, PATTERN_KEY
+        )
+    })?;
+    let (query, pattern) = if let Some(query) = key.strip_prefix(QUERY_KEY) {
+        (Some(query.trim().to_string()), None)
+    } else if let Some(pattern) = key.strip_prefix(PATTERN_KEY) {
+        (None, Some(pattern.trim().to_string()))
+    } else {
+        return Err(format!("unknown key: {:}", key));
+    };
+
+    let schema = FixtureSchema::load(path).map_err(|e| e.to_string())?;
+    let mut csv_records = CsvRecords::new(rest.as_bytes(), schema.null.clone());
+    let names = csv_records.headers().map_err(|e| e.to_string())?;
+    let rows = csv_records
+        .collect::<csv::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    let columns = schema.columns(&names, &rows);
+    Ok((query, pattern, crate::model::QueryResult { columns, rows }))
+}
+
+// Note: either the types of the columns, or `{"columns": {...}, "null": "\\N"}` which also gives
+// the null sentinel of a csv fixture.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum SchemaFile {
+    Schema {
+        columns: HashMap<String, String>,
+        null: Option<String>,
+    },
+    Columns(HashMap<String, String>),
+}
+
+/// The `{fixture}.schema.json` file next to a csv or json fixture, e.g.
+/// `{"id": "bigint", "created_at": "timestamp"}`; the column types are inferred from the values of
+/// the first rows when there's none, and a csv fixture takes `null` as its NULL instead of an
+/// empty unquoted value with `{"columns": {"id": "bigint"}, "null": "\\N"}`.
+#[derive(Default)]
+pub struct FixtureSchema {
+    types: HashMap<String, String>,
+    pub null: Option<String>,
+}
+
+impl FixtureSchema {
+    pub fn load(fixture_path: &str) -> io::Result<Self> {
+        let schema_path = format!("{:}{:}", fixture_path, SCHEMA_SUFFIX);
+        let f = match File::open(&schema_path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(FixtureSchema::default()),
+            Err(e) => {
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!("failed to open {:}: {:}", schema_path, e),
+                ))
+            }
+        };
+        match serde_json::from_reader::<_, SchemaFile>(BufReader::new(f))
+            .map_err(|e| invalid_data(format!("invalid {:}: {:}", schema_path, e)))?
+        {
+            SchemaFile::Schema { columns, null } => Ok(FixtureSchema {
+                types: columns,
+                null,
+            }),
+            SchemaFile::Columns(types) => Ok(FixtureSchema { types, null: None }),
+        }
+    }
+
+    pub fn columns(
+        &self,
+        names: &[String],
+        rows: &[crate::model::Record],
+    ) -> Vec<crate::model::Column> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| crate::model::Column {
+                name: name.clone(),
+                column_type: self.types.get(name).cloned().unwrap_or_else(|| {
+                    infer_type(
+                        rows.iter()
+                            .take(TYPE_INFERENCE_SAMPLE_ROWS)
+                            .filter_map(|row| row.get(i).and_then(Option::as_ref)),
+                    )
+                    .to_string()
+                }),
+            })
+            .collect()
+    }
+}
+
+// Note: keeps what the csv reader reads, to tell a quoted field from an unquoted one.
+struct Tee<R> {
+    inner: R,
+    read: Rc<RefCell<Vec<u8>>>,
+}
+
+impl<R: Read> Read for Tee<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.borrow_mut().extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Records of a csv fixture with a header row, whose empty unquoted values are NULL while a
+/// quoted `""` is an empty string, or whose unquoted `null` sentinel values are NULL if given.
+pub struct CsvRecords<R> {
+    csv_reader: csv::Reader<Tee<R>>,
+    read: Rc<RefCell<Vec<u8>>>,
+    // Note: the offset of the first byte of `read`, bytes of the records already read are dropped.
+    read_offset: u64,
+    null: Option<String>,
+}
+
+impl<R: Read> CsvRecords<R> {
+    pub fn new(reader: R, null: Option<String>) -> Self {
+        let read = Rc::new(RefCell::new(Vec::new()));
+        let csv_reader = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(Tee {
+                inner: reader,
+                read: read.clone(),
+            });
+        CsvRecords {
+            csv_reader,
+            read,
+            read_offset: 0,
+            null,
+        }
+    }
+
+    pub fn headers(&mut self) -> csv::Result<Vec<String>> {
+        Ok(self
+            .csv_reader
+            .headers()?
+            .iter()
+            .map(String::from)
+            .collect())
+    }
+}
+
+impl<R: Read> Iterator for CsvRecords<R> {
+    type Item = csv::Result<crate::model::Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = csv::StringRecord::new();
+        match self.csv_reader.read_record(&mut record) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(e) => return Some(Err(e)),
+        }
+        let start = record.position().map_or(self.read_offset, |v| v.byte());
+        let end = self.csv_reader.position().byte();
+        let mut read = self.read.borrow_mut();
+        let quoted = quoted_fields(
+            &read[(start - self.read_offset) as usize..(end - self.read_offset) as usize],
+        );
+        read.drain(..(end - self.read_offset) as usize);
+        self.read_offset = end;
+        Some(Ok(record
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let is_null = match &self.null {
+                    Some(null) => value == null,
+                    None => value.is_empty(),
+                };
+                if is_null && !quoted.get(i).copied().unwrap_or(false) {
+                    None
+                } else {
+                    Some(value.to_string())
+                }
+            })
+            .collect()))
+    }
+}
+
+// Note: whether each field of a raw csv record starts with a quote, a doubled quote in a quoted
+// field flips the state twice.
+fn quoted_fields(raw: &[u8]) -> Vec<bool> {
+    let mut quoted = vec![raw.first() == Some(&b'"')];
+    let mut in_quotes = false;
+    for (i, b) in raw.iter().enumerate() {
+        match b {
+            b'"' => in_quotes = !in_quotes,
+            b',' if !in_quotes => quoted.push(raw.get(i + 1) == Some(&b'"')),
+            _ => {}
+        }
+    }
+    quoted
+}
+
+// Note: NULL and empty values are skipped, a column without any other value is a varchar,
+// and a column of both bigint and double values is a double.
+fn infer_type<'a>(values: impl Iterator<Item = &'a String>) -> &'static str {
+    let date = Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
+    let timestamp = Regex::new(r"^\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}:\d{2}(\.\d{1,9})?$").unwrap();
+    let mut inferred = None;
+    for value in values.map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        let value_type =
+            if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+                "boolean"
+            } else if value.parse::<i64>().is_ok() {
+                "bigint"
+            } else if value.parse::<f64>().is_ok()
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'))
+            {
+                "double"
+            } else if date.is_match(value) {
+                "date"
+            } else if timestamp.is_match(value) {
+                "timestamp"
+            } else {
+                return crate::model::VARCHAR_TYPE;
+            };
+        inferred = match (inferred, value_type) {
+            (None, _) => Some(value_type),
+            (Some(t), _) if t == value_type => Some(t),
+            (Some("bigint"), "double") | (Some("double"), "bigint") => Some("double"),
+            _ => return crate::model::VARCHAR_TYPE,
+        };
+    }
+    inferred.unwrap_or(crate::model::VARCHAR_TYPE)
+}
+
+/// A database of `DATABASES_FILE`, in the data catalog `catalog_name`, or `AwsDataCatalog`.
+#[derive(serde::Deserialize)]
+pub struct DatabaseFixture {
+    #[serde(rename = "CatalogName")]
+    pub catalog_name: Option<String>,
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(flatten)]
+    pub database: crate::model::Database,
+}
+
+/// Reads the databases of a json file like
+/// `[{"Name": "sales", "Description": "...", "Parameters": {"k": "v"}, "CatalogName": "hive"}]`.
+pub fn read_database_fixtures(path: &str) -> io::Result<Vec<DatabaseFixture>> {
+    let f = File::open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to open {:}: {:}", path, e)))?;
+    serde_json::from_reader(BufReader::new(f))
+        .map_err(|e| invalid_data(format!("invalid {:}: {:}", path, e)))
+}
+
+/// Reads a json fixture, an array of objects whose keys are the columns, e.g.
+/// `[{"id": 1, "name": "zaneli"}]`. A key missing from an object is a `null`.
+pub fn read_json_fixture(path: &str) -> io::Result<crate::model::QueryResult> {
+    let f = File::open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to open {:}: {:}", path, e)))?;
+    let objects = serde_json::from_reader::<_, Vec<serde_json::Map<String, serde_json::Value>>>(
+        BufReader::new(f),
+    )
+    .map_err(|e| invalid_data(format!("invalid {:}: {:}", path, e)))?;
+    let mut names = Vec::<String>::new();
+    for key in objects.iter().flat_map(|v| v.keys()) {
+        if !names.contains(key) {
+            names.push(key.clone());
+        }
+    }
+    let rows = objects
+        .iter()
+        .map(|object| {
+            names
+                .iter()
+                .map(|name| match object.get(name) {
+                    Some(serde_json::Value::String(v)) => Some(v.clone()),
+                    Some(serde_json::Value::Null) | None => None,
+                    Some(v) => Some(v.to_string()),
+                })
+                .collect()
+        })
+        .collect::<Vec<_>>();
+    let columns = FixtureSchema::load(path)?.columns(&names, &rows);
+    Ok(crate::model::QueryResult { columns, rows })
+}
+
+/// Reads a parquet fixture, whose columns are typed by its schema.
+#[cfg(feature = "parquet")]
+pub fn read_parquet_fixture(
+    path: &str,
+) -> io::Result<(
+    Vec<crate::model::Column>,
+    impl Iterator<Item = io::Result<crate::model::Record>>,
+)> {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let f = File::open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to open {:}: {:}", path, e)))?;
+    let reader = SerializedFileReader::new(f)
+        .map_err(|e| invalid_data(format!("invalid {:}: {:}", path, e)))?;
+    let columns = reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .root_schema()
+        .get_fields()
+        .iter()
+        .map(|field| crate::model::Column {
+            name: field.name().to_string(),
+            column_type: parquet_type(field),
+        })
+        .collect();
+    let path = path.to_string();
+    let rows = parquet::record::reader::RowIter::from_file_into(Box::new(reader)).map(move |row| {
+        row.map(|row| {
+            row.get_column_iter()
+                .map(|(_, field)| parquet_value(field))
+                .collect()
+        })
+        .map_err(|e| invalid_data(format!("invalid {:}: {:}", path, e)))
+    });
+    Ok((columns, rows))
+}
+
+// Note: the Athena type of a parquet column, as Athena reads it from the schema.
+#[cfg(feature = "parquet")]
+fn parquet_type(field: &parquet::schema::types::Type) -> String {
+    use parquet::basic::{ConvertedType, LogicalType, Type as PhysicalType};
+
+    let info = field.get_basic_info();
+    if field.is_group() {
+        return match info.converted_type() {
+            ConvertedType::LIST => "array",
+            ConvertedType::MAP | ConvertedType::MAP_KEY_VALUE => "map",
+            _ => "row",
+        }
+        .to_string();
+    }
+    // Note: older writers only give the converted type, newer ones the logical type.
+    let converted_type = info.converted_type();
+    let athena_type = match (info.logical_type_ref(), field.get_physical_type()) {
+        (Some(LogicalType::Decimal(v)), _) => {
+            return format!("decimal({:},{:})", v.precision, v.scale)
+        }
+        _ if converted_type == ConvertedType::DECIMAL => {
+            return format!("decimal({:},{:})", field.get_precision(), field.get_scale())
+        }
+        (Some(LogicalType::Date), _) => "date",
+        (Some(LogicalType::Timestamp(_)), _) | (_, PhysicalType::INT96) => "timestamp",
+        (Some(LogicalType::Integer(v)), _) if v.bit_width == 8 => "tinyint",
+        (Some(LogicalType::Integer(v)), _) if v.bit_width == 16 => "smallint",
+        _ => match converted_type {
+            ConvertedType::DATE => "date",
+            ConvertedType::TIMESTAMP_MILLIS | ConvertedType::TIMESTAMP_MICROS => "timestamp",
+            ConvertedType::INT_8 => "tinyint",
+            ConvertedType::INT_16 => "smallint",
+            ConvertedType::UTF8 | ConvertedType::ENUM | ConvertedType::JSON => {
+                crate::model::VARCHAR_TYPE
+            }
+            _ => match (info.logical_type_ref(), field.get_physical_type()) {
+                (_, PhysicalType::BOOLEAN) => "boolean",
+                (_, PhysicalType::INT32) => "integer",
+                (_, PhysicalType::INT64) => "bigint",
+                (_, PhysicalType::FLOAT) => "float",
+                (_, PhysicalType::DOUBLE) => "double",
+                (Some(LogicalType::String | LogicalType::Enum | LogicalType::Json), _) => {
+                    crate::model::VARCHAR_TYPE
+                }
+                _ => "varbinary",
+            },
+        },
+    };
+    athena_type.to_string()
+}
+
+// Note: formatted as Athena does, e.g. a timestamp is like `2024-01-02 03:04:05.000`.
+#[cfg(feature = "parquet")]
+fn parquet_value(field: &parquet::record::Field) -> Option<String> {
+    use parquet::record::Field;
+
+    Some(match field {
+        Field::Null => return None,
+        Field::Str(v) => v.clone(),
+        Field::Bytes(v) => String::from_utf8_lossy(v.data()).to_string(),
+        Field::TimestampMillis(_) | Field::TimestampMicros(_) => {
+            field.to_string().trim_end_matches(" +00:00").to_string()
+        }
+        field => field.to_string(),
+    })
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inferred(values: &[&str]) -> &'static str {
+        infer_type(
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .iter(),
+        )
+    }
+
+    #[test]
+    fn infer_type_reads_the_type_the_values_share() {
+        assert_eq!(inferred(&["1", "-2", "30"]), "bigint");
+        assert_eq!(inferred(&["1.5", "2e3"]), "double");
+        assert_eq!(inferred(&["true", "FALSE"]), "boolean");
+        assert_eq!(inferred(&["2022-01-31"]), "date");
+        assert_eq!(
+            inferred(&["2022-01-31 12:34:56", "2022-01-31T12:34:56.789"]),
+            "timestamp"
+        );
+        assert_eq!(inferred(&["foo", "1"]), "varchar");
+    }
+
+    #[test]
+    fn infer_type_of_an_empty_column_is_varchar() {
+        assert_eq!(inferred(&[]), "varchar");
+        assert_eq!(inferred(&["", " "]), "varchar");
+    }
+
+    #[test]
+    fn infer_type_promotes_mixed_integers_and_floats_to_double() {
+        assert_eq!(inferred(&["1", "", "2.5", "3"]), "double");
+        assert_eq!(inferred(&["1", "true"]), "varchar");
+    }
+
+    #[test]
+    fn fixture_schema_overrides_the_inferred_types() {
+        let dir = crate::operation::tests::fixture_dir(&[(
+            "users.csv.schema.json",
+            r#"{"id": "varchar"}"#,
+        )]);
+        let schema = FixtureSchema::load(&format!("{:}/users.csv", dir)).unwrap();
+        let names = vec!["id".to_string(), "score".to_string()];
+        let rows = vec![vec![Some("1".to_string()), Some("2.5".to_string())]];
+        let column_types = schema
+            .columns(&names, &rows)
+            .into_iter()
+            .map(|v| v.column_type)
+            .collect::<Vec<_>>();
+        assert_eq!(column_types, ["varchar", "double"]);
+    }
+}
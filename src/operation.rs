@@ -1,258 +1,4450 @@
 use actix_rt::spawn;
 use actix_rt::time;
+use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, Result};
 use aws_sdk_athena::model::QueryExecutionState;
+use futures::future::{abortable, AbortHandle};
+use rand::Rng;
+use regex::Regex;
 use sqlparser::ast::{ObjectName, SetExpr, Statement, TableFactor};
-use sqlparser::dialect::GenericDialect;
-use sqlparser::parser::Parser;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
 use std::io::BufReader;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, LazyLock};
 use std::time::Duration;
 use uuid::Uuid;
 
+pub const AMZ_JSON_CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+const INVALID_REQUEST_EXCEPTION: &str = "InvalidRequestException";
+const INTERNAL_SERVER_EXCEPTION: &str = "InternalServerException";
+const IDEMPOTENT_PARAMETER_MISMATCH_EXCEPTION: &str = "IdempotentParameterMismatchException";
+const TOO_MANY_REQUESTS_EXCEPTION: &str = "TooManyRequestsException";
+const METADATA_EXCEPTION: &str = "MetadataException";
+const RESOURCE_NOT_FOUND_EXCEPTION: &str = "ResourceNotFoundException";
+
+const EVICTION_INTERVAL: Duration = Duration::from_secs(1);
+const RUNNING_SLOT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const AUTO_ENGINE_VERSION: &str = "AUTO";
+const CSV_FIXTURE_EXTENSION: &str = "csv";
+const JSON_FIXTURE_EXTENSION: &str = "json";
+const PARQUET_FIXTURE_EXTENSION: &str = "parquet";
+const FIXTURE_EXTENSIONS: [&str; 3] = [
+    CSV_FIXTURE_EXTENSION,
+    JSON_FIXTURE_EXTENSION,
+    PARQUET_FIXTURE_EXTENSION,
+];
+// Note: Athena names columns without a name like this.
+const EMPTY_RESULT_COLUMN_NAME: &str = "_col0";
+
+const MAX_LIST_QUERY_EXECUTIONS_RESULTS: u64 = 50;
+const MAX_BATCH_GET_QUERY_EXECUTION_IDS: usize = 50;
+const MAX_GET_QUERY_RESULTS_RESULTS: u64 = 1000;
+const MAX_LIST_NAMED_QUERIES_RESULTS: u64 = 50;
+const MAX_BATCH_GET_NAMED_QUERY_IDS: usize = 50;
+const NAMED_QUERY_NOT_FOUND_ERROR_CODE: &str = "NAMED_QUERY_NOT_FOUND";
+
+const PRIMARY_WORK_GROUP: &str = "primary";
+const WORK_GROUP_STATE_ENABLED: &str = "ENABLED";
+const WORK_GROUP_STATE_DISABLED: &str = "DISABLED";
+const MAX_LIST_WORK_GROUPS_RESULTS: u64 = 50;
+const MAX_LIST_PREPARED_STATEMENTS_RESULTS: u64 = 50;
+const MAX_LIST_TAGS_FOR_RESOURCE_RESULTS: u64 = 100;
+const MAX_TAGS: usize = 50;
+const MAX_TAG_KEY_LENGTH: usize = 128;
+const MAX_TAG_VALUE_LENGTH: usize = 256;
+const RESOURCE_TYPE_WORK_GROUP: &str = "workgroup";
+const RESOURCE_TYPE_DATA_CATALOG: &str = "datacatalog";
+const AWS_DATA_CATALOG: &str = "AwsDataCatalog";
+const DATA_CATALOG_TYPE_GLUE: &str = "GLUE";
+const DATA_CATALOG_TYPES: [&str; 3] = ["LAMBDA", "GLUE", "HIVE"];
+const MAX_LIST_DATA_CATALOGS_RESULTS: u64 = 50;
+const MAX_LIST_DATABASES_RESULTS: u64 = 50;
+const MAX_LIST_TABLE_METADATA_RESULTS: u64 = 50;
+const TABLE_CLASSIFICATION_PARAMETER: &str = "classification";
+const ENCRYPTION_OPTION_SSE_S3: &str = "SSE_S3";
+const ENCRYPTION_OPTIONS: [&str; 3] = [ENCRYPTION_OPTION_SSE_S3, "SSE_KMS", "CSE_KMS"];
+const DEFAULT_RESULT_REUSE_MAX_AGE_IN_MINUTES: u64 = 60;
+const MAX_RESULT_REUSE_MAX_AGE_IN_MINUTES: u64 = 10080;
+
+static WORK_GROUP_NAME_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9._-]{1,128}$").unwrap());
+static STATEMENT_NAME_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_@:]{1,256}$").unwrap());
+static DATA_CATALOG_NAME_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9_@-]{1,127}$").unwrap());
+
 pub fn start_query_execution(
-    input: &crate::model::Param,
+    input: &crate::model::StartQueryExecutionInput,
     data: &crate::model::AppData,
 ) -> Result<HttpResponse> {
-    let query_string = input
-        .query_string
+    let query_string = input.query_string.clone();
+    let work_group_name = input
+        .work_group
         .clone()
-        .ok_or_else(|| HttpResponse::BadRequest().body("unexpected input".to_string()))?;
-    let dialect = GenericDialect {};
-    let ast = Parser::parse_sql(&dialect, &query_string).map_err(|_| {
-        HttpResponse::BadRequest().body(format!("invalid query: {:}", query_string))
-    })?;
-    if ast.len() != 1 {
-        return Ok(HttpResponse::BadRequest().body(format!(
-            "unsupported query: {:}, ast.len() = {:}",
-            query_string,
-            ast.len()
+        .unwrap_or_else(|| PRIMARY_WORK_GROUP.to_string());
+    let work_group = data
+        .work_groups
+        .read()
+        .unwrap()
+        .get(&work_group_name)
+        .cloned()
+        .ok_or_else(|| work_group_not_found(&work_group_name))?;
+    if work_group.state == WORK_GROUP_STATE_DISABLED {
+        return Ok(invalid_request(format!(
+            "WorkGroup {:} is disabled",
+            work_group_name
         )));
     }
-    let table_name = match &ast[0] {
-        Statement::Query(query) => match &query.body {
-            SetExpr::Select(select) => {
-                if select.from.len() != 1 {
-                    return Ok(HttpResponse::BadRequest().body(format!(
-                        "unsupported query: {:}, select.from.len() = {:}",
-                        query_string,
-                        select.from.len()
-                    )));
-                }
-                match &select.from[0].relation {
-                    TableFactor::Table {
-                        name: ObjectName(name),
-                        alias: _,
-                        args: _,
-                        with_hints: _,
-                    } => {
-                        if name.len() == 1 {
-                            // Note: only `tablename`
-                            &name[0].value
-                        } else if name.len() == 2 {
-                            // Note: `databasename.tablename`
-                            &name[1].value
-                        } else {
-                            return Ok(HttpResponse::BadRequest().body(format!(
-                                "unsupported query: {:}, name.len() = {:}",
-                                query_string,
-                                name.len()
-                            )));
-                        }
-                    }
-                    relation => {
-                        return Ok(HttpResponse::BadRequest().body(format!(
-                            "unsupported query: {:}, relation = {:?}",
-                            query_string, relation
-                        )))
-                    }
-                }
-            }
-            stmt => {
-                return Ok(HttpResponse::BadRequest().body(format!(
-                    "unsupported query: {:}, statement = {:?}",
-                    query_string, stmt
+    if let Some(message) = input
+        .result_configuration
+        .as_ref()
+        .and_then(|v| v.encryption_configuration.as_ref())
+        .and_then(|v| encryption_configuration_error(v, "resultConfiguration"))
+    {
+        return Ok(invalid_request(message));
+    }
+
+    let client_request_token = match reserve_client_request_token(input, data) {
+        Ok(v) => v,
+        Err(response) => return Ok(response),
+    };
+
+    let statement_kind = crate::statement::StatementKind::classify(&query_string);
+    let hint = crate::hint::Hint::parse(&query_string);
+    // Note: a syntax error makes the query end in FAILED, unless VALIDATE_SQL is disabled.
+    // DDL is written in the Hive dialect, which the parser only partly knows, so it's never failed.
+    let metadata_query = crate::metadata::MetadataQuery::parse(&query_string);
+    let (table_name, syntax_error) = match crate::syntax::parse(&query_string) {
+        // Note: SHOW and DESCRIBE are written in the Hive dialect as well, and like EXPLAIN read
+        // no table.
+        _ if metadata_query.is_some() => (String::new(), None),
+        // Note: a query engine or a result fixture also serves statements other than SELECT,
+        // and an INSERT or CTAS without a table to read from has an UpdateCount of 0.
+        Ok(ast)
+            if data.engine.is_some()
+                || data.result_fixtures.is_some()
+                || hint.generate
+                || !statement_kind.has_rows() =>
+        {
+            (table_name(&query_string, &ast).unwrap_or_default(), None)
+        }
+        Ok(ast) => (table_name(&query_string, &ast)?, None),
+        Err(e) => (
+            String::new(),
+            Some(e).filter(|_| {
+                data.validate_sql && statement_kind != crate::statement::StatementKind::Definition
+            }),
+        ),
+    };
+
+    let outcome = match &hint.outcome {
+        Some(outcome) => match QueryExecutionState::from(outcome.as_ref()) {
+            state @ (QueryExecutionState::Succeeded
+            | QueryExecutionState::Failed
+            | QueryExecutionState::Cancelled) => Some(state),
+            _ => {
+                return Ok(invalid_request(format!(
+                    "unsupported outcome: {:}",
+                    outcome
                 )))
             }
         },
-        _ => {
-            return Ok(
-                HttpResponse::BadRequest().body(format!("unsupported query: {:}", query_string))
-            )
+        None => None,
+    };
+    let result_reuse_max_age = result_reuse_max_age(input)?;
+    let query_execution_id = new_query_execution_id(data);
+    tracing::Span::current().record(
+        "query_execution_id",
+        &tracing::field::display(&query_execution_id),
+    );
+    let reused = result_reuse_max_age
+        .filter(|_| statement_kind == crate::statement::StatementKind::Query)
+        .and_then(|v| reusable_query_process(data, &query_string, v));
+
+    let failure_reason = if query_string.contains(&data.fail_query_marker) {
+        Some(format!(
+            "query is flagged to fail by '{:}'",
+            data.fail_query_marker
+        ))
+    } else if outcome == Some(QueryExecutionState::Failed) {
+        Some("query is flagged to fail by 'minerva:outcome'".to_string())
+    } else {
+        data.fail_query_pattern
+            .as_ref()
+            .filter(|v| v.is_match(&query_string))
+            .map(|v| format!("query matches the fail pattern '{:}'", v))
+    };
+    let failure_reason = failure_reason.or_else(|| {
+        if outcome.is_none() && syntax_error.is_none() && data.chaos_failure_rate > 0.0 {
+            let failed = data
+                .chaos_rng
+                .lock()
+                .unwrap()
+                .gen_bool(data.chaos_failure_rate);
+            tracing::info!(
+                "chaos mode: query execution {:} will {:}",
+                query_execution_id,
+                if failed { "fail" } else { "succeed" }
+            );
+            if failed {
+                return Some("query failed by chaos mode".to_string());
+            }
+        }
+        None
+    });
+    let failure_reason = failure_reason.map(|reason| {
+        hint.error_message
+            .clone()
+            .or_else(|| data.failure.error_message.clone())
+            .unwrap_or(reason)
+    });
+    // Note: syntax and execution errors are reported as is, and before anything else.
+    let (result, failure_reason) = match (&syntax_error, &data.engine) {
+        (Some(syntax_error), _) => (None, Some(syntax_error.clone())),
+        (None, _)
+            if metadata_query.is_some()
+                && failure_reason.is_none()
+                && !matches!(
+                    outcome,
+                    Some(QueryExecutionState::Failed | QueryExecutionState::Cancelled)
+                ) =>
+        {
+            match metadata_query_result(metadata_query.as_ref().unwrap(), input, data) {
+                Ok(result) => (Some(Arc::new(result)), None),
+                Err(e) => (None, Some(e)),
+            }
+        }
+        (None, Some(engine))
+            if failure_reason.is_none()
+                && reused.is_none()
+                && !matches!(
+                    outcome,
+                    Some(QueryExecutionState::Failed | QueryExecutionState::Cancelled)
+                ) =>
+        {
+            match engine.execute(&query_string) {
+                Ok(result) => (Some(Arc::new(result)), None),
+                Err(e) => (None, Some(e)),
+            }
+        }
+        _ => (None, failure_reason),
+    };
+    let outcome = if syntax_error.is_some() {
+        QueryExecutionState::Failed
+    } else {
+        outcome.unwrap_or(if failure_reason.is_some() {
+            QueryExecutionState::Failed
+        } else {
+            QueryExecutionState::Succeeded
+        })
+    };
+
+    // Note: DDL, SHOW, DESCRIBE and EXPLAIN have no RUNNING phase to speak of, unless a hint gives it one.
+    let state_durations = if reused.is_some() {
+        crate::model::StateDurations {
+            queued: Duration::from_secs(0),
+            running: Duration::from_secs(0),
         }
+    } else {
+        hint.state_durations(
+            if statement_kind == crate::statement::StatementKind::Definition
+                || metadata_query.is_some()
+            {
+                crate::model::StateDurations {
+                    running: Duration::from_secs(0),
+                    ..data.state_durations
+                }
+            } else {
+                data.state_durations
+            },
+        )
+    };
+    let immediate =
+        data.immediate || (state_durations.queued.is_zero() && state_durations.running.is_zero());
+    // Note: a query only completes right away while MAX_RUNNING_QUERIES leaves a slot free and no
+    // query waits for one, otherwise it waits its turn in process_query, then completes without
+    // staying RUNNING.
+    let (immediate, state_durations) = if immediate && running_slots_taken(data) {
+        (
+            false,
+            crate::model::StateDurations {
+                queued: Duration::from_secs(0),
+                running: Duration::from_secs(0),
+            },
+        )
+    } else {
+        (immediate, state_durations)
     };
+    // Note: a result to upload is uploaded by process_query, before the query is SUCCEEDED.
+    #[cfg(feature = "s3")]
+    let immediate = immediate && data.result_uploader.is_none();
 
-    let query_execution_id = Uuid::new_v4().to_string();
-    process_query(
-        query_execution_id.clone(),
-        table_name.clone(),
-        data.process_interval,
-        data.processes_r.clone(),
-        data.processes_w.clone(),
+    // Note: the result of a query engine comes first, then the fixture picked by the query string.
+    let result_fixture = data
+        .result_fixtures
+        .as_ref()
+        .and_then(|v| v.find(&query_string));
+    let mut query_process = crate::model::QueryProcess {
+        query_string: query_string.clone(),
+        table_name: table_name.clone(),
+        state: QueryExecutionState::Queued.as_str().to_string(),
+        outcome: outcome.as_str().to_string(),
+        failure_reason,
+        error_category: hint.error_category.unwrap_or(data.failure.error_category),
+        error_type: hint.error_type.unwrap_or(data.failure.error_type),
+        retryable: hint.retryable.unwrap_or(data.failure.retryable),
+        submission_date_time: data.clock.now_millis(),
+        running_date_time: None,
+        completion_date_time: None,
+        data_scanned_in_bytes: 0,
+        output_location: output_location(input, &work_group, data, &query_execution_id),
+        encryption_configuration: result_configuration_setting(input, &work_group, |v| {
+            v.encryption_configuration.clone()
+        })
+        .or_else(|| data.default_encryption_configuration.clone()),
+        expected_bucket_owner: result_configuration_setting(input, &work_group, |v| {
+            v.expected_bucket_owner.clone()
+        }),
+        database: input
+            .query_execution_context
+            .as_ref()
+            .and_then(|v| v.database.clone()),
+        catalog: input
+            .query_execution_context
+            .as_ref()
+            .and_then(|v| v.catalog.clone()),
+        work_group: work_group_name,
+        engine_version: engine_version(input.engine_version.as_ref(), &data.engine_version),
+        result: result.or_else(|| match &result_fixture {
+            Some(crate::model::fixtures::ResultFixture::Result(result)) => Some(result.clone()),
+            _ => None,
+        }),
+        result_fixture: match result_fixture {
+            Some(crate::model::fixtures::ResultFixture::File(fixture_path)) => Some(fixture_path),
+            _ => None,
+        },
+        statement_kind,
+        update_count: hint.update_count,
+        generated: hint.generated(),
+        reused_previous_result: false,
+    };
+    // Note: a query which ends FAILED scans nothing, whatever is set for it.
+    if outcome != QueryExecutionState::Failed {
+        query_process.data_scanned_in_bytes = hint
+            .data_scanned_in_bytes
+            .or(data.data_scanned_in_bytes)
+            .unwrap_or_else(|| result_size(&query_process, data));
+    }
+    // Note: ends SUCCEEDED right away with the result of the reused execution, without scanning.
+    if let Some(reused) = reused {
+        query_process = crate::model::QueryProcess {
+            outcome: QueryExecutionState::Succeeded.as_str().to_string(),
+            failure_reason: None,
+            data_scanned_in_bytes: 0,
+            result: reused.result,
+            result_fixture: reused.result_fixture,
+            update_count: reused.update_count,
+            generated: reused.generated,
+            reused_previous_result: true,
+            ..query_process
+        };
+    }
+    let submission_date_time = query_process.submission_date_time;
+    let final_query_process =
+        query_process.with_state(query_process.final_state(), submission_date_time);
+    let output_written = immediate
+        && final_query_process.state == QueryExecutionState::Succeeded.as_str()
+        && write_succeeded_output(data, &query_execution_id, &final_query_process);
+    // Note: the result written ahead is removed again if the execution isn't recorded.
+    let mut evicted = Vec::new();
+    let recorded = 'recorded: {
+        let mut processes = data.processes.write().unwrap();
+        // Note: counted under the write lock, so concurrent starts can't both take the last slot.
+        if let Some(max_concurrent_queries) = data.max_concurrent_queries {
+            if count_query_processes(&processes, |v| !v.is_terminal()) >= max_concurrent_queries {
+                break 'recorded Err(error_response(
+                    StatusCode::BAD_REQUEST,
+                    TOO_MANY_REQUESTS_EXCEPTION,
+                    format!(
+                        "You have exceeded the limit for the number of queries you can run concurrently ({:})",
+                        max_concurrent_queries
+                    ),
+                ));
+            }
+        }
+        if let Some(max_tracked_queries) = data.max_tracked_queries {
+            match evict_oldest_queries(max_tracked_queries, &mut processes) {
+                Some(v) => evicted = v,
+                None => {
+                    break 'recorded Err(error_response(
+                        StatusCode::BAD_REQUEST,
+                        TOO_MANY_REQUESTS_EXCEPTION,
+                        format!(
+                            "Already tracking {:} query executions which have not finished",
+                            max_tracked_queries
+                        ),
+                    ))
+                }
+            }
+        }
+        record_transition(
+            data,
+            &mut processes,
+            &query_execution_id,
+            None,
+            query_process,
+            submission_date_time,
+        );
+        if immediate {
+            // Note: record the outcome right away, without spawning process_query.
+            record_transition(
+                data,
+                &mut processes,
+                &query_execution_id,
+                Some(QueryExecutionState::Queued),
+                final_query_process.clone(),
+                submission_date_time,
+            );
+        }
+        Ok(())
+    };
+    forget_query_processes(
+        data.output_dir.as_deref(),
+        &data.client_request_tokens,
+        &evicted,
     );
+    if let Err(response) = recorded {
+        if output_written {
+            remove_output(data.output_dir.as_deref(), &final_query_process);
+        }
+        return Ok(response);
+    }
+    if !immediate {
+        process_query(query_execution_id.clone(), state_durations, data);
+    }
+    client_request_token.complete(&query_execution_id);
 
-    Ok(
-        HttpResponse::Ok().json(crate::model::StartQueryExecutionResponse::new(
-            query_execution_id,
-        )),
-    )
+    Ok(ok_response(crate::model::StartQueryExecutionResponse::new(
+        query_execution_id,
+    )))
 }
 
 pub fn get_query_execution(
-    input: &crate::model::Param,
+    input: &crate::model::GetQueryExecutionInput,
     data: &crate::model::AppData,
 ) -> Result<HttpResponse> {
-    let query_execution_id = input
-        .query_execution_id
-        .clone()
-        .ok_or_else(|| HttpResponse::BadRequest().body("unexpected input".to_string()))?;
-    let query_process = data
-        .processes_r
-        .get_one::<String>(&query_execution_id)
-        .ok_or_else(|| {
-            HttpResponse::BadRequest().body("query_execution_id not found".to_string())
-        })?;
-    let state = QueryExecutionState::from(query_process.state.as_ref());
+    let (query_execution_id, query_process) = find_query_process(&input.query_execution_id, data)?;
+
+    Ok(ok_response(crate::model::GetQueryExecutionResponse::new(
+        query_execution_id,
+        &query_process,
+        data.clock.now_millis(),
+    )))
+}
 
-    Ok(
-        HttpResponse::Ok().json(crate::model::GetQueryExecutionResponse::new(
+// Note: the execution stays in the forced state, its transitions are stopped whatever the state is.
+pub fn force_state(
+    data: &crate::model::AppData,
+    query_execution_id: &str,
+    state: QueryExecutionState,
+    reason: Option<String>,
+) -> Result<HttpResponse> {
+    loop {
+        let query_process = data
+            .processes
+            .read()
+            .unwrap()
+            .get(query_execution_id)
+            .cloned()
+            .ok_or_else(|| query_execution_not_found(query_execution_id))?;
+        let previous_state = QueryExecutionState::from(query_process.state.as_ref());
+        if query_process.is_terminal() {
+            return Ok(invalid_request(format!(
+                "Query has already finished. Current state: {:}",
+                previous_state.as_str()
+            )));
+        }
+        if let Some(handle) = data
+            .process_handles
+            .lock()
+            .unwrap()
+            .remove(query_execution_id)
+        {
+            handle.abort();
+        }
+        let now = data.clock.now_millis();
+        let mut next_query_process = query_process.with_state(state.clone(), now);
+        if state == QueryExecutionState::Failed {
+            next_query_process.failure_reason = reason
+                .clone()
+                .or(next_query_process.failure_reason)
+                .or_else(|| Some("query is failed by the admin API".to_string()));
+        }
+        let output_written = state == QueryExecutionState::Succeeded
+            && write_succeeded_output(data, query_execution_id, &next_query_process);
+        let mut processes = data.processes.write().unwrap();
+        // Note: the execution moved on before its transitions were stopped, it's looked up again.
+        if !record_transition(
+            data,
+            &mut processes,
             query_execution_id,
-            state,
-        )),
-    )
+            Some(previous_state),
+            next_query_process.clone(),
+            now,
+        ) {
+            drop(processes);
+            if output_written {
+                remove_output(data.output_dir.as_deref(), &next_query_process);
+            }
+            continue;
+        }
+        return Ok(ok_response(crate::model::GetQueryExecutionResponse::new(
+            query_execution_id.to_string(),
+            &processes[query_execution_id],
+            now,
+        )));
+    }
 }
 
-pub fn get_query_results(
-    input: &crate::model::Param,
+pub fn batch_get_query_execution(
+    input: &crate::model::BatchGetQueryExecutionInput,
     data: &crate::model::AppData,
 ) -> Result<HttpResponse> {
-    let query_execution_id = input
-        .query_execution_id
-        .clone()
-        .ok_or_else(|| HttpResponse::BadRequest().body("unexpected input".to_string()))?;
-    let query_process = data
-        .processes_r
-        .get_one::<String>(&query_execution_id)
-        .ok_or_else(|| {
-            HttpResponse::BadRequest().body("query_execution_id not found".to_string())
-        })?;
+    let query_execution_ids = &input.query_execution_ids;
+    if !(1..=MAX_BATCH_GET_QUERY_EXECUTION_IDS).contains(&query_execution_ids.len()) {
+        return Ok(invalid_request(format!(
+            "QueryExecutionIds must contain between 1 and {:} ids",
+            MAX_BATCH_GET_QUERY_EXECUTION_IDS
+        )));
+    }
+
+    let now = data.clock.now_millis();
+    let processes = data.processes.read().unwrap();
+    let mut response = crate::model::BatchGetQueryExecutionResponse {
+        query_executions: Vec::new(),
+        unprocessed_query_execution_ids: Vec::new(),
+    };
+    for query_execution_id in query_execution_ids {
+        match processes.get(query_execution_id) {
+            Some(query_process) => {
+                response
+                    .query_executions
+                    .push(crate::model::QueryExecutionResponse::new(
+                        query_execution_id.clone(),
+                        query_process,
+                        now,
+                    ))
+            }
+            None => response.unprocessed_query_execution_ids.push(
+                crate::model::UnprocessedQueryExecutionId {
+                    query_execution_id: query_execution_id.clone(),
+                    error_code: INVALID_REQUEST_EXCEPTION.to_string(),
+                    error_message: query_execution_not_found_message(query_execution_id),
+                },
+            ),
+        }
+    }
+
+    Ok(ok_response(response))
+}
+
+// Note: the message of Athena for the results of a query which hasn't SUCCEEDED, clients match
+// on its prefix, and a failed query's is followed by why it failed.
+fn query_results_unavailable_message(query_process: &crate::model::QueryProcess) -> Option<String> {
     let state = QueryExecutionState::from(query_process.state.as_ref());
-    if state != QueryExecutionState::Succeeded {
-        return Ok(HttpResponse::BadRequest().body(format!("state not succeeded yet: {:?}", state)));
+    let message = match state {
+        QueryExecutionState::Succeeded => return None,
+        QueryExecutionState::Queued | QueryExecutionState::Running => {
+            return Some(format!(
+                "Query has not yet finished. Current state: {:}",
+                state.as_str()
+            ))
+        }
+        _ => format!(
+            "Query did not finish successfully. Final query state: {:}",
+            state.as_str()
+        ),
+    };
+    match (&state, &query_process.failure_reason) {
+        (QueryExecutionState::Failed, Some(reason)) => Some(format!("{:}: {:}", message, reason)),
+        _ => Some(message),
+    }
+}
+
+// Note: rows of a query result, read from a fixture or a `QueryEngine`.
+type Records = Box<dyn Iterator<Item = std::result::Result<crate::model::Record, HttpResponse>>>;
+
+pub fn get_query_results(
+    input: &crate::model::GetQueryResultsInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let (query_execution_id, query_process) = find_query_process(&input.query_execution_id, data)?;
+    if let Some(message) = query_results_unavailable_message(&query_process) {
+        return Ok(invalid_request(message));
     }
+    let max_results = max_results(input.max_results, MAX_GET_QUERY_RESULTS_RESULTS)?;
+    let offset = match &input.next_token {
+        Some(next_token) => query_results_offset(next_token, &query_execution_id)?,
+        None => 0,
+    };
 
     let table_name = &query_process.table_name;
-    let f = File::open(format!("{:}/{:}.csv", data.csv_fixture_dir, table_name))?;
-    let b = BufReader::new(f);
-    let mut csv_reader = csv::ReaderBuilder::new().has_headers(true).from_reader(b);
+    // Note: an INSERT or CTAS has an empty result, with the count of the rows read by its query
+    // as UpdateCount unless a `minerva:update_count` hint gives it, and DDL has neither rows nor
+    // columns, as Athena does.
+    if !query_process.statement_kind.has_rows() {
+        let update_count = match query_process.statement_kind {
+            crate::statement::StatementKind::Modification => {
+                query_process.update_count.unwrap_or_else(|| {
+                    query_result_records(&query_process, data, 0)
+                        .ok()
+                        .map(|(_, records)| records.filter(|v| v.is_ok()).count() as u64)
+                        .unwrap_or(0)
+                })
+            }
+            _ => 0,
+        };
+        return Ok(ok_response(crate::model::GetQueryResultsResponse::new(
+            table_name.to_string(),
+            Vec::new(),
+            Vec::new(),
+            None,
+            update_count,
+        )));
+    }
+    let (columns, mut records) = query_result_records(&query_process, data, offset)?;
 
-    let mut column_names = Vec::new();
-    for header in csv_reader
-        .headers()
-        .map_err(|_| HttpResponse::BadRequest().body("failed to read csv headers".to_string()))?
+    let mut rows = Vec::new();
+    // Note: the header row is the first row of the first page, as Athena does,
+    // and there is none for statements without columns, e.g. DDL run by a query engine,
+    // nor for SHOW and DESCRIBE, whose rows Athena gives as they are.
+    if data.include_header_row
+        && input.next_token.is_none()
+        && !columns.is_empty()
+        && query_process.statement_kind != crate::statement::StatementKind::Utility
     {
-        column_names.push(header.to_string());
+        rows.push(crate::model::Row::new(
+            &columns
+                .iter()
+                .map(|v| Some(v.name.clone()))
+                .collect::<Vec<_>>(),
+        ));
     }
+    let header_rows = rows.len();
+    while rows.len() < max_results as usize {
+        match records.next() {
+            Some(record) => rows.push(crate::model::Row::new(&record?)),
+            None => break,
+        }
+    }
+    let next_token = records
+        .next()
+        .map(|_| query_results_next_token(&query_execution_id, offset + rows.len() - header_rows));
 
-    let mut rows = Vec::new();
-    let offset = input
-        .next_token
-        .as_ref()
-        .unwrap_or(&"0".to_string())
-        .parse::<u64>()
-        .map_err(|_| HttpResponse::BadRequest().body("invalid next_token".to_string()))?;
-    let limit = input.max_results.unwrap_or(100) + offset;
+    Ok(ok_response(crate::model::GetQueryResultsResponse::new(
+        table_name.to_string(),
+        columns,
+        rows,
+        next_token,
+        0,
+    )))
+}
+
+pub fn get_query_runtime_statistics(
+    input: &crate::model::GetQueryRuntimeStatisticsInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let (_, query_process) = find_query_process(&input.query_execution_id, data)?;
+    // Note: the rows of the result are counted once the query has SUCCEEDED, every row read
+    // makes it to the result.
+    let rows = (QueryExecutionState::from(query_process.state.as_ref())
+        == QueryExecutionState::Succeeded)
+        .then(|| {
+            let (rows, bytes) = match query_process.generated {
+                Some((rows, cols)) => (rows, crate::generate::byte_count(rows, cols)),
+                None => query_result_records(&query_process, data, 0)
+                    .ok()
+                    .map(|(_, records)| {
+                        records
+                            .filter_map(|v| v.ok())
+                            .fold((0, 0), |(rows, bytes), v| {
+                                (rows + 1, bytes + record_size(&v))
+                            })
+                    })
+                    .unwrap_or((0, 0)),
+            };
+            let output_rows = match query_process.statement_kind {
+                crate::statement::StatementKind::Query => rows,
+                _ => 0,
+            };
+            crate::model::QueryRuntimeStatisticsRows {
+                input_rows: rows,
+                input_bytes: query_process.data_scanned_in_bytes,
+                output_bytes: if output_rows > 0 { bytes } else { 0 },
+                output_rows,
+            }
+        });
+
+    Ok(ok_response(
+        crate::model::GetQueryRuntimeStatisticsResponse::new(
+            &query_process,
+            rows,
+            data.clock.now_millis(),
+        ),
+    ))
+}
 
-    let mut count = 0;
-    if input.next_token.is_none() {
-        let _ = count + 1;
-        rows.push(crate::model::Row::new(&column_names));
+pub fn stop_query_execution(
+    input: &crate::model::StopQueryExecutionInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let query_execution_id = input.query_execution_id.clone();
+    let mut processes = data.processes.write().unwrap();
+    let query_process = processes
+        .get(&query_execution_id)
+        .cloned()
+        .ok_or_else(|| query_execution_not_found(&query_execution_id))?;
+    match QueryExecutionState::from(query_process.state.as_ref()) {
+        QueryExecutionState::Queued | QueryExecutionState::Running => {}
+        // Note: stopping a query which has already finished is a no-op, same as Athena.
+        _ => return Ok(ok_response(crate::model::StopQueryExecutionResponse {})),
+    }
+    if let Some(handle) = data
+        .process_handles
+        .lock()
+        .unwrap()
+        .remove(&query_execution_id)
+    {
+        handle.abort();
     }
+    let now = data.clock.now_millis();
+    record_transition(
+        data,
+        &mut processes,
+        &query_execution_id,
+        Some(QueryExecutionState::from(query_process.state.as_ref())),
+        query_process.with_state(QueryExecutionState::Cancelled, now),
+        now,
+    );
 
-    let mut next_token = None;
-    for records in csv_reader.records() {
-        count += 1;
-        if count < offset {
-            continue;
-        }
-        if count >= limit {
-            next_token = Some(count.to_string());
-            break;
-        }
-        let rs = records.map_err(|_| {
-            HttpResponse::BadRequest().body("failed to read csv fixture".to_string())
-        })?;
+    Ok(ok_response(crate::model::StopQueryExecutionResponse {}))
+}
 
-        let mut records = Vec::new();
-        for record in rs.iter() {
-            records.push(crate::model::Datum {
-                var_char_value: record.to_string(),
-            });
+pub fn list_query_executions(
+    input: &crate::model::ListQueryExecutionsInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let max_results = max_results(input.max_results, MAX_LIST_QUERY_EXECUTIONS_RESULTS)?;
+
+    // Note: newest first, the id breaks ties between executions submitted in the same millisecond.
+    let mut query_executions = data
+        .processes
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(id, v)| (v.submission_date_time, id.clone()))
+        .collect::<Vec<_>>();
+    query_executions.sort_by(|a, b| b.cmp(a));
+    let query_execution_ids = query_executions
+        .into_iter()
+        .map(|(_, id)| id)
+        .collect::<Vec<_>>();
+
+    // Note: NextToken is the id of the first execution of the next page.
+    let (query_execution_ids, next_token) = paginate(
+        query_execution_ids,
+        input.next_token.as_deref(),
+        max_results,
+        |id| id,
+    )?;
+
+    Ok(ok_response(crate::model::ListQueryExecutionsResponse::new(
+        query_execution_ids,
+        next_token,
+    )))
+}
+
+pub fn create_named_query(
+    input: &crate::model::CreateNamedQueryInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let required = |value: &String, name: &str| {
+        Some(value.clone())
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| invalid_request(format!("{:} is required", name)))
+    };
+    let name = required(&input.name, "Name")?;
+    let database = required(&input.database, "Database")?;
+    let query_string = required(&input.query_string, "QueryString")?;
+
+    let named_query = crate::model::NamedQuery {
+        name,
+        description: input.description.clone(),
+        database,
+        query_string,
+        work_group: input
+            .work_group
+            .clone()
+            .unwrap_or_else(|| PRIMARY_WORK_GROUP.to_string()),
+        client_request_token: input.client_request_token.clone(),
+        sequence: 0,
+    };
+
+    let mut named_queries = data.named_queries.write().unwrap();
+    // Note: a retry with the same token returns the named query it created, as
+    // StartQueryExecution does.
+    if let Some(client_request_token) = &named_query.client_request_token {
+        if let Some((named_query_id, created)) = named_queries
+            .iter()
+            .find(|(_, v)| v.client_request_token.as_ref() == Some(client_request_token))
+        {
+            let retried = crate::model::NamedQuery {
+                sequence: created.sequence,
+                ..named_query.clone()
+            };
+            if *created != retried {
+                return Ok(error_response(
+                    StatusCode::BAD_REQUEST,
+                    IDEMPOTENT_PARAMETER_MISMATCH_EXCEPTION,
+                    format!(
+                        "ClientRequestToken {:} is already used for another named query",
+                        client_request_token
+                    ),
+                ));
+            }
+            return Ok(ok_response(crate::model::CreateNamedQueryResponse::new(
+                named_query_id.clone(),
+            )));
         }
-        rows.push(crate::model::Row { data: records });
     }
+    let named_query_id = Uuid::new_v4().to_string();
+    let sequence = named_queries
+        .values()
+        .map(|v| v.sequence + 1)
+        .max()
+        .unwrap_or(0);
+    named_queries.insert(
+        named_query_id.clone(),
+        crate::model::NamedQuery {
+            sequence,
+            ..named_query
+        },
+    );
+    Ok(ok_response(crate::model::CreateNamedQueryResponse::new(
+        named_query_id,
+    )))
+}
 
-    Ok(
-        HttpResponse::Ok().json(crate::model::GetQueryResultsResponse::new(
-            table_name.to_string(),
-            column_names,
-            rows,
-            next_token,
-        )),
-    )
+pub fn get_named_query(
+    input: &crate::model::GetNamedQueryInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let named_query_id = input.named_query_id.clone();
+    let named_queries = data.named_queries.read().unwrap();
+    let named_query = named_queries
+        .get(&named_query_id)
+        .ok_or_else(|| invalid_request(named_query_not_found_message(&named_query_id)))?;
+    Ok(ok_response(crate::model::GetNamedQueryResponse::new(
+        named_query_id,
+        named_query,
+    )))
 }
 
-fn process_query(
-    query_execution_id: String,
-    table_name: String,
-    process_interval: Duration,
-    processes_r: evmap::ReadHandle<String, crate::model::QueryProcess>,
-    processes_w: Arc<Mutex<evmap::WriteHandle<String, crate::model::QueryProcess>>>,
-) {
-    spawn(async move {
-        let mut interval = time::interval(process_interval);
-        loop {
-            let query_state = processes_r
-                .get_one::<String>(&query_execution_id)
-                .map(|v| QueryExecutionState::from(v.state.as_ref()));
-            let mut processes_w = processes_w.lock().unwrap();
-            match query_state {
-                None => {
-                    processes_w.insert(
-                        query_execution_id.clone(),
-                        crate::model::QueryProcess {
-                            table_name: table_name.clone(),
-                            state: QueryExecutionState::Queued.as_str().to_string(),
-                        },
-                    );
-                }
-                Some(QueryExecutionState::Queued) => {
-                    processes_w.empty(query_execution_id.clone());
-                    processes_w.insert(
-                        query_execution_id.clone(),
-                        crate::model::QueryProcess {
-                            table_name: table_name.clone(),
-                            state: QueryExecutionState::Running.as_str().to_string(),
-                        },
-                    );
-                }
-                Some(QueryExecutionState::Running) => {
-                    processes_w.empty(query_execution_id.clone());
-                    processes_w.insert(
-                        query_execution_id.clone(),
-                        crate::model::QueryProcess {
-                            table_name: table_name.clone(),
-                            state: QueryExecutionState::Succeeded.as_str().to_string(),
-                        },
-                    );
-                }
-                Some(QueryExecutionState::Succeeded) => return,
-                Some(state) => panic!("unexpected state: {:?}", state),
-            }
-            processes_w.refresh();
+pub fn delete_named_query(
+    input: &crate::model::DeleteNamedQueryInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    // Note: an unknown id is deleted already, as Athena answers it.
+    data.named_queries
+        .write()
+        .unwrap()
+        .remove(&input.named_query_id);
+    Ok(ok_response(crate::model::DeleteNamedQueryResponse {}))
+}
 
-            interval.tick().await;
+pub fn update_named_query(
+    input: &crate::model::UpdateNamedQueryInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let required = |value: &String, name: &str| {
+        Some(value.clone())
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| invalid_request(format!("{:} is required", name)))
+    };
+    let name = required(&input.name, "Name")?;
+    let query_string = required(&input.query_string, "QueryString")?;
+
+    let mut named_queries = data.named_queries.write().unwrap();
+    let named_query = named_queries
+        .get_mut(&input.named_query_id)
+        .ok_or_else(|| invalid_request(named_query_not_found_message(&input.named_query_id)))?;
+    named_query.name = name;
+    named_query.description = input.description.clone();
+    named_query.query_string = query_string;
+    Ok(ok_response(crate::model::UpdateNamedQueryResponse {}))
+}
+
+pub fn batch_get_named_query(
+    input: &crate::model::BatchGetNamedQueryInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let named_query_ids = &input.named_query_ids;
+    if !(1..=MAX_BATCH_GET_NAMED_QUERY_IDS).contains(&named_query_ids.len()) {
+        return Ok(invalid_request(format!(
+            "NamedQueryIds must contain between 1 and {:} ids",
+            MAX_BATCH_GET_NAMED_QUERY_IDS
+        )));
+    }
+
+    let named_queries = data.named_queries.read().unwrap();
+    let mut response = crate::model::BatchGetNamedQueryResponse {
+        named_queries: Vec::new(),
+        unprocessed_named_query_ids: Vec::new(),
+    };
+    for named_query_id in named_query_ids {
+        match named_queries.get(named_query_id) {
+            Some(named_query) => {
+                response
+                    .named_queries
+                    .push(crate::model::NamedQueryResponse::new(
+                        named_query_id.clone(),
+                        named_query,
+                    ))
+            }
+            None => {
+                response
+                    .unprocessed_named_query_ids
+                    .push(crate::model::UnprocessedNamedQueryId {
+                        named_query_id: named_query_id.clone(),
+                        error_code: NAMED_QUERY_NOT_FOUND_ERROR_CODE.to_string(),
+                        error_message: named_query_not_found_message(named_query_id),
+                    })
+            }
         }
-    })
+    }
+
+    Ok(ok_response(response))
+}
+
+pub fn list_named_queries(
+    input: &crate::model::ListNamedQueriesInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let max_results = max_results(input.max_results, MAX_LIST_NAMED_QUERIES_RESULTS)?;
+    // Note: the named queries of `primary` unless a workgroup is given, as Athena lists them.
+    let work_group = input
+        .work_group
+        .clone()
+        .unwrap_or_else(|| PRIMARY_WORK_GROUP.to_string());
+    if !data.work_groups.read().unwrap().contains_key(&work_group) {
+        return Ok(work_group_not_found(&work_group));
+    }
+
+    // Note: in the order they were created, unlike executions.
+    let mut named_queries = data
+        .named_queries
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(_, v)| v.work_group == work_group)
+        .map(|(id, v)| (v.sequence, id.clone()))
+        .collect::<Vec<_>>();
+    named_queries.sort();
+    let named_query_ids = named_queries
+        .into_iter()
+        .map(|(_, id)| id)
+        .collect::<Vec<_>>();
+
+    // Note: NextToken is the id of the first named query of the next page, as ListQueryExecutions.
+    let (named_query_ids, next_token) = paginate(
+        named_query_ids,
+        input.next_token.as_deref(),
+        max_results,
+        |id| id,
+    )?;
+
+    Ok(ok_response(crate::model::ListNamedQueriesResponse::new(
+        named_query_ids,
+        next_token,
+    )))
+}
+
+/// The workgroups there from the start, `primary` with the default configuration.
+pub fn initial_work_groups(
+    default_engine_version: &str,
+    now: u64,
+) -> HashMap<String, crate::model::WorkGroup> {
+    HashMap::from([(
+        PRIMARY_WORK_GROUP.to_string(),
+        crate::model::WorkGroup {
+            description: None,
+            state: WORK_GROUP_STATE_ENABLED.to_string(),
+            configuration: crate::model::WorkGroupConfiguration {
+                engine_version: Some(engine_version(None, default_engine_version)),
+                ..Default::default()
+            },
+            creation_time: now,
+        },
+    )])
+}
+
+pub fn create_work_group(
+    input: &crate::model::CreateWorkGroupInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let name = input.name.clone();
+    if !WORK_GROUP_NAME_PATTERN.is_match(&name) {
+        return Ok(invalid_request(format!(
+            "1 validation error detected: Value '{:}' at 'name' failed to satisfy constraint: Member must satisfy regular expression pattern: [a-zA-Z0-9._-]{{1,128}}",
+            name
+        )));
+    }
+    let mut configuration = input.configuration.clone().unwrap_or_default();
+    if let Some(message) = configuration
+        .result_configuration
+        .as_ref()
+        .and_then(|v| v.encryption_configuration.as_ref())
+        .and_then(|v| encryption_configuration_error(v, "configuration.resultConfiguration"))
+    {
+        return Ok(invalid_request(message));
+    }
+    configuration.engine_version = Some(engine_version(
+        configuration.engine_version.as_ref(),
+        &data.engine_version,
+    ));
+    if let Some(message) = tags_error(&input.tags) {
+        return Ok(invalid_request(message));
+    }
+    let tags = input
+        .tags
+        .iter()
+        .map(|v| (v.key.clone(), v.value.clone()))
+        .collect::<BTreeMap<_, _>>();
+    if tags.len() > MAX_TAGS {
+        return Ok(invalid_request(too_many_tags_message(&name)));
+    }
+
+    let mut work_groups = data.work_groups.write().unwrap();
+    if work_groups.contains_key(&name) {
+        return Ok(invalid_request("WorkGroup is already created".to_string()));
+    }
+    // Note: kept as TagResource keeps them, ListTagsForResource lists them for the ARN of the
+    // workgroup.
+    if !tags.is_empty() {
+        data.tags
+            .write()
+            .unwrap()
+            .insert(resource_key(RESOURCE_TYPE_WORK_GROUP, &name), tags);
+    }
+    work_groups.insert(
+        name,
+        crate::model::WorkGroup {
+            description: input.description.clone(),
+            state: WORK_GROUP_STATE_ENABLED.to_string(),
+            configuration,
+            creation_time: data.clock.now_millis(),
+        },
+    );
+    Ok(ok_response(crate::model::CreateWorkGroupResponse {}))
+}
+
+pub fn get_work_group(
+    input: &crate::model::GetWorkGroupInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let name = input.work_group.clone();
+    let work_groups = data.work_groups.read().unwrap();
+    let work_group = work_groups
+        .get(&name)
+        .ok_or_else(|| work_group_not_found(&name))?;
+    Ok(ok_response(crate::model::GetWorkGroupResponse::new(
+        name, work_group,
+    )))
+}
+
+pub fn list_work_groups(
+    input: &crate::model::ListWorkGroupsInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let max_results = max_results(input.max_results, MAX_LIST_WORK_GROUPS_RESULTS)?;
+
+    // Note: by name, NextToken is the name of the first workgroup of the next page.
+    let mut work_groups = data
+        .work_groups
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(name, v)| (name.clone(), v.clone()))
+        .collect::<Vec<_>>();
+    work_groups.sort_by(|a, b| a.0.cmp(&b.0));
+    let (work_groups, next_token) = paginate(
+        work_groups,
+        input.next_token.as_deref(),
+        max_results,
+        |(name, _)| name,
+    )?;
+
+    Ok(ok_response(crate::model::ListWorkGroupsResponse::new(
+        work_groups,
+        next_token,
+    )))
+}
+
+pub fn update_work_group(
+    input: &crate::model::UpdateWorkGroupInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let name = input.work_group.clone();
+    if let Some(message) = input
+        .configuration_updates
+        .as_ref()
+        .and_then(|v| v.result_configuration_updates.as_ref())
+        .and_then(|v| v.encryption_configuration.as_ref())
+        .and_then(|v| {
+            encryption_configuration_error(v, "configurationUpdates.resultConfigurationUpdates")
+        })
+    {
+        return Ok(invalid_request(message));
+    }
+    if let Some(state) = input.state.as_deref() {
+        if !matches!(state, WORK_GROUP_STATE_ENABLED | WORK_GROUP_STATE_DISABLED) {
+            return Ok(invalid_request(format!(
+                "1 validation error detected: Value '{:}' at 'state' failed to satisfy constraint: Member must satisfy enum value set: [ENABLED, DISABLED]",
+                state
+            )));
+        }
+    }
+    let mut work_groups = data.work_groups.write().unwrap();
+    let work_group = work_groups
+        .get_mut(&name)
+        .ok_or_else(|| work_group_not_found(&name))?;
+    if let Some(description) = &input.description {
+        work_group.description = Some(description.clone());
+    }
+    if let Some(state) = &input.state {
+        work_group.state = state.clone();
+    }
+    // Note: only the given settings change, as Athena does.
+    if let Some(updates) = &input.configuration_updates {
+        let configuration = &mut work_group.configuration;
+        if let Some(result_configuration_updates) = &updates.result_configuration_updates {
+            let result_configuration = configuration
+                .result_configuration
+                .get_or_insert_with(Default::default);
+            if result_configuration_updates.remove_output_location == Some(true) {
+                result_configuration.output_location = None;
+            } else if let Some(v) = &result_configuration_updates.output_location {
+                result_configuration.output_location = Some(v.clone());
+            }
+            if result_configuration_updates.remove_encryption_configuration == Some(true) {
+                result_configuration.encryption_configuration = None;
+            } else if let Some(v) = &result_configuration_updates.encryption_configuration {
+                result_configuration.encryption_configuration = Some(v.clone());
+            }
+            if result_configuration_updates.remove_expected_bucket_owner == Some(true) {
+                result_configuration.expected_bucket_owner = None;
+            } else if let Some(v) = &result_configuration_updates.expected_bucket_owner {
+                result_configuration.expected_bucket_owner = Some(v.clone());
+            }
+            if result_configuration.output_location.is_none()
+                && result_configuration.encryption_configuration.is_none()
+                && result_configuration.expected_bucket_owner.is_none()
+            {
+                configuration.result_configuration = None;
+            }
+        }
+        if let Some(v) = updates.enforce_work_group_configuration {
+            configuration.enforce_work_group_configuration = v;
+        }
+        if let Some(v) = updates.publish_cloud_watch_metrics_enabled {
+            configuration.publish_cloud_watch_metrics_enabled = v;
+        }
+        if updates.remove_bytes_scanned_cutoff_per_query == Some(true) {
+            configuration.bytes_scanned_cutoff_per_query = None;
+        } else if let Some(v) = updates.bytes_scanned_cutoff_per_query {
+            configuration.bytes_scanned_cutoff_per_query = Some(v);
+        }
+        if let Some(v) = updates.requester_pays_enabled {
+            configuration.requester_pays_enabled = v;
+        }
+        if let Some(v) = &updates.engine_version {
+            configuration.engine_version = Some(engine_version(Some(v), &data.engine_version));
+        }
+    }
+    Ok(ok_response(crate::model::UpdateWorkGroupResponse {}))
+}
+
+// Note: a workgroup with named queries or prepared statements is only deleted along with them by
+// RecursiveDeleteOption.
+pub fn delete_work_group(
+    input: &crate::model::DeleteWorkGroupInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let name = input.work_group.clone();
+    if name == PRIMARY_WORK_GROUP {
+        return Ok(invalid_request(
+            "The primary workgroup cannot be deleted".to_string(),
+        ));
+    }
+    let mut work_groups = data.work_groups.write().unwrap();
+    if !work_groups.contains_key(&name) {
+        return Ok(work_group_not_found(&name));
+    }
+    let mut named_queries = data.named_queries.write().unwrap();
+    let mut prepared_statements = data.prepared_statements.write().unwrap();
+    if named_queries.values().any(|v| v.work_group == name)
+        || prepared_statements.keys().any(|(v, _)| *v == name)
+    {
+        if input.recursive_delete_option != Some(true) {
+            return Ok(invalid_request(format!(
+                "WorkGroup {:} is not empty, set RecursiveDeleteOption to delete its named queries and prepared statements too",
+                name
+            )));
+        }
+        named_queries.retain(|_, v| v.work_group != name);
+        prepared_statements.retain(|(v, _), _| *v != name);
+    }
+    work_groups.remove(&name);
+    data.tags
+        .write()
+        .unwrap()
+        .remove(&resource_key(RESOURCE_TYPE_WORK_GROUP, &name));
+    Ok(ok_response(crate::model::DeleteWorkGroupResponse {}))
+}
+
+pub fn create_prepared_statement(
+    input: &crate::model::CreatePreparedStatementInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let statement_name = input.statement_name.clone();
+    if !STATEMENT_NAME_PATTERN.is_match(&statement_name) {
+        return Ok(invalid_request(format!(
+            "1 validation error detected: Value '{:}' at 'statementName' failed to satisfy constraint: Member must satisfy regular expression pattern: [a-zA-Z_][a-zA-Z0-9_@:]{{1,256}}",
+            statement_name
+        )));
+    }
+    let work_group = input.work_group.clone();
+    let query_statement = Some(input.query_statement.clone())
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| invalid_request("QueryStatement is required".to_string()))?;
+    if !data.work_groups.read().unwrap().contains_key(&work_group) {
+        return Ok(work_group_not_found(&work_group));
+    }
+
+    let mut prepared_statements = data.prepared_statements.write().unwrap();
+    let key = (work_group, statement_name);
+    if prepared_statements.contains_key(&key) {
+        return Ok(invalid_request(format!(
+            "Prepared statement {:} already exists in WorkGroup {:}",
+            key.1, key.0
+        )));
+    }
+    prepared_statements.insert(
+        key,
+        crate::model::PreparedStatement {
+            query_statement,
+            description: input.description.clone(),
+            last_modified_time: data.clock.now_millis(),
+        },
+    );
+    Ok(ok_response(
+        crate::model::CreatePreparedStatementResponse {},
+    ))
+}
+
+pub fn get_prepared_statement(
+    input: &crate::model::GetPreparedStatementInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let key = (input.work_group.clone(), input.statement_name.clone());
+    let prepared_statements = data.prepared_statements.read().unwrap();
+    let prepared_statement = prepared_statements
+        .get(&key)
+        .ok_or_else(|| prepared_statement_not_found(&key))?;
+    Ok(ok_response(
+        crate::model::GetPreparedStatementResponse::new(key.clone(), prepared_statement),
+    ))
+}
+
+pub fn list_prepared_statements(
+    input: &crate::model::ListPreparedStatementsInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let work_group = input.work_group.clone();
+    let max_results = max_results(input.max_results, MAX_LIST_PREPARED_STATEMENTS_RESULTS)?;
+    if !data.work_groups.read().unwrap().contains_key(&work_group) {
+        return Ok(work_group_not_found(&work_group));
+    }
+
+    // Note: by name, NextToken is the name of the first statement of the next page.
+    let mut prepared_statements = data
+        .prepared_statements
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|((v, _), _)| *v == work_group)
+        .map(|((_, name), v)| (name.clone(), v.clone()))
+        .collect::<Vec<_>>();
+    prepared_statements.sort_by(|a, b| a.0.cmp(&b.0));
+    let (prepared_statements, next_token) = paginate(
+        prepared_statements,
+        input.next_token.as_deref(),
+        max_results,
+        |(name, _)| name,
+    )?;
+
+    Ok(ok_response(
+        crate::model::ListPreparedStatementsResponse::new(prepared_statements, next_token),
+    ))
+}
+
+pub fn delete_prepared_statement(
+    input: &crate::model::DeletePreparedStatementInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let key = (input.work_group.clone(), input.statement_name.clone());
+    data.prepared_statements
+        .write()
+        .unwrap()
+        .remove(&key)
+        .ok_or_else(|| prepared_statement_not_found(&key))?;
+    Ok(ok_response(
+        crate::model::DeletePreparedStatementResponse {},
+    ))
+}
+
+pub fn tag_resource(
+    input: &crate::model::TagResourceInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let resource = tagged_resource(&input.resource_arn, data)?;
+    let tags = &input.tags;
+    if let Some(message) = tags_error(tags) {
+        return Ok(invalid_request(message));
+    }
+
+    let mut resource_tags = data.tags.write().unwrap();
+    let mut next_tags = resource_tags.get(&resource).cloned().unwrap_or_default();
+    for tag in tags {
+        next_tags.insert(tag.key.clone(), tag.value.clone());
+    }
+    // Note: the tags given again only replace their values, they count once toward the limit.
+    if next_tags.len() > MAX_TAGS {
+        return Ok(invalid_request(too_many_tags_message(&input.resource_arn)));
+    }
+    resource_tags.insert(resource, next_tags);
+    Ok(ok_response(crate::model::TagResourceResponse {}))
+}
+
+pub fn untag_resource(
+    input: &crate::model::UntagResourceInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let resource = tagged_resource(&input.resource_arn, data)?;
+    let tag_keys = &input.tag_keys;
+    for (i, key) in tag_keys.iter().enumerate() {
+        if let Some(message) = tag_key_error(key, &format!("tagKeys.{:}.member", i + 1)) {
+            return Ok(invalid_request(message));
+        }
+    }
+
+    // Note: a key which isn't there is ignored, as Athena does.
+    if let Some(tags) = data.tags.write().unwrap().get_mut(&resource) {
+        for key in tag_keys {
+            tags.remove(key);
+        }
+    }
+    Ok(ok_response(crate::model::UntagResourceResponse {}))
+}
+
+pub fn list_tags_for_resource(
+    input: &crate::model::ListTagsForResourceInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let resource = tagged_resource(&input.resource_arn, data)?;
+    let max_results = max_results(input.max_results, MAX_LIST_TAGS_FOR_RESOURCE_RESULTS)?;
+
+    // Note: by key, NextToken is the key of the first tag of the next page.
+    let tags = data
+        .tags
+        .read()
+        .unwrap()
+        .get(&resource)
+        .map(|v| {
+            v.iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let (tags, next_token) = paginate(
+        tags,
+        input.next_token.as_deref(),
+        max_results,
+        |(key, _)| key,
+    )?;
+
+    Ok(ok_response(crate::model::ListTagsForResourceResponse::new(
+        tags, next_token,
+    )))
+}
+
+/// The data catalogs there from the start, `AwsDataCatalog` of the Glue Data Catalog, with the
+/// given databases; a catalog of a database which isn't there is a Glue Data Catalog as well.
+pub fn initial_data_catalogs(
+    databases: Vec<crate::model::fixtures::DatabaseFixture>,
+) -> HashMap<String, crate::model::DataCatalog> {
+    let glue_data_catalog = || crate::model::DataCatalog {
+        catalog_type: DATA_CATALOG_TYPE_GLUE.to_string(),
+        description: None,
+        parameters: BTreeMap::new(),
+        databases: BTreeMap::new(),
+    };
+    let mut data_catalogs = HashMap::from([(AWS_DATA_CATALOG.to_string(), glue_data_catalog())]);
+    for database in databases {
+        data_catalogs
+            .entry(
+                database
+                    .catalog_name
+                    .unwrap_or_else(|| AWS_DATA_CATALOG.to_string()),
+            )
+            .or_insert_with(glue_data_catalog)
+            .databases
+            .insert(database.name, database.database);
+    }
+    data_catalogs
+}
+
+pub fn create_data_catalog(
+    input: &crate::model::CreateDataCatalogInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let name = input.name.clone();
+    if !DATA_CATALOG_NAME_PATTERN.is_match(&name) {
+        return Ok(invalid_request(format!(
+            "The catalog name {:} must consist of at most 127 alphanumeric, underscore, at sign, or hyphen characters",
+            name
+        )));
+    }
+    let catalog_type = input.catalog_type.clone();
+    if !DATA_CATALOG_TYPES.contains(&catalog_type.as_str()) {
+        return Ok(invalid_request(format!(
+            "1 validation error detected: Value '{:}' at 'type' failed to satisfy constraint: Member must satisfy enum value set: [{:}]",
+            catalog_type,
+            DATA_CATALOG_TYPES.join(", ")
+        )));
+    }
+
+    let mut data_catalogs = data.data_catalogs.write().unwrap();
+    if data_catalogs.contains_key(&name) {
+        return Ok(invalid_request(format!(
+            "DataCatalog {:} already exists",
+            name
+        )));
+    }
+    data_catalogs.insert(
+        name,
+        crate::model::DataCatalog {
+            catalog_type,
+            description: input.description.clone(),
+            parameters: input.parameters.clone().unwrap_or_default(),
+            databases: BTreeMap::new(),
+        },
+    );
+    Ok(ok_response(crate::model::CreateDataCatalogResponse {}))
+}
+
+pub fn get_data_catalog(
+    input: &crate::model::GetDataCatalogInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let name = input.name.clone();
+    let data_catalogs = data.data_catalogs.read().unwrap();
+    let data_catalog = data_catalogs
+        .get(&name)
+        .ok_or_else(|| data_catalog_not_found(&name))?;
+    Ok(ok_response(crate::model::GetDataCatalogResponse::new(
+        name,
+        data_catalog,
+    )))
+}
+
+pub fn list_data_catalogs(
+    input: &crate::model::ListDataCatalogsInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let max_results = max_results(input.max_results, MAX_LIST_DATA_CATALOGS_RESULTS)?;
+
+    // Note: by name, NextToken is the name of the first catalog of the next page.
+    let mut data_catalogs = data
+        .data_catalogs
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(name, v)| (name.clone(), v.clone()))
+        .collect::<Vec<_>>();
+    data_catalogs.sort_by(|a, b| a.0.cmp(&b.0));
+    let (data_catalogs, next_token) = paginate(
+        data_catalogs,
+        input.next_token.as_deref(),
+        max_results,
+        |(name, _)| name,
+    )?;
+
+    Ok(ok_response(crate::model::ListDataCatalogsResponse::new(
+        data_catalogs,
+        next_token,
+    )))
+}
+
+pub fn list_databases(
+    input: &crate::model::ListDatabasesInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let catalog_name = input.catalog_name.clone();
+    let max_results = max_results(input.max_results, MAX_LIST_DATABASES_RESULTS)?;
+
+    // Note: by name, NextToken is the name of the first database of the next page.
+    let databases = data
+        .data_catalogs
+        .read()
+        .unwrap()
+        .get(&catalog_name)
+        .ok_or_else(|| data_catalog_not_found(&catalog_name))?
+        .databases
+        .iter()
+        .map(|(name, v)| (name.clone(), v.clone()))
+        .collect::<Vec<_>>();
+    let (databases, next_token) = paginate(
+        databases,
+        input.next_token.as_deref(),
+        max_results,
+        |(name, _)| name,
+    )?;
+
+    Ok(ok_response(crate::model::ListDatabasesResponse::new(
+        databases, next_token,
+    )))
+}
+
+pub fn get_database(
+    input: &crate::model::GetDatabaseInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let catalog_name = input.catalog_name.clone();
+    let database_name = input.database_name.clone();
+    let data_catalogs = data.data_catalogs.read().unwrap();
+    let database = data_catalogs
+        .get(&catalog_name)
+        .ok_or_else(|| data_catalog_not_found(&catalog_name))?
+        .databases
+        .get(&database_name)
+        .ok_or_else(|| database_not_found(&database_name))?;
+    Ok(ok_response(crate::model::GetDatabaseResponse::new(
+        database_name,
+        database,
+    )))
+}
+
+pub fn list_table_metadata(
+    input: &crate::model::ListTableMetadataInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let max_results = max_results(input.max_results, MAX_LIST_TABLE_METADATA_RESULTS)?;
+    let expression = input
+        .expression
+        .as_deref()
+        .map(|v| {
+            table_name_expression(v)
+                .map_err(|e| invalid_request(format!("invalid Expression {:}: {:}", v, e)))
+        })
+        .transpose()?;
+
+    // Note: by name, NextToken is the name of the first table of the next page.
+    let mut tables = fixture_tables(&data.csv_fixture_dir);
+    for table in database(&input.catalog_name, &input.database_name, data)?.tables {
+        tables.insert(table.name.clone(), table);
+    }
+    let tables = tables
+        .into_values()
+        .filter(|v| expression.as_ref().is_none_or(|e| e.is_match(&v.name)))
+        .collect::<Vec<_>>();
+    let (tables, next_token) = paginate(tables, input.next_token.as_deref(), max_results, |v| {
+        &v.name
+    })?;
+
+    Ok(ok_response(crate::model::ListTableMetadataResponse::new(
+        tables, next_token,
+    )))
+}
+
+pub fn get_table_metadata(
+    input: &crate::model::GetTableMetadataInput,
+    data: &crate::model::AppData,
+) -> Result<HttpResponse> {
+    let table_name = input.table_name.clone();
+    let table = database(&input.catalog_name, &input.database_name, data)?
+        .tables
+        .into_iter()
+        .find(|v| v.name == table_name)
+        .or_else(|| fixture_table(&data.csv_fixture_dir, &table_name))
+        .ok_or_else(|| {
+            error_response(
+                StatusCode::BAD_REQUEST,
+                METADATA_EXCEPTION,
+                format!("Table {:} is not found", table_name),
+            )
+        })?;
+    Ok(ok_response(crate::model::GetTableMetadataResponse::new(
+        table,
+    )))
+}
+
+fn database(
+    catalog_name: &str,
+    database_name: &str,
+    data: &crate::model::AppData,
+) -> std::result::Result<crate::model::Database, HttpResponse> {
+    data.data_catalogs
+        .read()
+        .unwrap()
+        .get(catalog_name)
+        .ok_or_else(|| data_catalog_not_found(catalog_name))?
+        .databases
+        .get(database_name)
+        .cloned()
+        .ok_or_else(|| database_not_found(database_name))
+}
+
+// Note: every database has a table for each fixture of CSV_FIXTURE_DIR, as a query reads it
+// whatever its database is; a table given in DATABASES_FILE replaces the one of its name.
+fn fixture_tables(csv_fixture_dir: &str) -> BTreeMap<String, crate::model::Table> {
+    let table_names = match std::fs::read_dir(csv_fixture_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let extension = path.extension()?.to_str()?;
+                let table_name = path.file_stem()?.to_str()?;
+                // Note: also leaves out the `.schema.json` files next to the fixtures.
+                (FIXTURE_EXTENSIONS.contains(&extension) && !table_name.contains('.'))
+                    .then(|| table_name.to_string())
+            })
+            .collect::<BTreeSet<_>>(),
+        Err(_) => BTreeSet::new(),
+    };
+    table_names
+        .into_iter()
+        .filter_map(|v| fixture_table(csv_fixture_dir, &v))
+        .map(|v| (v.name.clone(), v))
+        .collect()
+}
+
+// Note: the columns are the ones GetQueryResults returns for the fixture, `None` when there's
+// no fixture or it can't be read.
+fn fixture_table(csv_fixture_dir: &str, table_name: &str) -> Option<crate::model::Table> {
+    let fixture_path = fixture_path(csv_fixture_dir, table_name);
+    if !std::path::Path::new(&fixture_path).exists() {
+        return None;
+    }
+    let (columns, _) = read_fixture(&fixture_path).ok()?;
+    let classification = std::path::Path::new(&fixture_path)
+        .extension()
+        .and_then(|v| v.to_str())
+        .unwrap_or(CSV_FIXTURE_EXTENSION);
+    Some(crate::model::Table {
+        name: table_name.to_string(),
+        table_type: crate::model::EXTERNAL_TABLE_TYPE.to_string(),
+        columns: columns.iter().map(Into::into).collect(),
+        partition_keys: Vec::new(),
+        parameters: BTreeMap::from([(
+            TABLE_CLASSIFICATION_PARAMETER.to_string(),
+            classification.to_string(),
+        )]),
+    })
+}
+
+// Note: answered from the data catalogs as ListDatabases, ListTableMetadata and GetTableMetadata
+// do, in the catalog and database of the QueryExecutionContext unless the statement names one;
+// an unknown one fails the query as Athena does.
+fn metadata_query_result(
+    metadata_query: &crate::metadata::MetadataQuery,
+    input: &crate::model::StartQueryExecutionInput,
+    data: &crate::model::AppData,
+) -> std::result::Result<crate::model::QueryResult, String> {
+    let context = input.query_execution_context.as_ref();
+    let catalog_name = context
+        .and_then(|v| v.catalog.clone())
+        .unwrap_or_else(|| AWS_DATA_CATALOG.to_string());
+    let data_catalogs = data.data_catalogs.read().unwrap();
+    let data_catalog = data_catalogs
+        .get(&catalog_name)
+        .ok_or_else(|| format!("Catalog {:} does not exist", catalog_name))?;
+    let database = |database_name: &Option<String>| {
+        let database_name = database_name
+            .clone()
+            .or_else(|| context.and_then(|v| v.database.clone()))
+            .unwrap_or_else(|| crate::DEFAULT_DATABASE.to_string());
+        match data_catalog.databases.get(&database_name) {
+            Some(database) => Ok((database_name, database)),
+            None => Err(format!(
+                "FAILED: SemanticException [Error 10072]: Database does not exist: {:}",
+                database_name
+            )),
+        }
+    };
+    let pattern = |pattern: &Option<String>| {
+        pattern
+            .as_deref()
+            .map(|v| {
+                table_name_expression(v).map_err(|e| format!("invalid pattern {:}: {:}", v, e))
+            })
+            .transpose()
+    };
+    let (column_name, rows) = match metadata_query {
+        crate::metadata::MetadataQuery::ShowDatabases { pattern: p } => {
+            let pattern = pattern(p)?;
+            let rows = data_catalog
+                .databases
+                .keys()
+                .filter(|v| pattern.as_ref().is_none_or(|e| e.is_match(v)))
+                .map(|v| vec![Some(v.clone())])
+                .collect();
+            (crate::metadata::DATABASE_NAME_COLUMN, rows)
+        }
+        crate::metadata::MetadataQuery::ShowTables {
+            database: database_name,
+            pattern: p,
+        } => {
+            let (_, database) = database(database_name)?;
+            let pattern = pattern(p)?;
+            let mut tables = fixture_tables(&data.csv_fixture_dir);
+            for table in &database.tables {
+                tables.insert(table.name.clone(), table.clone());
+            }
+            let rows = tables
+                .into_keys()
+                .filter(|v| pattern.as_ref().is_none_or(|e| e.is_match(v)))
+                .map(|v| vec![Some(v)])
+                .collect();
+            (crate::metadata::TAB_NAME_COLUMN, rows)
+        }
+        crate::metadata::MetadataQuery::Describe {
+            database: database_name,
+            table: table_name,
+        } => {
+            let (database_name, database) = database(database_name)?;
+            let table = database
+                .tables
+                .iter()
+                .find(|v| v.name == *table_name)
+                .cloned()
+                .or_else(|| fixture_table(&data.csv_fixture_dir, table_name))
+                .ok_or_else(|| {
+                    format!(
+                        "FAILED: SemanticException [Error 10001]: Table not found {:}.{:}",
+                        database_name, table_name
+                    )
+                })?;
+            (
+                crate::metadata::COL_NAME_COLUMN,
+                crate::metadata::describe_rows(&table),
+            )
+        }
+        crate::metadata::MetadataQuery::Explain { analyze, statement } => (
+            crate::metadata::QUERY_PLAN_COLUMN,
+            crate::metadata::explain_rows(
+                &catalog_name,
+                &context
+                    .and_then(|v| v.database.clone())
+                    .unwrap_or_else(|| crate::DEFAULT_DATABASE.to_string()),
+                statement,
+                *analyze,
+            ),
+        ),
+    };
+    Ok(crate::model::QueryResult {
+        columns: vec![crate::model::Column::varchar(column_name)],
+        rows,
+    })
+}
+
+// Note: a Hive style pattern as Glue takes it, `*` is any characters and `|` separates the
+// alternatives, e.g. `sales*|orders`.
+fn table_name_expression(expression: &str) -> std::result::Result<Regex, regex::Error> {
+    let alternatives = expression
+        .split('|')
+        .map(|v| regex::escape(v.trim()).replace("\\*", ".*"))
+        .collect::<Vec<_>>();
+    Regex::new(&format!("(?i)^(?:{:})$", alternatives.join("|")))
+}
+
+fn process_query(
+    query_execution_id: String,
+    state_durations: crate::model::StateDurations,
+    data: &crate::model::AppData,
+) {
+    let id = query_execution_id.clone();
+    let handles = data.process_handles.clone();
+    let data = data.clone();
+    let (process, handle) = abortable(async move {
+        let mut deadline = data.clock.now_millis();
+        // Note: set while the execution waits in `waiting_queries` for a running slot.
+        let mut waiting = false;
+        let mut transition = None;
+        // Note: with QUERY_TIMEOUT_SECS, the execution fails once it's still unfinished then.
+        let timeout_at = data.query_timeout.and_then(|timeout| {
+            data.processes
+                .read()
+                .unwrap()
+                .get(&query_execution_id)
+                .map(|v| v.submission_date_time + timeout.as_millis() as u64)
+        });
+        let mut timed_out = false;
+        loop {
+            if waiting {
+                // Note: polled in real time, also with the virtual clock.
+                time::delay_for(RUNNING_SLOT_POLL_INTERVAL).await;
+                let now = data.clock.now_millis();
+                if timeout_at.is_some_and(|v| now >= v) {
+                    transition = Some((QueryExecutionState::Queued, QueryExecutionState::Failed));
+                    waiting = false;
+                    timed_out = true;
+                    deadline = now;
+                }
+            } else {
+                // Note: each state is held for its own duration, counted from when it was entered.
+                let next = data
+                    .processes
+                    .read()
+                    .unwrap()
+                    .get(&query_execution_id)
+                    .and_then(|v| {
+                        let state = QueryExecutionState::from(v.state.as_ref());
+                        data.transition_plan
+                            .next(&crate::transition::TransitionContext {
+                                state: &state,
+                                elapsed: Duration::from_millis(
+                                    deadline.saturating_sub(v.submission_date_time),
+                                ),
+                                outcome: &v.final_state(),
+                                queued: state_durations.queued,
+                                running: state_durations.running,
+                            })
+                            .map(|(next_state, duration)| (state, next_state, duration))
+                    });
+                // Note: `None` means the execution is finished or no longer tracked.
+                let (state, next_state, duration) = match next {
+                    Some(v) => v,
+                    None => break,
+                };
+                let next_deadline = deadline + duration.as_millis() as u64;
+                match timeout_at.filter(|v| next_deadline > *v) {
+                    Some(timeout_at) => {
+                        transition = Some((state, QueryExecutionState::Failed));
+                        timed_out = true;
+                        deadline = deadline.max(timeout_at);
+                    }
+                    None => {
+                        transition = Some((state, next_state));
+                        deadline = next_deadline;
+                    }
+                }
+                data.clock.sleep_until(deadline).await;
+            }
+            let (state, next_state) = match &transition {
+                Some(v) => v.clone(),
+                None => break,
+            };
+            // Note: uploaded and written before the lock is taken, the query stays RUNNING meanwhile.
+            #[cfg(feature = "s3")]
+            let upload_failure = match next_state {
+                QueryExecutionState::Succeeded => upload_output(&data, &query_execution_id).await,
+                _ => None,
+            };
+            let written_query_process = match next_state {
+                QueryExecutionState::Succeeded => data
+                    .processes
+                    .read()
+                    .unwrap()
+                    .get(&query_execution_id)
+                    .cloned()
+                    .filter(|v| write_succeeded_output(&data, &query_execution_id, v)),
+                _ => None,
+            };
+            let recorded_state = {
+                let mut processes = data.processes.write().unwrap();
+                let query_process = match processes.get(&query_execution_id).cloned() {
+                    Some(v) => v,
+                    None => break,
+                };
+                if let (Some(max_running_queries), QueryExecutionState::Running) =
+                    (data.max_running_queries, &next_state)
+                {
+                    let mut waiting_queries = data.waiting_queries.lock().unwrap();
+                    if !waiting {
+                        waiting_queries.push_back(query_execution_id.clone());
+                        waiting = true;
+                    }
+                    // Note: drop executions which were stopped or evicted while waiting.
+                    while let Some(front) = waiting_queries.front() {
+                        let queued = processes
+                            .get(front)
+                            .map(|v| v.state == QueryExecutionState::Queued.as_str())
+                            .unwrap_or(false);
+                        if queued {
+                            break;
+                        }
+                        waiting_queries.pop_front();
+                    }
+                    let running_query_count = count_query_processes(&processes, |v| {
+                        v.state == QueryExecutionState::Running.as_str()
+                    });
+                    if waiting_queries.front() != Some(&query_execution_id)
+                        || running_query_count >= max_running_queries
+                    {
+                        continue;
+                    }
+                    waiting_queries.pop_front();
+                    waiting = false;
+                    deadline = data.clock.now_millis();
+                }
+                let mut next_query_process = query_process.with_state(next_state.clone(), deadline);
+                #[cfg(feature = "s3")]
+                if let Some(reason) = upload_failure {
+                    next_query_process =
+                        query_process.with_state(QueryExecutionState::Failed, deadline);
+                    next_query_process.failure_reason = Some(reason);
+                }
+                if timed_out {
+                    next_query_process.failure_reason = Some(data.query_timeout_message.clone());
+                }
+                if next_state == QueryExecutionState::Failed
+                    && next_query_process.failure_reason.is_none()
+                {
+                    next_query_process.failure_reason =
+                        Some("query is failed by the transition plan".to_string());
+                }
+                let next_state = next_query_process.state.clone();
+                // Note: a concurrent StopQueryExecution makes this a no-op, the state it came from is gone.
+                record_transition(
+                    &data,
+                    &mut processes,
+                    &query_execution_id,
+                    Some(state),
+                    next_query_process,
+                    deadline,
+                )
+                .then_some(next_state)
+            };
+            if let Some(written_query_process) = &written_query_process {
+                if recorded_state.as_deref() != Some(QueryExecutionState::Succeeded.as_str()) {
+                    remove_output(data.output_dir.as_deref(), written_query_process);
+                }
+            }
+            if recorded_state.is_none() {
+                break;
+            }
+        }
+        data.process_handles
+            .lock()
+            .unwrap()
+            .remove(&query_execution_id);
+    });
+    handles.lock().unwrap().insert(id, handle);
+    spawn(async move {
+        let _ = process.await;
+    })
+}
+
+// Note: the records from `offset` on, a generated result starts there without making up the
+// rows before it.
+fn query_result_records(
+    query_process: &crate::model::QueryProcess,
+    data: &crate::model::AppData,
+    offset: usize,
+) -> std::result::Result<(Vec<crate::model::Column>, Records), HttpResponse> {
+    if let Some((rows, cols)) = query_process.generated {
+        return Ok((
+            crate::generate::columns(cols),
+            Box::new(crate::generate::rows(rows, cols, offset as u64).map(Ok)),
+        ));
+    }
+    let (columns, records) = stored_result_records(query_process, data)?;
+    Ok((columns, Box::new(records.skip(offset))))
+}
+
+// Note: the result of a query engine or a fixture loaded at startup comes first, then the fixture
+// file picked by StartQueryExecution (an empty single column result when no fixture matched), and
+// the csv named after the table.
+fn stored_result_records(
+    query_process: &crate::model::QueryProcess,
+    data: &crate::model::AppData,
+) -> std::result::Result<(Vec<crate::model::Column>, Records), HttpResponse> {
+    if let Some(result) = &query_process.result {
+        return Ok((
+            result.columns.clone(),
+            Box::new(result.rows.clone().into_iter().map(Ok)),
+        ));
+    }
+    if data.result_fixtures.is_some() {
+        return match &query_process.result_fixture {
+            Some(fixture_path) => read_fixture(fixture_path),
+            None => Ok((
+                vec![crate::model::Column::varchar(EMPTY_RESULT_COLUMN_NAME)],
+                Box::new(std::iter::empty()),
+            )),
+        };
+    }
+    read_fixture(&fixture_path(
+        &data.csv_fixture_dir,
+        &query_process.table_name,
+    ))
+}
+
+// Note: the size of the result stored_result_records reads, the file size of a fixture and the
+// length of the values of a result in memory or generated.
+fn result_size(query_process: &crate::model::QueryProcess, data: &crate::model::AppData) -> u64 {
+    let file_size = |path: &str| std::fs::metadata(path).map(|v| v.len()).unwrap_or(0);
+    if let Some((rows, cols)) = query_process.generated {
+        return crate::generate::byte_count(rows, cols);
+    }
+    if let Some(result) = &query_process.result {
+        return result.rows.iter().map(record_size).sum();
+    }
+    if data.result_fixtures.is_some() {
+        return query_process
+            .result_fixture
+            .as_deref()
+            .map(file_size)
+            .unwrap_or(0);
+    }
+    if query_process.table_name.is_empty() {
+        return 0;
+    }
+    file_size(&fixture_path(
+        &data.csv_fixture_dir,
+        &query_process.table_name,
+    ))
+}
+
+// Note: the length of the values of the row, NULLs have none.
+fn record_size(record: &crate::model::Record) -> u64 {
+    record.iter().flatten().map(|v| v.len() as u64).sum()
+}
+
+// Note: the csv named after the table is read unless there's only a json or parquet one.
+fn fixture_path(csv_fixture_dir: &str, table_name: &str) -> String {
+    let fixture_path =
+        |extension: &str| format!("{:}/{:}.{:}", csv_fixture_dir, table_name, extension);
+    FIXTURE_EXTENSIONS
+        .iter()
+        .map(|v| fixture_path(v))
+        .find(|v| std::path::Path::new(v).exists())
+        .unwrap_or_else(|| fixture_path(CSV_FIXTURE_EXTENSION))
+}
+
+// Note: the token is opaque to clients, it's the hex encoded execution id and offset of the next row.
+fn query_results_next_token(query_execution_id: &str, offset: usize) -> String {
+    format!("{:}:{:}", query_execution_id, offset)
+        .bytes()
+        .map(|v| format!("{:02x}", v))
+        .collect()
+}
+
+fn query_results_offset(
+    next_token: &str,
+    query_execution_id: &str,
+) -> std::result::Result<usize, HttpResponse> {
+    let invalid_next_token = || invalid_request(format!("invalid NextToken: {:}", next_token));
+    let bytes = (0..next_token.len())
+        .step_by(2)
+        .map(|i| {
+            next_token
+                .get(i..i + 2)
+                .and_then(|v| u8::from_str_radix(v, 16).ok())
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(invalid_next_token)?;
+    let token = String::from_utf8(bytes).map_err(|_| invalid_next_token())?;
+    match token.rsplit_once(':') {
+        Some((id, offset)) if id == query_execution_id => {
+            offset.parse::<usize>().map_err(|_| invalid_next_token())
+        }
+        _ => Err(invalid_next_token()),
+    }
+}
+
+// Note: the format of a fixture is told by its extension, csv unless it's `.json` or `.parquet`.
+fn read_fixture(
+    fixture_path: &str,
+) -> std::result::Result<(Vec<crate::model::Column>, Records), HttpResponse> {
+    match std::path::Path::new(fixture_path)
+        .extension()
+        .and_then(|v| v.to_str())
+    {
+        Some(JSON_FIXTURE_EXTENSION) => {
+            let result = crate::model::fixtures::read_json_fixture(fixture_path)
+                .map_err(|e| internal_server_error(e.to_string()))?;
+            Ok((result.columns, Box::new(result.rows.into_iter().map(Ok))))
+        }
+        #[cfg(feature = "parquet")]
+        Some(PARQUET_FIXTURE_EXTENSION) => {
+            let (columns, rows) = crate::model::fixtures::read_parquet_fixture(fixture_path)
+                .map_err(|e| internal_server_error(e.to_string()))?;
+            Ok((
+                columns,
+                Box::new(rows.map(|v| v.map_err(|e| internal_server_error(e.to_string())))),
+            ))
+        }
+        #[cfg(not(feature = "parquet"))]
+        Some(PARQUET_FIXTURE_EXTENSION) => Err(internal_server_error(format!(
+            "{:} needs minerva built with the parquet feature",
+            fixture_path
+        ))),
+        _ => read_csv_fixture(fixture_path),
+    }
+}
+
+// Note: the column types are inferred from the first rows, which are read ahead.
+fn read_csv_fixture(
+    fixture_path: &str,
+) -> std::result::Result<(Vec<crate::model::Column>, Records), HttpResponse> {
+    let f = File::open(fixture_path)
+        .map_err(|e| internal_server_error(format!("failed to open {:}: {:}", fixture_path, e)))?;
+    let schema = crate::model::fixtures::FixtureSchema::load(fixture_path)
+        .map_err(|e| internal_server_error(e.to_string()))?;
+    let mut csv_records =
+        crate::model::fixtures::CsvRecords::new(BufReader::new(f), schema.null.clone());
+
+    let names = csv_records
+        .headers()
+        .map_err(|_| internal_server_error("failed to read csv headers".to_string()))?;
+    let mut records = csv_records.map(|records| {
+        records.map_err(|_| internal_server_error("failed to read csv fixture".to_string()))
+    });
+    let sample = records
+        .by_ref()
+        .take(crate::model::fixtures::TYPE_INFERENCE_SAMPLE_ROWS)
+        .collect::<Vec<_>>();
+    let columns = schema.columns(
+        &names,
+        &sample
+            .iter()
+            .filter_map(|v| v.as_ref().ok().cloned())
+            .collect::<Vec<_>>(),
+    );
+    Ok((columns, Box::new(sample.into_iter().chain(records))))
+}
+
+/// A ClientRequestToken reserved while its execution is started, released unless it's completed
+/// with the id of the execution.
+struct ClientRequestTokenReservation<'a> {
+    data: &'a crate::model::AppData,
+    client_request_token: Option<String>,
+}
+
+impl ClientRequestTokenReservation<'_> {
+    fn complete(mut self, query_execution_id: &str) {
+        if let Some(client_request_token) = self.client_request_token.take() {
+            let mut client_request_tokens = self.data.client_request_tokens.lock().unwrap();
+            if let Some((_, id)) = client_request_tokens.get_mut(&client_request_token) {
+                *id = Some(query_execution_id.to_string());
+            }
+            self.data.client_request_tokens_changed.notify_all();
+        }
+    }
+}
+
+impl Drop for ClientRequestTokenReservation<'_> {
+    fn drop(&mut self) {
+        if let Some(client_request_token) = self.client_request_token.take() {
+            self.data
+                .client_request_tokens
+                .lock()
+                .unwrap()
+                .remove(&client_request_token);
+            self.data.client_request_tokens_changed.notify_all();
+        }
+    }
+}
+
+// Note: the lock is only held to look up and reserve the token; a retry with a token whose
+// execution is still being started waits for its id, and the response to give instead of
+// starting one is the `Err`.
+fn reserve_client_request_token<'a>(
+    input: &crate::model::StartQueryExecutionInput,
+    data: &'a crate::model::AppData,
+) -> std::result::Result<ClientRequestTokenReservation<'a>, HttpResponse> {
+    let client_request_token = match &input.client_request_token {
+        Some(v) => v,
+        None => {
+            return Ok(ClientRequestTokenReservation {
+                data,
+                client_request_token: None,
+            })
+        }
+    };
+    let mut client_request_tokens = data.client_request_tokens.lock().unwrap();
+    loop {
+        match client_request_tokens.get(client_request_token) {
+            Some((token_query_string, _)) if *token_query_string != input.query_string => {
+                return Err(error_response(
+                    StatusCode::BAD_REQUEST,
+                    IDEMPOTENT_PARAMETER_MISMATCH_EXCEPTION,
+                    format!(
+                        "ClientRequestToken {:} is already used for another query",
+                        client_request_token
+                    ),
+                ));
+            }
+            Some((_, Some(query_execution_id))) => {
+                return Err(ok_response(crate::model::StartQueryExecutionResponse::new(
+                    query_execution_id.clone(),
+                )));
+            }
+            Some((_, None)) => {
+                client_request_tokens = data
+                    .client_request_tokens_changed
+                    .wait(client_request_tokens)
+                    .unwrap();
+            }
+            None => break,
+        }
+    }
+    client_request_tokens.insert(
+        client_request_token.clone(),
+        (input.query_string.clone(), None),
+    );
+    Ok(ClientRequestTokenReservation {
+        data,
+        client_request_token: Some(client_request_token.clone()),
+    })
+}
+
+// Note: `query-0001`, `query-0002`, ... with DETERMINISTIC_IDS, for tests which compare ids.
+fn new_query_execution_id(data: &crate::model::AppData) -> String {
+    match &data.query_execution_id_counter {
+        Some(counter) => format!(
+            "query-{:04}",
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1
+        ),
+        None => Uuid::new_v4().to_string(),
+    }
+}
+
+// Note: the client's OutputLocation (OUTPUT_LOCATION when none is given), or the object of the
+// result under it when the results are written to OUTPUT_DIR, as Athena reports it.
+fn output_location(
+    input: &crate::model::StartQueryExecutionInput,
+    work_group: &crate::model::WorkGroup,
+    data: &crate::model::AppData,
+    query_execution_id: &str,
+) -> Option<String> {
+    let output_location =
+        result_configuration_setting(input, work_group, |v| v.output_location.clone())
+            .unwrap_or_else(|| data.default_output_location.clone());
+    if !writes_output(data) {
+        return Some(output_location);
+    }
+    let output_location = format!(
+        "{:}/{:}.csv",
+        output_location.trim_end_matches('/'),
+        query_execution_id
+    );
+    #[cfg(feature = "s3")]
+    if let Some(result_uploader) = &data.result_uploader {
+        return Some(result_uploader.output_location(&output_location));
+    }
+    Some(output_location)
+}
+
+// Note: a setting of the ResultConfiguration of the query or its workgroup; the workgroup's wins
+// when it enforces its configuration, it's only the default otherwise.
+fn result_configuration_setting<T>(
+    input: &crate::model::StartQueryExecutionInput,
+    work_group: &crate::model::WorkGroup,
+    setting: impl Fn(&crate::model::ResultConfiguration) -> Option<T>,
+) -> Option<T> {
+    let requested = input.result_configuration.as_ref().and_then(&setting);
+    let work_group_setting = work_group
+        .configuration
+        .result_configuration
+        .as_ref()
+        .and_then(&setting);
+    if work_group.configuration.enforce_work_group_configuration {
+        work_group_setting.or(requested)
+    } else {
+        requested.or(work_group_setting)
+    }
+}
+
+/// The validation error of an `EncryptionConfiguration` given at `path`, if any: the KMS options
+/// need a `KmsKey`.
+pub fn encryption_configuration_error(
+    encryption_configuration: &crate::model::EncryptionConfiguration,
+    path: &str,
+) -> Option<String> {
+    let encryption_option = encryption_configuration.encryption_option.as_str();
+    if !ENCRYPTION_OPTIONS.contains(&encryption_option) {
+        return Some(format!(
+            "1 validation error detected: Value '{:}' at '{:}.encryptionConfiguration.encryptionOption' failed to satisfy constraint: Member must satisfy enum value set: [{:}]",
+            encryption_option,
+            path,
+            ENCRYPTION_OPTIONS.join(", ")
+        ));
+    }
+    if encryption_option != ENCRYPTION_OPTION_SSE_S3 && encryption_configuration.kms_key.is_none() {
+        return Some(format!("KmsKey is required for {:}", encryption_option));
+    }
+    None
+}
+
+// Note: whether the results are written to OUTPUT_DIR or uploaded, when OutputLocation is the
+// object of the result.
+fn writes_output(data: &crate::model::AppData) -> bool {
+    #[cfg(feature = "s3")]
+    if data.result_uploader.is_some() {
+        return true;
+    }
+    data.output_dir.is_some()
+}
+
+// Note: `Some` with the StateChangeReason when the result couldn't be uploaded, as Athena fails
+// a query whose result it can't write to its OutputLocation.
+#[cfg(feature = "s3")]
+async fn upload_output(data: &crate::model::AppData, query_execution_id: &str) -> Option<String> {
+    let result_uploader = data.result_uploader.as_ref()?;
+    let query_process = data
+        .processes
+        .read()
+        .unwrap()
+        .get(query_execution_id)
+        .cloned()?;
+    let output_location = query_process.output_location.clone()?;
+    if !query_process.statement_kind.has_rows() {
+        return None;
+    }
+    let objects = query_result_records(&query_process, data, 0)
+        .map_err(|_| "failed to read the result".to_string())
+        .and_then(|(columns, records)| {
+            crate::output::result_objects(
+                &query_process.table_name,
+                &columns,
+                records.map(|v| v.map_err(|_| std::io::Error::other("failed to read the result"))),
+                data.output_metadata,
+            )
+            .map_err(|e| e.to_string())
+        });
+    let result = match objects {
+        Ok((csv, metadata)) => {
+            result_uploader
+                .upload(&output_location, csv, metadata)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    result.err().map(|e| {
+        tracing::warn!(
+            "failed to upload the result of {:} to {:}: {:}",
+            query_execution_id,
+            output_location,
+            e
+        );
+        format!(
+            "Unable to write the query result to the output location {:}: {:}",
+            output_location, e
+        )
+    })
+}
+
+// Note: the result of an execution which is about to be SUCCEEDED is written before the state is
+// recorded, so the file is there once the query is seen SUCCEEDED, and without holding
+// `processes` as the whole result is read; returns whether it was written.
+fn write_succeeded_output(
+    data: &crate::model::AppData,
+    query_execution_id: &str,
+    query_process: &crate::model::QueryProcess,
+) -> bool {
+    if let (Some(output_dir), Some(output_location), true) = (
+        &data.output_dir,
+        &query_process.output_location,
+        query_process.statement_kind.has_rows(),
+    ) {
+        match write_output(data, output_dir, output_location, query_process) {
+            Ok(()) => return true,
+            Err(e) => tracing::warn!(
+                "failed to write the result of {:} to {:}: {:}",
+                query_execution_id,
+                output_location,
+                e
+            ),
+        }
+    }
+    false
+}
+
+fn write_output(
+    data: &crate::model::AppData,
+    output_dir: &str,
+    output_location: &str,
+    query_process: &crate::model::QueryProcess,
+) -> std::io::Result<()> {
+    let failed_to_read = || std::io::Error::other("failed to read the result");
+    let (columns, records) =
+        query_result_records(query_process, data, 0).map_err(|_| failed_to_read())?;
+    crate::output::write_result(
+        &crate::output::output_path(output_dir, output_location),
+        &query_process.table_name,
+        &columns,
+        records.map(|v| v.map_err(|_| failed_to_read())),
+        data.output_metadata,
+    )
+}
+
+// Note: echoes the EngineVersion sent by the client, the effective one follows the selected one unless it's AUTO.
+fn engine_version(
+    requested: Option<&crate::model::EngineVersion>,
+    default_engine_version: &str,
+) -> crate::model::EngineVersion {
+    let selected_engine_version = requested
+        .and_then(|v| v.selected_engine_version.clone())
+        .unwrap_or_else(|| default_engine_version.to_string());
+    let effective_engine_version = requested
+        .and_then(|v| v.effective_engine_version.clone())
+        .unwrap_or_else(|| {
+            if selected_engine_version == AUTO_ENGINE_VERSION {
+                default_engine_version.to_string()
+            } else {
+                selected_engine_version.clone()
+            }
+        });
+    crate::model::EngineVersion {
+        selected_engine_version: Some(selected_engine_version),
+        effective_engine_version: Some(effective_engine_version),
+    }
+}
+
+fn table_name(query_string: &str, ast: &[Statement]) -> std::result::Result<String, HttpResponse> {
+    if ast.len() != 1 {
+        return Err(invalid_request(format!(
+            "unsupported query: {:}, ast.len() = {:}",
+            query_string,
+            ast.len()
+        )));
+    }
+    let table_name = match &ast[0] {
+        // Note: the rows an INSERT or CTAS writes are the ones its query reads.
+        Statement::Query(query)
+        | Statement::Insert { source: query, .. }
+        | Statement::CreateTable {
+            query: Some(query), ..
+        } => match &query.body {
+            SetExpr::Select(select) => {
+                if select.from.len() != 1 {
+                    return Err(invalid_request(format!(
+                        "unsupported query: {:}, select.from.len() = {:}",
+                        query_string,
+                        select.from.len()
+                    )));
+                }
+                match &select.from[0].relation {
+                    TableFactor::Table {
+                        name: ObjectName(name),
+                        alias: _,
+                        args: _,
+                        with_hints: _,
+                    } => {
+                        if name.len() == 1 {
+                            // Note: only `tablename`
+                            &name[0].value
+                        } else if name.len() == 2 {
+                            // Note: `databasename.tablename`
+                            &name[1].value
+                        } else {
+                            return Err(invalid_request(format!(
+                                "unsupported query: {:}, name.len() = {:}",
+                                query_string,
+                                name.len()
+                            )));
+                        }
+                    }
+                    relation => {
+                        return Err(invalid_request(format!(
+                            "unsupported query: {:}, relation = {:?}",
+                            query_string, relation
+                        )))
+                    }
+                }
+            }
+            stmt => {
+                return Err(invalid_request(format!(
+                    "unsupported query: {:}, statement = {:?}",
+                    query_string, stmt
+                )))
+            }
+        },
+        _ => {
+            return Err(invalid_request(format!(
+                "unsupported query: {:}",
+                query_string
+            )))
+        }
+    };
+    Ok(table_name.clone())
+}
+
+// Note: every state change goes through here, so the events subscribers and the webhook see all of them.
+// It's a compare-and-swap: the change is only applied if the execution is still in `previous_state`
+// (`None` for a new one), and a finished execution is never overwritten.
+fn record_transition(
+    data: &crate::model::AppData,
+    processes: &mut HashMap<String, crate::model::QueryProcess>,
+    query_execution_id: &str,
+    previous_state: Option<QueryExecutionState>,
+    query_process: crate::model::QueryProcess,
+    at: u64,
+) -> bool {
+    let current = processes.get(query_execution_id);
+    if current.map(|v| QueryExecutionState::from(v.state.as_ref())) != previous_state
+        || current.is_some_and(|v| v.is_terminal())
+    {
+        return false;
+    }
+    data.events.publish(
+        query_execution_id,
+        previous_state.clone(),
+        &query_process.state,
+        at,
+    );
+    if let (Some(url), Some(previous_state), true) = (
+        &data.completion_webhook_url,
+        previous_state,
+        query_process.is_terminal(),
+    ) {
+        crate::webhook::notify_completion(url, query_execution_id, previous_state, &query_process);
+    }
+    processes.insert(query_execution_id.to_string(), query_process);
+    true
+}
+
+// Note: only finished executions are evicted, so no process_query task can re-insert them.
+pub fn evict_queries(
+    ttl: Option<Duration>,
+    retention: Option<Duration>,
+    output_dir: Option<String>,
+    clock: Arc<dyn crate::clock::Clock>,
+    processes: crate::model::QueryProcesses,
+    client_request_tokens: crate::model::ClientRequestTokens,
+) -> AbortHandle {
+    let (eviction, handle) = abortable(async move {
+        let mut interval = time::interval(EVICTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            let now = clock.now_millis();
+            let expired_at = |v: Duration| now.saturating_sub(v.as_millis() as u64);
+            let mut evicted = Vec::new();
+            processes.write().unwrap().retain(|id, v| {
+                // Note: QUERY_TTL_SECS counts from the submission, QUERY_RETENTION_SECS from the
+                // completion, so a long query is still read for the whole retention once done.
+                let expired = v.is_terminal()
+                    && (ttl.is_some_and(|ttl| v.submission_date_time < expired_at(ttl))
+                        || retention.is_some_and(|retention| {
+                            v.completion_date_time.is_some_and(|completion_date_time| {
+                                completion_date_time < expired_at(retention)
+                            })
+                        }));
+                if expired {
+                    evicted.push((id.clone(), v.clone()));
+                }
+                !expired
+            });
+            forget_query_processes(output_dir.as_deref(), &client_request_tokens, &evicted);
+        }
+    });
+    spawn(async move {
+        let _ = eviction.await;
+    });
+    handle
+}
+
+// Note: makes room for one more execution by evicting the oldest finished ones, returns them
+// or `None` when there isn't enough of them.
+fn evict_oldest_queries(
+    max_tracked_queries: usize,
+    processes: &mut HashMap<String, crate::model::QueryProcess>,
+) -> Option<Vec<(String, crate::model::QueryProcess)>> {
+    if processes.len() < max_tracked_queries {
+        return Some(Vec::new());
+    }
+    let excess = processes.len() + 1 - max_tracked_queries;
+    let mut finished = processes
+        .iter()
+        .filter(|(_, v)| v.is_terminal())
+        .map(|(id, v)| (v.submission_date_time, id.clone()))
+        .collect::<Vec<_>>();
+    if finished.len() < excess {
+        return None;
+    }
+    finished.sort();
+    let evicted = finished
+        .into_iter()
+        .take(excess)
+        .filter_map(|(_, id)| processes.remove(&id).map(|v| (id, v)))
+        .collect();
+    // Note: the new execution brings the count back to the cap.
+    tracing::info!(
+        "evicted {:} finished query executions, tracking {:} (MAX_TRACKED_QUERIES)",
+        excess,
+        max_tracked_queries
+    );
+    Some(evicted)
+}
+
+// Note: called once `processes` is released, as the token lock is taken before it. A token of a
+// forgotten execution is released too, so a retry with it starts a new execution instead of
+// returning an id which isn't found anymore.
+fn forget_query_processes(
+    output_dir: Option<&str>,
+    client_request_tokens: &crate::model::ClientRequestTokens,
+    query_processes: &[(String, crate::model::QueryProcess)],
+) {
+    if query_processes.is_empty() {
+        return;
+    }
+    for (_, query_process) in query_processes {
+        remove_output(output_dir, query_process);
+    }
+    client_request_tokens
+        .lock()
+        .unwrap()
+        .retain(|_, (_, query_execution_id)| {
+            !query_execution_id
+                .as_ref()
+                .is_some_and(|query_execution_id| {
+                    query_processes
+                        .iter()
+                        .any(|(id, _)| id == query_execution_id)
+                })
+        });
+}
+
+/// Removes the result files written to OUTPUT_DIR of an execution which is forgotten.
+pub fn remove_output(output_dir: Option<&str>, query_process: &crate::model::QueryProcess) {
+    if let (Some(output_dir), Some(output_location)) = (output_dir, &query_process.output_location)
+    {
+        if let Err(e) =
+            crate::output::remove_result(&crate::output::output_path(output_dir, output_location))
+        {
+            tracing::warn!(
+                "failed to remove the result at {:}: {:}",
+                output_location,
+                e
+            );
+        }
+    }
+}
+
+// Note: `processes` is locked before `waiting_queries`, as process_query does.
+fn running_slots_taken(data: &crate::model::AppData) -> bool {
+    data.max_running_queries.is_some_and(|max_running_queries| {
+        let processes = data.processes.read().unwrap();
+        !data.waiting_queries.lock().unwrap().is_empty()
+            || count_query_processes(&processes, |v| {
+                v.state == QueryExecutionState::Running.as_str()
+            }) >= max_running_queries
+    })
+}
+
+fn count_query_processes(
+    processes: &HashMap<String, crate::model::QueryProcess>,
+    predicate: impl Fn(&crate::model::QueryProcess) -> bool,
+) -> usize {
+    processes.values().filter(|v| predicate(v)).count()
+}
+
+fn find_query_process(
+    query_execution_id: &str,
+    data: &crate::model::AppData,
+) -> std::result::Result<(String, crate::model::QueryProcess), HttpResponse> {
+    let query_process = data
+        .processes
+        .read()
+        .unwrap()
+        .get(query_execution_id)
+        .cloned()
+        .ok_or_else(|| query_execution_not_found(query_execution_id))?;
+    Ok((query_execution_id.to_string(), query_process))
+}
+
+// Note: NextToken is the key of the first item of the next page, so it becomes invalid once that
+// item is gone.
+fn paginate<T>(
+    items: Vec<T>,
+    next_token: Option<&str>,
+    max_results: u64,
+    key: impl Fn(&T) -> &str,
+) -> std::result::Result<(Vec<T>, Option<String>), HttpResponse> {
+    let offset = match next_token {
+        Some(next_token) => items
+            .iter()
+            .position(|v| key(v) == next_token)
+            .ok_or_else(|| invalid_request(format!("invalid NextToken: {:}", next_token)))?,
+        None => 0,
+    };
+    let mut items = items.into_iter().skip(offset);
+    let page = items.by_ref().take(max_results as usize).collect();
+    let next_token = items.next().map(|v| key(&v).to_string());
+    Ok((page, next_token))
+}
+
+// Note: MaxResults out of its range is rejected with the validation error of Athena.
+fn max_results(max_results: Option<u64>, max: u64) -> std::result::Result<u64, HttpResponse> {
+    match max_results {
+        None => Ok(max),
+        Some(v) if v < 1 => Err(invalid_request(format!(
+            "1 validation error detected: Value '{:}' at 'maxResults' failed to satisfy constraint: Member must have value greater than or equal to 1",
+            v
+        ))),
+        Some(v) if v > max => Err(invalid_request(format!(
+            "1 validation error detected: Value '{:}' at 'maxResults' failed to satisfy constraint: Member must have value less than or equal to {:}",
+            v, max
+        ))),
+        Some(v) => Ok(v),
+    }
+}
+
+fn work_group_not_found(name: &str) -> HttpResponse {
+    invalid_request(format!("WorkGroup {:} is not found", name))
+}
+
+fn prepared_statement_not_found((work_group, statement_name): &(String, String)) -> HttpResponse {
+    error_response(
+        StatusCode::BAD_REQUEST,
+        RESOURCE_NOT_FOUND_EXCEPTION,
+        format!(
+            "Prepared statement {:} is not found in WorkGroup {:}",
+            statement_name, work_group
+        ),
+    )
+}
+
+// Note: a workgroup or data catalog, e.g. `arn:aws:athena:us-east-1:123456789012:workgroup/primary`,
+// which has to be there; the region and account aren't checked, so its tags are kept by
+// `resource_key`.
+fn tagged_resource(
+    resource_arn: &str,
+    data: &crate::model::AppData,
+) -> std::result::Result<String, HttpResponse> {
+    let (resource_type, name) = match resource_of_arn(resource_arn) {
+        Some(v) => v,
+        None => {
+            return Err(invalid_request(format!(
+                "{:} is not the ARN of a workgroup or data catalog",
+                resource_arn
+            )))
+        }
+    };
+    let exists = match resource_type {
+        RESOURCE_TYPE_WORK_GROUP => data.work_groups.read().unwrap().contains_key(name),
+        _ => data.data_catalogs.read().unwrap().contains_key(name),
+    };
+    if !exists {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            RESOURCE_NOT_FOUND_EXCEPTION,
+            format!("The resource {:} is not found", resource_arn),
+        ));
+    }
+    Ok(resource_key(resource_type, name))
+}
+
+fn resource_key(resource_type: &str, name: &str) -> String {
+    format!("{:}/{:}", resource_type, name)
+}
+
+// Note: the type, `workgroup` or `datacatalog`, and name of the resource of an Athena ARN.
+fn resource_of_arn(resource_arn: &str) -> Option<(&str, &str)> {
+    let mut parts = resource_arn.splitn(6, ':');
+    if parts.next() != Some("arn") || parts.nth(1) != Some("athena") {
+        return None;
+    }
+    let (resource_type, name) = parts.nth(2)?.split_once('/')?;
+    match resource_type {
+        RESOURCE_TYPE_WORK_GROUP | RESOURCE_TYPE_DATA_CATALOG if !name.is_empty() => {
+            Some((resource_type, name))
+        }
+        _ => None,
+    }
+}
+
+fn tags_error(tags: &[crate::model::Tag]) -> Option<String> {
+    for (i, tag) in tags.iter().enumerate() {
+        let path = format!("tags.{:}.member", i + 1);
+        if let Some(message) = tag_key_error(&tag.key, &format!("{:}.key", path)) {
+            return Some(message);
+        }
+        if tag.value.chars().count() > MAX_TAG_VALUE_LENGTH {
+            return Some(format!(
+                "1 validation error detected: Value '{:}' at '{:}.value' failed to satisfy constraint: Member must have length less than or equal to {:}",
+                tag.value, path, MAX_TAG_VALUE_LENGTH
+            ));
+        }
+    }
+    None
+}
+
+fn too_many_tags_message(resource: &str) -> String {
+    format!(
+        "The resource {:} can have at most {:} tags",
+        resource, MAX_TAGS
+    )
+}
+
+fn tag_key_error(key: &str, path: &str) -> Option<String> {
+    let length = key.chars().count();
+    if length < 1 {
+        return Some(format!(
+            "1 validation error detected: Value '{:}' at '{:}' failed to satisfy constraint: Member must have length greater than or equal to 1",
+            key, path
+        ));
+    }
+    if length > MAX_TAG_KEY_LENGTH {
+        return Some(format!(
+            "1 validation error detected: Value '{:}' at '{:}' failed to satisfy constraint: Member must have length less than or equal to {:}",
+            key, path, MAX_TAG_KEY_LENGTH
+        ));
+    }
+    None
+}
+
+// Note: `Some` with the max age when ResultReuseByAgeConfiguration is enabled.
+fn result_reuse_max_age(
+    input: &crate::model::StartQueryExecutionInput,
+) -> std::result::Result<Option<Duration>, HttpResponse> {
+    let configuration = match input
+        .result_reuse_configuration
+        .as_ref()
+        .and_then(|v| v.result_reuse_by_age_configuration.as_ref())
+    {
+        Some(v) if v.enabled => v,
+        _ => return Ok(None),
+    };
+    match configuration
+        .max_age_in_minutes
+        .unwrap_or(DEFAULT_RESULT_REUSE_MAX_AGE_IN_MINUTES)
+    {
+        v if v > MAX_RESULT_REUSE_MAX_AGE_IN_MINUTES => Err(invalid_request(format!(
+            "1 validation error detected: Value '{:}' at 'resultReuseConfiguration.resultReuseByAgeConfiguration.maxAgeInMinutes' failed to satisfy constraint: Member must have value less than or equal to {:}",
+            v, MAX_RESULT_REUSE_MAX_AGE_IN_MINUTES
+        ))),
+        v => Ok(Some(Duration::from_secs(v * 60))),
+    }
+}
+
+// Note: the latest execution of the same query string which SUCCEEDED within `max_age`.
+fn reusable_query_process(
+    data: &crate::model::AppData,
+    query_string: &str,
+    max_age: Duration,
+) -> Option<crate::model::QueryProcess> {
+    let since = data
+        .clock
+        .now_millis()
+        .saturating_sub(max_age.as_millis() as u64);
+    let processes = data.processes.read().unwrap();
+    let reusable = processes
+        .iter()
+        .filter(|(_, v)| {
+            v.state == QueryExecutionState::Succeeded.as_str()
+                && v.completion_date_time.is_some_and(|v| v >= since)
+        })
+        .collect::<Vec<_>>();
+    let reused = reusable
+        .iter()
+        .filter(|(_, v)| v.query_string == query_string)
+        .max_by_key(|(_, v)| v.completion_date_time);
+    match reused {
+        Some((query_execution_id, _)) => tracing::info!(
+            cached_results = reusable.len(),
+            reused_query_execution_id = %query_execution_id,
+            "reusing the result of a previous execution"
+        ),
+        None => tracing::info!(
+            cached_results = reusable.len(),
+            "no previous result to reuse"
+        ),
+    }
+    reused.map(|(_, v)| (*v).clone())
+}
+
+fn data_catalog_not_found(name: &str) -> HttpResponse {
+    invalid_request(format!("DataCatalog {:} is not found", name))
+}
+
+fn database_not_found(name: &str) -> HttpResponse {
+    error_response(
+        StatusCode::BAD_REQUEST,
+        METADATA_EXCEPTION,
+        format!("Database {:} is not found", name),
+    )
+}
+
+fn named_query_not_found_message(named_query_id: &str) -> String {
+    format!("NamedQuery {:} was not found", named_query_id)
+}
+
+fn query_execution_not_found(query_execution_id: &str) -> HttpResponse {
+    invalid_request(query_execution_not_found_message(query_execution_id))
+}
+
+fn query_execution_not_found_message(query_execution_id: &str) -> String {
+    format!("QueryExecution {:} was not found", query_execution_id)
+}
+
+fn ok_response<T: serde::Serialize>(value: T) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(AMZ_JSON_CONTENT_TYPE)
+        .json(value)
+}
+
+pub fn invalid_request(message: String) -> HttpResponse {
+    error_response(StatusCode::BAD_REQUEST, INVALID_REQUEST_EXCEPTION, message)
+}
+
+fn internal_server_error(message: String) -> HttpResponse {
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        INTERNAL_SERVER_EXCEPTION,
+        message,
+    )
+}
+
+pub fn error_response(status: StatusCode, error_type: &str, message: String) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(AMZ_JSON_CONTENT_TYPE)
+        .json(crate::model::ErrorResponse::new(error_type, message))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use actix_web::body::{Body, ResponseBody};
+    use actix_web::http::StatusCode;
+    use serde_json::json;
+
+    pub(crate) fn app_data(builder: crate::MinervaServerBuilder) -> crate::model::AppData {
+        builder.app_data().unwrap()
+    }
+
+    // Note: the operation is dispatched like `root` does, without a listener.
+    pub(crate) fn call(
+        data: &crate::model::AppData,
+        operation: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let (status, _, body) = dispatch(data, &format!("AmazonAthena.{:}", operation), body);
+        let body = match body.is_empty() {
+            true => serde_json::Value::Null,
+            false => serde_json::from_slice(&body).unwrap(),
+        };
+        (status, body)
+    }
+
+    // Note: the status, content type and body the operation of `target` answers.
+    pub(crate) fn dispatch(
+        data: &crate::model::AppData,
+        target: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, String, Vec<u8>) {
+        let mut response = crate::dispatch::Registry::new()
+            .dispatch(target, body, data)
+            .unwrap_or_else(HttpResponse::from_error);
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = match response.take_body() {
+            ResponseBody::Body(Body::Bytes(v)) | ResponseBody::Other(Body::Bytes(v)) => v.to_vec(),
+            _ => Vec::new(),
+        };
+        (response.status(), content_type, body)
+    }
+
+    pub(crate) fn start(data: &crate::model::AppData, query_string: &str) -> String {
+        let (status, body) = call(
+            data,
+            "StartQueryExecution",
+            json!({ "QueryString": query_string }),
+        );
+        assert_eq!(status, StatusCode::OK, "{:}", body);
+        body["QueryExecutionId"].as_str().unwrap().to_string()
+    }
+
+    pub(crate) fn query_execution(
+        data: &crate::model::AppData,
+        query_execution_id: &str,
+    ) -> serde_json::Value {
+        let (status, body) = call(
+            data,
+            "GetQueryExecution",
+            json!({ "QueryExecutionId": query_execution_id }),
+        );
+        assert_eq!(status, StatusCode::OK, "{:}", body);
+        body["QueryExecution"].clone()
+    }
+
+    pub(crate) fn state(data: &crate::model::AppData, query_execution_id: &str) -> String {
+        query_execution(data, query_execution_id)["Status"]["State"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    // Note: a directory of its own for each test, left in the temp dir.
+    pub(crate) fn fixture_dir(fixtures: &[(&str, &str)]) -> String {
+        let dir = std::env::temp_dir().join(format!("minerva-{:}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in fixtures {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+        dir.to_str().unwrap().to_string()
+    }
+
+    pub(crate) fn create_named_query(data: &crate::model::AppData, name: &str) -> String {
+        let (status, body) = call(
+            data,
+            "CreateNamedQuery",
+            json!({ "Name": name, "Database": "default", "QueryString": "SELECT * FROM users" }),
+        );
+        assert_eq!(status, StatusCode::OK, "{:}", body);
+        body["NamedQueryId"].as_str().unwrap().to_string()
+    }
+
+    // Note: lets the spawned process_query tasks run up to their next wait.
+    pub(crate) async fn settle() {
+        time::delay_for(Duration::from_millis(20)).await;
+    }
+
+    pub(crate) async fn advance(data: &crate::model::AppData, duration: Duration) {
+        settle().await;
+        data.clock.advance(duration).unwrap();
+        settle().await;
+    }
+
+    #[actix_rt::test]
+    async fn get_query_execution_right_after_start_query_execution_is_queued() {
+        let data =
+            app_data(crate::MinervaServer::builder().process_interval(Duration::from_secs(60)));
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        assert_eq!(state(&data, &query_execution_id), "QUEUED");
+    }
+
+    #[actix_rt::test]
+    async fn each_state_is_held_for_its_own_duration() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .queued_duration(Duration::from_secs(0))
+                .running_duration(Duration::from_secs(5)),
+        );
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        settle().await;
+        assert_eq!(state(&data, &query_execution_id), "RUNNING");
+        advance(&data, Duration::from_millis(4999)).await;
+        assert_eq!(state(&data, &query_execution_id), "RUNNING");
+        advance(&data, Duration::from_millis(1)).await;
+        assert_eq!(state(&data, &query_execution_id), "SUCCEEDED");
+    }
+
+    #[actix_rt::test]
+    async fn a_duration_hint_applies_to_its_own_execution_only() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .process_interval(Duration::from_secs(1)),
+        );
+        let hinted = start(&data, "SELECT * FROM users -- minerva: running=30s");
+        let other = start(&data, "SELECT * FROM users");
+        advance(&data, Duration::from_secs(2)).await;
+        assert_eq!(state(&data, &hinted), "RUNNING");
+        assert_eq!(state(&data, &other), "SUCCEEDED");
+        advance(&data, Duration::from_secs(29)).await;
+        assert_eq!(state(&data, &hinted), "SUCCEEDED");
+    }
+
+    #[test]
+    fn an_immediate_query_is_succeeded_from_the_start() {
+        let csv_fixture_dir = fixture_dir(&[("users.csv", "id,name\n1,foo\n")]);
+        for builder in [
+            crate::MinervaServer::builder().immediate(true),
+            crate::MinervaServer::builder().process_interval(Duration::from_secs(0)),
+        ] {
+            let data = app_data(builder.csv_fixture_dir(csv_fixture_dir.clone()));
+            let query_execution_id = start(&data, "SELECT * FROM users");
+            assert_eq!(state(&data, &query_execution_id), "SUCCEEDED");
+            assert!(data.process_handles.lock().unwrap().is_empty());
+
+            let (status, body) = call(
+                &data,
+                "GetQueryResults",
+                json!({ "QueryExecutionId": query_execution_id }),
+            );
+            assert_eq!(status, StatusCode::OK, "{:}", body);
+            assert_eq!(body["ResultSet"]["Rows"].as_array().unwrap().len(), 2);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn the_outcome_directive_picks_the_final_state_and_rejects_an_unknown_one() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .process_interval(Duration::from_secs(1))
+                .csv_fixture_dir(fixture_dir(&[("users.csv", "id\n1\n")])),
+        );
+        let failed = start(&data, "/* minerva:outcome=FAILED */ SELECT * FROM users");
+        let cancelled = start(&data, "SELECT * FROM users\n-- minerva:outcome=CANCELLED");
+        let succeeded = start(&data, "SELECT * FROM users -- minerva:outcome=succeeded");
+        for query_execution_id in [&failed, &cancelled, &succeeded] {
+            assert_eq!(state(&data, query_execution_id), "QUEUED");
+        }
+        advance(&data, Duration::from_secs(1)).await;
+        for query_execution_id in [&failed, &cancelled, &succeeded] {
+            assert_eq!(state(&data, query_execution_id), "RUNNING");
+        }
+        advance(&data, Duration::from_secs(1)).await;
+        let status = &query_execution(&data, &failed)["Status"];
+        assert_eq!(status["State"], "FAILED");
+        assert_eq!(
+            status["StateChangeReason"],
+            "query is flagged to fail by 'minerva:outcome'"
+        );
+        assert_eq!(state(&data, &cancelled), "CANCELLED");
+        assert_eq!(state(&data, &succeeded), "SUCCEEDED");
+
+        for (query_string, outcome) in [
+            ("SELECT * FROM users -- minerva:outcome=DONE", "DONE"),
+            (
+                "/* minerva:outcome=running */ SELECT * FROM users",
+                "RUNNING",
+            ),
+        ] {
+            let (status, body) = call(
+                &data,
+                "StartQueryExecution",
+                json!({ "QueryString": query_string }),
+            );
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+            assert_eq!(
+                body["Message"],
+                format!("unsupported outcome: {:}", outcome)
+            );
+        }
+        let (_, body) = call(&data, "ListQueryExecutions", json!({}));
+        assert_eq!(body["QueryExecutionIds"].as_array().unwrap().len(), 3);
+    }
+
+    #[actix_rt::test]
+    async fn a_query_is_queued_once_started_and_transitions_from_there() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .process_interval(Duration::from_secs(1)),
+        );
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        assert_eq!(state(&data, &query_execution_id), "QUEUED");
+        settle().await;
+        assert_eq!(state(&data, &query_execution_id), "QUEUED");
+        advance(&data, Duration::from_secs(1)).await;
+        assert_eq!(state(&data, &query_execution_id), "RUNNING");
+    }
+
+    #[actix_rt::test]
+    async fn a_query_submitted_before_the_ttl_is_evicted() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .immediate(true),
+        );
+        let expired = start(&data, "SELECT * FROM users");
+        data.clock.advance(Duration::from_secs(30)).unwrap();
+        let retained = start(&data, "SELECT * FROM users");
+        data.clock.advance(Duration::from_secs(31)).unwrap();
+
+        let handle = evict_queries(
+            Some(Duration::from_secs(60)),
+            None,
+            None,
+            data.clock.clone(),
+            data.processes.clone(),
+            data.client_request_tokens.clone(),
+        );
+        settle().await;
+        handle.abort();
+        let (status, body) = call(
+            &data,
+            "GetQueryExecution",
+            json!({ "QueryExecutionId": expired }),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+        assert_eq!(state(&data, &retained), "SUCCEEDED");
+    }
+
+    #[actix_rt::test]
+    async fn a_long_query_is_retained_for_the_whole_retention_once_finished() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .queued_duration(Duration::from_secs(0))
+                .running_duration(Duration::from_secs(120)),
+        );
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        let sweep = || async {
+            let handle = evict_queries(
+                None,
+                Some(Duration::from_secs(60)),
+                None,
+                data.clock.clone(),
+                data.processes.clone(),
+                data.client_request_tokens.clone(),
+            );
+            settle().await;
+            handle.abort();
+        };
+        advance(&data, Duration::from_secs(120)).await;
+        assert_eq!(state(&data, &query_execution_id), "SUCCEEDED");
+        sweep().await;
+        assert_eq!(state(&data, &query_execution_id), "SUCCEEDED");
+        advance(&data, Duration::from_secs(60)).await;
+        sweep().await;
+        assert_eq!(state(&data, &query_execution_id), "SUCCEEDED");
+
+        advance(&data, Duration::from_millis(1)).await;
+        sweep().await;
+        let (status, body) = call(
+            &data,
+            "GetQueryExecution",
+            json!({ "QueryExecutionId": query_execution_id }),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+    }
+
+    #[actix_rt::test]
+    async fn the_client_request_token_of_an_evicted_query_is_released() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .immediate(true)
+                .max_tracked_queries(2),
+        );
+        let start_with_token = |client_request_token: &str| {
+            let (status, body) = call(
+                &data,
+                "StartQueryExecution",
+                json!({ "QueryString": "SELECT * FROM users", "ClientRequestToken": client_request_token }),
+            );
+            assert_eq!(status, StatusCode::OK, "{:}", body);
+            body["QueryExecutionId"].as_str().unwrap().to_string()
+        };
+        let tokens = |data: &crate::model::AppData| {
+            let mut tokens = data
+                .client_request_tokens
+                .lock()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>();
+            tokens.sort();
+            tokens
+        };
+
+        // Note: by QUERY_TTL_SECS.
+        let expired = start_with_token("token-1");
+        data.clock.advance(Duration::from_secs(61)).unwrap();
+        let handle = evict_queries(
+            Some(Duration::from_secs(60)),
+            None,
+            None,
+            data.clock.clone(),
+            data.processes.clone(),
+            data.client_request_tokens.clone(),
+        );
+        settle().await;
+        handle.abort();
+        assert!(tokens(&data).is_empty());
+        let restarted = start_with_token("token-1");
+        assert_ne!(restarted, expired);
+        assert_eq!(state(&data, &restarted), "SUCCEEDED");
+
+        // Note: by MAX_TRACKED_QUERIES, which evicts the oldest, `restarted`, first.
+        for client_request_token in ["token-2", "token-3"] {
+            data.clock.advance(Duration::from_secs(1)).unwrap();
+            start_with_token(client_request_token);
+        }
+        assert_eq!(tokens(&data), ["token-2", "token-3"]);
+        let restarted_again = start_with_token("token-1");
+        assert_ne!(restarted_again, restarted);
+        assert_eq!(state(&data, &restarted_again), "SUCCEEDED");
+    }
+
+    #[test]
+    fn a_client_request_token_reused_with_another_query_is_a_mismatch() {
+        let data = app_data(crate::MinervaServer::builder().immediate(true));
+        let start_with_token = |query_string: &str| {
+            call(
+                &data,
+                "StartQueryExecution",
+                json!({ "QueryString": query_string, "ClientRequestToken": "token-1" }),
+            )
+        };
+        let (status, first) = start_with_token("SELECT * FROM users");
+        assert_eq!(status, StatusCode::OK);
+        let (status, second) = start_with_token("SELECT * FROM users");
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(second["QueryExecutionId"], first["QueryExecutionId"]);
+
+        let (status, body) = start_with_token("SELECT * FROM orders");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["__type"], IDEMPOTENT_PARAMETER_MISMATCH_EXCEPTION);
+        assert_eq!(data.processes.read().unwrap().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn a_start_over_the_concurrent_queries_limit_is_rejected_until_one_finishes() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .queued_duration(Duration::from_secs(0))
+                .running_duration(Duration::from_secs(60))
+                .max_concurrent_queries(2),
+        );
+        start(&data, "SELECT * FROM users");
+        start(&data, "SELECT * FROM users");
+        let body = json!({ "QueryString": "SELECT * FROM users" });
+        let (status, rejected) = call(&data, "StartQueryExecution", body.clone());
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(rejected["__type"], TOO_MANY_REQUESTS_EXCEPTION);
+        assert_eq!(data.processes.read().unwrap().len(), 2);
+
+        advance(&data, Duration::from_secs(60)).await;
+        let (status, _) = call(&data, "StartQueryExecution", body);
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn the_task_of_a_stopped_or_removed_query_ends_without_changing_it() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .process_interval(Duration::from_secs(1)),
+        );
+        let stopped = start(&data, "SELECT * FROM users");
+        let removed = start(&data, "SELECT * FROM users");
+        settle().await;
+        assert_eq!(data.process_handles.lock().unwrap().len(), 2);
+
+        let (status, _) = call(
+            &data,
+            "StopQueryExecution",
+            json!({ "QueryExecutionId": stopped }),
+        );
+        assert_eq!(status, StatusCode::OK);
+        data.processes.write().unwrap().remove(&removed);
+        for _ in 0..5 {
+            advance(&data, Duration::from_secs(1)).await;
+            assert_eq!(state(&data, &stopped), "CANCELLED");
+            assert!(!data.processes.read().unwrap().contains_key(&removed));
+        }
+        assert!(data.process_handles.lock().unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn a_stopped_query_is_never_succeeded_by_its_transition() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .queued_duration(Duration::from_secs(0))
+                .running_duration(Duration::from_secs(1)),
+        );
+        let stop = |data: &crate::model::AppData, query_execution_id: &str| {
+            let (status, _) = call(
+                data,
+                "StopQueryExecution",
+                json!({ "QueryExecutionId": query_execution_id }),
+            );
+            assert_eq!(status, StatusCode::OK);
+            state(data, query_execution_id)
+        };
+
+        // Note: every transition to SUCCEEDED is due, and runs after the stops.
+        let query_execution_ids = (0..300)
+            .map(|_| start(&data, "SELECT * FROM users"))
+            .collect::<Vec<_>>();
+        settle().await;
+        data.clock.advance(Duration::from_secs(1)).unwrap();
+        for query_execution_id in &query_execution_ids {
+            assert_eq!(stop(&data, query_execution_id), "CANCELLED");
+        }
+        settle().await;
+        for query_execution_id in &query_execution_ids {
+            assert_eq!(state(&data, query_execution_id), "CANCELLED");
+        }
+
+        // Note: the stops race the transitions from another thread, a query either succeeds
+        // before its stop or stays CANCELLED.
+        let query_execution_ids = (0..300)
+            .map(|_| start(&data, "SELECT * FROM users"))
+            .collect::<Vec<_>>();
+        settle().await;
+        data.clock.advance(Duration::from_secs(1)).unwrap();
+        let stopper = {
+            let (data, query_execution_ids) = (data.clone(), query_execution_ids.clone());
+            std::thread::spawn(move || {
+                query_execution_ids
+                    .iter()
+                    .map(|v| stop(&data, v))
+                    .collect::<Vec<_>>()
+            })
+        };
+        while !stopper.is_finished() {
+            time::delay_for(Duration::from_millis(1)).await;
+        }
+        let stopped_states = stopper.join().unwrap();
+        settle().await;
+        for (query_execution_id, stopped_state) in query_execution_ids.iter().zip(stopped_states) {
+            assert_eq!(state(&data, query_execution_id), stopped_state);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn a_reused_client_request_token_starts_no_second_execution() {
+        let data = app_data(crate::MinervaServer::builder().virtual_clock(true));
+        let body = json!({ "QueryString": "SELECT * FROM users", "ClientRequestToken": "token-1" });
+        let (_, first) = call(&data, "StartQueryExecution", body.clone());
+        let (_, second) = call(&data, "StartQueryExecution", body);
+        assert_eq!(first["QueryExecutionId"], second["QueryExecutionId"]);
+        assert_eq!(data.processes.read().unwrap().len(), 1);
+        assert_eq!(data.process_handles.lock().unwrap().len(), 1);
+
+        let (_, other) = call(
+            &data,
+            "StartQueryExecution",
+            json!({ "QueryString": "SELECT * FROM users", "ClientRequestToken": "token-2" }),
+        );
+        assert_ne!(other["QueryExecutionId"], first["QueryExecutionId"]);
+        assert_ne!(
+            start(&data, "SELECT * FROM users"),
+            start(&data, "SELECT * FROM users")
+        );
+    }
+
+    #[test]
+    fn a_query_with_a_syntax_error_is_failed_unless_sql_is_not_validated() {
+        let csv_fixture_dir = fixture_dir(&[("users.csv", "id\n1\n")]);
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .immediate(true)
+                .csv_fixture_dir(csv_fixture_dir.clone()),
+        );
+        let failed = start(&data, "SELECT *\nFROM users\nWHERE id = = 1");
+        let status = &query_execution(&data, &failed)["Status"];
+        assert_eq!(status["State"], "FAILED");
+        assert_eq!(
+            status["StateChangeReason"],
+            "line 3:12: Expected an expression:, found: ="
+        );
+        // Note: Hive DDL the parser doesn't know.
+        let ddl_query =
+            "CREATE DATABASE IF NOT EXISTS sales COMMENT 'sales' LOCATION 's3://foo/sales/'";
+        assert!(crate::syntax::parse(ddl_query).is_err());
+        let ddl = start(&data, ddl_query);
+        assert_eq!(state(&data, &ddl), "SUCCEEDED");
+
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .immediate(true)
+                .validate_sql(false)
+                .csv_fixture_dir(csv_fixture_dir),
+        );
+        let unvalidated = start(&data, "SELECT *\nFROM users\nWHERE id = = 1");
+        assert_eq!(state(&data, &unvalidated), "SUCCEEDED");
+    }
+
+    #[test]
+    fn get_query_results_resumes_at_its_next_token_and_rejects_another() {
+        let csv_fixture_dir = fixture_dir(&[("users.csv", "id\n1\n2\n3\n4\n")]);
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .immediate(true)
+                .csv_fixture_dir(csv_fixture_dir),
+        );
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        let first_row = |body: &serde_json::Value| body["ResultSet"]["Rows"][0]["Data"][0].clone();
+
+        let (status, body) = call(
+            &data,
+            "GetQueryResults",
+            json!({ "QueryExecutionId": query_execution_id, "MaxResults": 3 }),
+        );
+        assert_eq!(status, StatusCode::OK);
+        let (_, next) = call(
+            &data,
+            "GetQueryResults",
+            json!({ "QueryExecutionId": query_execution_id, "NextToken": body["NextToken"] }),
+        );
+        assert_eq!(first_row(&next), json!({ "VarCharValue": "3" }));
+        assert!(next.get("NextToken").is_none());
+
+        let other = start(&data, "SELECT * FROM users");
+        for next_token in [json!("garbage"), body["NextToken"].clone()] {
+            let (status, body) = call(
+                &data,
+                "GetQueryResults",
+                json!({ "QueryExecutionId": other, "NextToken": next_token }),
+            );
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{:}", body);
+            assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+        }
+    }
+
+    #[test]
+    fn get_query_results_has_the_column_types_on_every_page() {
+        let csv_fixture_dir = fixture_dir(&[
+            ("users.csv", "id,score,name\n1,1,foo\n2,2.5,\n"),
+            ("users.csv.schema.json", r#"{"name": "char(3)"}"#),
+        ]);
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .immediate(true)
+                .csv_fixture_dir(csv_fixture_dir),
+        );
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        let mut next_token = None;
+        for _ in 0..2 {
+            let (_, body) = call(
+                &data,
+                "GetQueryResults",
+                json!({ "QueryExecutionId": query_execution_id, "MaxResults": 2, "NextToken": next_token }),
+            );
+            let column_types = body["ResultSet"]["ResultSetMetadata"]["ColumnInfo"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["Type"].as_str().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(column_types, ["bigint", "double", "char(3)"]);
+            next_token = body.get("NextToken").cloned();
+        }
+        assert_eq!(next_token, None);
+    }
+
+    #[test]
+    fn the_header_row_is_the_first_row_of_the_first_page_only() {
+        let csv_fixture_dir = fixture_dir(&[("users.csv", "id\n1\n2\n3\n")]);
+        let values = |body: &serde_json::Value| {
+            body["ResultSet"]["Rows"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["Data"][0]["VarCharValue"].clone())
+                .collect::<serde_json::Value>()
+        };
+        let pages = |include_header_row: bool| {
+            let data = app_data(
+                crate::MinervaServer::builder()
+                    .immediate(true)
+                    .include_header_row(include_header_row)
+                    .csv_fixture_dir(csv_fixture_dir.clone()),
+            );
+            let query_execution_id = start(&data, "SELECT * FROM users");
+            let (_, first) = call(
+                &data,
+                "GetQueryResults",
+                json!({ "QueryExecutionId": query_execution_id, "MaxResults": 2 }),
+            );
+            let (_, second) = call(
+                &data,
+                "GetQueryResults",
+                json!({ "QueryExecutionId": query_execution_id, "NextToken": first["NextToken"] }),
+            );
+            (values(&first), values(&second))
+        };
+        assert_eq!(pages(true), (json!(["id", "1"]), json!(["2", "3"])));
+        assert_eq!(pages(false), (json!(["1", "2"]), json!(["3"])));
+
+        let data = app_data(crate::MinervaServer::builder().immediate(true));
+        let query_execution_id = start(&data, "SHOW DATABASES");
+        let (_, body) = call(
+            &data,
+            "GetQueryResults",
+            json!({ "QueryExecutionId": query_execution_id }),
+        );
+        assert_eq!(values(&body), json!(["default"]));
+    }
+
+    #[actix_rt::test]
+    async fn ddl_succeeds_without_running_with_an_empty_result() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .process_interval(Duration::from_secs(1)),
+        );
+        let query_execution_id = start(
+            &data,
+            "-- migration\nCREATE EXTERNAL TABLE users (id bigint) LOCATION 's3://bucket/users/'",
+        );
+        advance(&data, Duration::from_secs(1)).await;
+        let query_execution = query_execution(&data, &query_execution_id);
+        assert_eq!(query_execution["Status"]["State"], "SUCCEEDED");
+        assert_eq!(query_execution["StatementType"], "DDL");
+
+        let (_, body) = call(
+            &data,
+            "GetQueryResults",
+            json!({ "QueryExecutionId": query_execution_id }),
+        );
+        assert_eq!(
+            body["ResultSet"],
+            json!({ "Rows": [], "ResultSetMetadata": { "ColumnInfo": [] } })
+        );
+    }
+
+    #[test]
+    fn a_generated_result_is_paged_and_resumed_from_its_next_token() {
+        let data = app_data(crate::MinervaServer::builder().immediate(true));
+        let query_execution_id = start(
+            &data,
+            "SELECT * FROM big -- minerva:generate rows=2500 cols=3",
+        );
+        assert_eq!(
+            query_execution(&data, &query_execution_id)["Statistics"]["DataScannedInBytes"],
+            crate::generate::byte_count(2500, 3)
+        );
+        let page = |next_token: &serde_json::Value| {
+            let (status, body) = call(
+                &data,
+                "GetQueryResults",
+                json!({ "QueryExecutionId": query_execution_id, "MaxResults": 1000, "NextToken": next_token }),
+            );
+            assert_eq!(status, StatusCode::OK, "{:}", body);
+            let rows = body["ResultSet"]["Rows"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| {
+                    v["Data"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|v| v["VarCharValue"].as_str().unwrap().to_string())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let column_info = body["ResultSet"]["ResultSetMetadata"]["ColumnInfo"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| (v["Name"].clone(), v["Type"].clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                column_info,
+                [
+                    (json!("c0"), json!("bigint")),
+                    (json!("c1"), json!("varchar")),
+                    (json!("c2"), json!("bigint")),
+                ]
+            );
+            (rows, body.get("NextToken").cloned().unwrap_or_default())
+        };
+
+        let (first, next_token) = page(&serde_json::Value::Null);
+        assert_eq!(first.len(), 1000);
+        assert_eq!(first[0], ["c0", "c1", "c2"]);
+        assert_eq!(first[1], ["0", "row-0-c1", "0"]);
+        let (second, last_token) = page(&next_token);
+        assert_eq!(second.len(), 1000);
+        assert_eq!(second[0], ["999", "row-999-c1", "2997"]);
+        assert_eq!(page(&next_token), (second, last_token.clone()));
+
+        let (third, end) = page(&last_token);
+        assert_eq!(third.len(), 501);
+        assert_eq!(third[0], ["1999", "row-1999-c1", "5997"]);
+        assert_eq!(third[500], ["2499", "row-2499-c1", "7497"]);
+        assert_eq!(end, serde_json::Value::Null);
+    }
+
+    // Note: the users of the csv and json fixtures below, typed by the parquet schema.
+    #[cfg(feature = "parquet")]
+    fn write_parquet_fixture(path: &str) {
+        use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+
+        let schema = parse_message_type(
+            "message users { REQUIRED INT64 id; OPTIONAL BYTE_ARRAY name (UTF8); }",
+        )
+        .unwrap();
+        let mut writer = SerializedFileWriter::new(
+            File::create(path).unwrap(),
+            Arc::new(schema),
+            Arc::new(WriterProperties::builder().build()),
+        )
+        .unwrap();
+        let mut row_group = writer.next_row_group().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column
+            .typed::<Int64Type>()
+            .write_batch(&[1, 2], None, None)
+            .unwrap();
+        column.close().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&[ByteArray::from("foo")], Some(&[1, 0]), None)
+            .unwrap();
+        column.close().unwrap();
+        row_group.close().unwrap();
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn csv_json_and_parquet_fixtures_have_the_same_result() {
+        let csv_fixture_dirs = vec![
+            fixture_dir(&[("users.csv", "id,name\n1,foo\n2,\n")]),
+            fixture_dir(&[(
+                "users.json",
+                r#"[{"id": 1, "name": "foo"}, {"id": 2, "name": null}]"#,
+            )]),
+            #[cfg(feature = "parquet")]
+            {
+                let dir = fixture_dir(&[]);
+                write_parquet_fixture(&format!("{:}/users.parquet", dir));
+                dir
+            },
+        ];
+        let result_sets = csv_fixture_dirs
+            .into_iter()
+            .map(|csv_fixture_dir| {
+                let data = app_data(
+                    crate::MinervaServer::builder()
+                        .immediate(true)
+                        .csv_fixture_dir(csv_fixture_dir),
+                );
+                let query_execution_id = start(&data, "SELECT * FROM users");
+                let (status, body) = call(
+                    &data,
+                    "GetQueryResults",
+                    json!({ "QueryExecutionId": query_execution_id }),
+                );
+                assert_eq!(status, StatusCode::OK, "{:}", body);
+                body["ResultSet"].clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            result_sets[0]["Rows"],
+            json!([
+                { "Data": [{ "VarCharValue": "id" }, { "VarCharValue": "name" }] },
+                { "Data": [{ "VarCharValue": "1" }, { "VarCharValue": "foo" }] },
+                { "Data": [{ "VarCharValue": "2" }, {}] },
+            ])
+        );
+        for result_set in &result_sets[1..] {
+            assert_eq!(result_set, &result_sets[0]);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn get_query_results_of_a_query_which_has_not_succeeded_is_an_invalid_request() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .process_interval(Duration::from_secs(1)),
+        );
+        let results_error = |query_execution_id: &str| {
+            let (status, body) = call(
+                &data,
+                "GetQueryResults",
+                json!({ "QueryExecutionId": query_execution_id }),
+            );
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+            body["Message"].as_str().unwrap().to_string()
+        };
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        let failed = start(&data, "SELECT * FROM users -- minerva:fail");
+        assert_eq!(
+            results_error(&query_execution_id),
+            "Query has not yet finished. Current state: QUEUED"
+        );
+        advance(&data, Duration::from_secs(1)).await;
+        assert_eq!(
+            results_error(&query_execution_id),
+            "Query has not yet finished. Current state: RUNNING"
+        );
+        advance(&data, Duration::from_secs(1)).await;
+        assert_eq!(
+            results_error(&failed),
+            "Query did not finish successfully. Final query state: FAILED: query is flagged to fail by '-- minerva:fail'"
+        );
+        assert_eq!(results_error("foo"), "QueryExecution foo was not found");
+    }
+
+    #[test]
+    fn a_deleted_named_query_is_unprocessed_by_batch_get_named_query() {
+        let data = app_data(crate::MinervaServer::builder());
+        let kept = create_named_query(&data, "kept");
+        let deleted = create_named_query(&data, "deleted");
+        let (status, _) = call(
+            &data,
+            "DeleteNamedQuery",
+            json!({ "NamedQueryId": deleted }),
+        );
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = call(&data, "GetNamedQuery", json!({ "NamedQueryId": deleted }));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+
+        let (status, body) = call(
+            &data,
+            "BatchGetNamedQuery",
+            json!({ "NamedQueryIds": [kept, deleted] }),
+        );
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["NamedQueries"][0]["NamedQueryId"], kept.as_str());
+        assert_eq!(body["NamedQueries"][0]["Name"], "kept");
+        assert_eq!(body["NamedQueries"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            body["UnprocessedNamedQueryIds"][0]["NamedQueryId"],
+            deleted.as_str()
+        );
+
+        let (status, body) = call(&data, "BatchGetNamedQuery", json!({ "NamedQueryIds": [] }));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+    }
+
+    #[test]
+    fn get_query_results_pages_have_at_most_1000_rows_with_the_header_row() {
+        let rows = (0..1500).map(|v| format!("{:}\n", v)).collect::<String>();
+        let csv_fixture_dir = fixture_dir(&[("users.csv", &format!("id\n{:}", rows))]);
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .immediate(true)
+                .csv_fixture_dir(csv_fixture_dir),
+        );
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        let rows = |body: &serde_json::Value| body["ResultSet"]["Rows"].as_array().unwrap().clone();
+
+        let (_, first) = call(
+            &data,
+            "GetQueryResults",
+            json!({ "QueryExecutionId": query_execution_id }),
+        );
+        let first_rows = rows(&first);
+        assert_eq!(first_rows.len(), 1000);
+        assert_eq!(first_rows[0]["Data"][0]["VarCharValue"], "id");
+        assert_eq!(first_rows[999]["Data"][0]["VarCharValue"], "998");
+        let (_, second) = call(
+            &data,
+            "GetQueryResults",
+            json!({ "QueryExecutionId": query_execution_id, "MaxResults": 1000, "NextToken": first["NextToken"] }),
+        );
+        let second_rows = rows(&second);
+        assert_eq!(second_rows.len(), 501);
+        assert_eq!(second_rows[0]["Data"][0]["VarCharValue"], "999");
+
+        for max_results in [0, 1001, 5000] {
+            let (status, body) = call(
+                &data,
+                "GetQueryResults",
+                json!({ "QueryExecutionId": query_execution_id, "MaxResults": max_results }),
+            );
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+            assert!(body["Message"]
+                .as_str()
+                .unwrap()
+                .starts_with("1 validation error detected"));
+        }
+    }
+
+    #[test]
+    fn work_groups_are_created_listed_updated_and_deleted() {
+        let data = app_data(crate::MinervaServer::builder());
+        for name in ["etl", "adhoc"] {
+            let (status, _) = call(&data, "CreateWorkGroup", json!({ "Name": name }));
+            assert_eq!(status, StatusCode::OK);
+        }
+        for name in ["etl", "no spaces"] {
+            let (status, body) = call(&data, "CreateWorkGroup", json!({ "Name": name }));
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+        }
+
+        let (_, first) = call(&data, "ListWorkGroups", json!({ "MaxResults": 2 }));
+        let (_, second) = call(
+            &data,
+            "ListWorkGroups",
+            json!({ "MaxResults": 2, "NextToken": first["NextToken"] }),
+        );
+        let names = |body: &serde_json::Value| {
+            body["WorkGroups"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["Name"].clone())
+                .collect::<serde_json::Value>()
+        };
+        assert_eq!(names(&first), json!(["adhoc", "etl"]));
+        assert_eq!(names(&second), json!(["primary"]));
+        assert!(second.get("NextToken").is_none());
+
+        let (status, _) = call(
+            &data,
+            "UpdateWorkGroup",
+            json!({ "WorkGroup": "etl", "Description": "nightly", "State": "DISABLED" }),
+        );
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = call(&data, "GetWorkGroup", json!({ "WorkGroup": "etl" }));
+        assert_eq!(body["WorkGroup"]["Description"], "nightly");
+        assert_eq!(body["WorkGroup"]["State"], "DISABLED");
+
+        let (status, _) = call(&data, "DeleteWorkGroup", json!({ "WorkGroup": "primary" }));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = call(&data, "DeleteWorkGroup", json!({ "WorkGroup": "etl" }));
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = call(&data, "GetWorkGroup", json!({ "WorkGroup": "etl" }));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["Message"], "WorkGroup etl is not found");
+    }
+
+    #[actix_rt::test]
+    async fn the_result_of_a_succeeded_query_is_written_under_the_output_dir() {
+        let csv_fixture_dir = fixture_dir(&[("users.csv", "id,name\n1,\"say \"\"hi\"\"\"\n2,\n")]);
+        let output_dir = fixture_dir(&[]);
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .process_interval(Duration::from_secs(1))
+                .csv_fixture_dir(csv_fixture_dir)
+                .output_dir(output_dir.clone()),
+        );
+        let succeeded = start(&data, "SELECT * FROM users");
+        let stopped = start(&data, "SELECT * FROM users");
+        let output_path = |query_execution_id: &str| {
+            let output_location = query_execution(&data, query_execution_id)["ResultConfiguration"]
+                ["OutputLocation"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            assert_eq!(
+                output_location,
+                format!("s3://minerva-results/{:}.csv", query_execution_id)
+            );
+            crate::output::output_path(&output_dir, &output_location)
+        };
+        advance(&data, Duration::from_secs(1)).await;
+        assert!(!output_path(&succeeded).exists());
+        call(
+            &data,
+            "StopQueryExecution",
+            json!({ "QueryExecutionId": stopped }),
+        );
+        advance(&data, Duration::from_secs(1)).await;
+
+        assert_eq!(
+            std::fs::read_to_string(output_path(&succeeded)).unwrap(),
+            "\"id\",\"name\"\n\"1\",\"say \"\"hi\"\"\"\n\"2\",\n"
+        );
+        let metadata: serde_json::Value = serde_json::from_slice(
+            &std::fs::read(format!("{:}.metadata", output_path(&succeeded).display())).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(metadata["ColumnInfo"][0]["Name"], "id");
+        assert!(!output_path(&stopped).exists());
+    }
+
+    #[test]
+    fn get_query_results_stream_is_rejected_with_a_pointer_to_get_query_results() {
+        let data = app_data(crate::MinervaServer::builder().immediate(true));
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        let (status, content_type, body) = dispatch(
+            &data,
+            "AmazonAthenaStreamingService.GetQueryResultsStream",
+            json!({ "QueryExecutionId": query_execution_id }),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(content_type, "application/x-amz-json-1.1");
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["__type"], crate::dispatch::UNKNOWN_OPERATION_EXCEPTION);
+        assert_eq!(
+            body["Message"],
+            "GetQueryResultsStream is not supported, set UseResultsetStreaming=0 on the JDBC/ODBC driver to fetch the results with GetQueryResults"
+        );
+    }
+
+    #[test]
+    fn get_query_execution_echoes_the_result_configuration() {
+        let data = app_data(crate::MinervaServer::builder().immediate(true));
+        for encryption_configuration in [
+            json!({ "EncryptionOption": "SSE_S3" }),
+            json!({ "EncryptionOption": "SSE_KMS", "KmsKey": "arn:aws:kms:us-east-1:123456789012:key/foo" }),
+            json!({ "EncryptionOption": "CSE_KMS", "KmsKey": "arn:aws:kms:us-east-1:123456789012:key/bar" }),
+        ] {
+            let result_configuration = json!({
+                "OutputLocation": "s3://foo/bar/",
+                "EncryptionConfiguration": encryption_configuration,
+                "ExpectedBucketOwner": "123456789012",
+            });
+            let (status, body) = call(
+                &data,
+                "StartQueryExecution",
+                json!({ "QueryString": "SELECT * FROM users", "ResultConfiguration": result_configuration }),
+            );
+            assert_eq!(status, StatusCode::OK, "{:}", body);
+            let query_execution =
+                query_execution(&data, body["QueryExecutionId"].as_str().unwrap());
+            assert_eq!(
+                query_execution["ResultConfiguration"]["EncryptionConfiguration"],
+                result_configuration["EncryptionConfiguration"]
+            );
+            assert_eq!(
+                query_execution["ResultConfiguration"]["ExpectedBucketOwner"],
+                "123456789012"
+            );
+        }
+
+        let (status, body) = call(
+            &data,
+            "StartQueryExecution",
+            json!({
+                "QueryString": "SELECT * FROM users",
+                "ResultConfiguration": { "EncryptionConfiguration": { "EncryptionOption": "SSE_KMS" } },
+            }),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["Message"], "KmsKey is required for SSE_KMS");
+    }
+
+    #[test]
+    fn get_query_execution_reports_the_default_encryption_configuration() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .immediate(true)
+                .result_encryption_option("SSE_KMS")
+                .result_kms_key("arn:aws:kms:us-east-1:123456789012:key/foo"),
+        );
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        assert_eq!(
+            query_execution(&data, &query_execution_id)["ResultConfiguration"]
+                ["EncryptionConfiguration"],
+            json!({ "EncryptionOption": "SSE_KMS", "KmsKey": "arn:aws:kms:us-east-1:123456789012:key/foo" })
+        );
+    }
+
+    #[test]
+    fn show_tables_and_describe_are_answered_from_the_databases_and_the_fixtures() {
+        let databases_dir = fixture_dir(&[(
+            "databases.json",
+            r#"[{"Name": "sales", "Tables": [{
+                    "Name": "orders",
+                    "Columns": [
+                        {"Name": "id", "Type": "bigint", "Comment": "order id"},
+                        {"Name": "amount", "Type": "double"}
+                    ],
+                    "PartitionKeys": [{"Name": "dt", "Type": "string", "Comment": "day"}]
+                }]}]"#,
+        )]);
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .immediate(true)
+                .csv_fixture_dir(fixture_dir(&[("users.csv", "id\n1\n")]))
+                .databases_file(format!("{:}/databases.json", databases_dir)),
+        );
+        let result = |query_string: &str| {
+            let query_execution_id = start(&data, query_string);
+            assert_eq!(
+                state(&data, &query_execution_id),
+                "SUCCEEDED",
+                "{:}",
+                query_string
+            );
+            let (_, body) = call(
+                &data,
+                "GetQueryResults",
+                json!({ "QueryExecutionId": query_execution_id }),
+            );
+            let column_names = body["ResultSet"]["ResultSetMetadata"]["ColumnInfo"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["Name"].clone())
+                .collect::<serde_json::Value>();
+            let values = body["ResultSet"]["Rows"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["Data"][0]["VarCharValue"].clone())
+                .collect::<serde_json::Value>();
+            (column_names, values)
+        };
+
+        assert_eq!(
+            result("SHOW TABLES IN sales"),
+            (json!(["tab_name"]), json!(["orders", "users"]))
+        );
+        assert_eq!(
+            result("SHOW TABLES IN sales 'ord*|accounts'"),
+            (json!(["tab_name"]), json!(["orders"]))
+        );
+        assert_eq!(
+            result("SHOW TABLES"),
+            (json!(["tab_name"]), json!(["users"]))
+        );
+        assert_eq!(
+            result("DESCRIBE sales.orders"),
+            (
+                json!(["col_name"]),
+                json!([
+                    "id                  \tbigint              \torder id            ",
+                    "amount              \tdouble              \t                    ",
+                    "dt                  \tstring              \tday                 ",
+                    "",
+                    "# Partition Information",
+                    "# col_name          \tdata_type           \tcomment             ",
+                    "",
+                    "dt                  \tstring              \tday                 ",
+                ])
+            )
+        );
+        assert_eq!(
+            result("DESC users"),
+            (
+                json!(["col_name"]),
+                json!(["id                  \tbigint              \t                    "])
+            )
+        );
+
+        for (query_string, reason) in [
+            (
+                "SHOW TABLES IN nowhere",
+                "FAILED: SemanticException [Error 10072]: Database does not exist: nowhere",
+            ),
+            (
+                "DESCRIBE nowhere.orders",
+                "FAILED: SemanticException [Error 10072]: Database does not exist: nowhere",
+            ),
+            (
+                "DESCRIBE sales.missing",
+                "FAILED: SemanticException [Error 10001]: Table not found sales.missing",
+            ),
+        ] {
+            let query_execution_id = start(&data, query_string);
+            let query_execution = query_execution(&data, &query_execution_id);
+            assert_eq!(
+                query_execution["Status"]["State"], "FAILED",
+                "{:}",
+                query_string
+            );
+            assert_eq!(
+                query_execution["Status"]["StateChangeReason"], reason,
+                "{:}",
+                query_string
+            );
+        }
+    }
+
+    #[test]
+    fn explain_returns_a_plan_without_consulting_the_result_fixtures() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .immediate(true)
+                .csv_fixture_dir(fixture_dir(&[("users.csv", "id\n1\n")]))
+                .fixtures_dir(fixture_dir(&[(
+                    "users.json",
+                    r#"{"pattern": "FROM users", "columns": [{"name": "fixture"}], "rows": [["fixture"]]}"#,
+                )])),
+        );
+        let result = |query_string: &str| {
+            let query_execution_id = start(&data, query_string);
+            let query_execution = query_execution(&data, &query_execution_id);
+            assert_eq!(query_execution["Status"]["State"], "SUCCEEDED");
+            let (_, body) = call(
+                &data,
+                "GetQueryResults",
+                json!({ "QueryExecutionId": query_execution_id }),
+            );
+            let column_names = body["ResultSet"]["ResultSetMetadata"]["ColumnInfo"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["Name"].clone())
+                .collect::<serde_json::Value>();
+            let values = body["ResultSet"]["Rows"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["Data"][0]["VarCharValue"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>();
+            (
+                query_execution["StatementType"].clone(),
+                column_names,
+                values,
+            )
+        };
+        let plan = [
+            "Fragment 0 [SINGLE]",
+            "    Output layout: [*]",
+            "    Output partitioning: SINGLE []",
+            "    Output[columnNames = [*]]",
+            "    └─ RemoteSource[sourceFragmentIds = [1]]",
+            "",
+            "Fragment 1 [SOURCE]",
+            "    Output layout: [*]",
+            "    Output partitioning: SINGLE []",
+            "    TableScan[table = awsdatacatalog:default:users]",
+        ];
+
+        let (_, column_names, values) = result("SELECT * FROM users");
+        assert_eq!(column_names, json!(["fixture"]));
+        assert_eq!(values, ["fixture", "fixture"]);
+
+        for query_string in [
+            "EXPLAIN SELECT * FROM users",
+            "EXPLAIN (FORMAT TEXT) SELECT * FROM users",
+            "explain (type distributed, format text) SELECT * FROM users",
+        ] {
+            assert_eq!(
+                result(query_string),
+                (
+                    json!("UTILITY"),
+                    json!(["Query Plan"]),
+                    plan.map(String::from).to_vec()
+                ),
+                "{:}",
+                query_string
+            );
+        }
+
+        let (statement_type, column_names, values) = result("EXPLAIN ANALYZE SELECT * FROM users");
+        assert_eq!(statement_type, "UTILITY");
+        assert_eq!(column_names, json!(["Query Plan"]));
+        assert!(values[0].starts_with("Queued: "), "{:}", values[0]);
+        assert!(values[0].contains(", Execution: "), "{:}", values[0]);
+        let fragments = values
+            .iter()
+            .filter(|v| v.starts_with("Fragment "))
+            .collect::<Vec<_>>();
+        assert_eq!(fragments, ["Fragment 0 [SINGLE]", "Fragment 1 [SOURCE]"]);
+        assert_eq!(
+            values.iter().filter(|v| v.starts_with("    CPU: ")).count(),
+            fragments.len()
+        );
+        assert_eq!(
+            values.last().unwrap(),
+            "    TableScan[table = awsdatacatalog:default:users]"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn a_query_unfinished_past_the_timeout_is_failed() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .process_interval(Duration::from_secs(1))
+                .query_timeout(Duration::from_secs(10)),
+        );
+        let slow = start(&data, "SELECT * FROM users -- minerva: running=60s");
+        let fast = start(&data, "SELECT * FROM users");
+        advance(&data, Duration::from_millis(9999)).await;
+        assert_eq!(state(&data, &slow), "RUNNING");
+        assert_eq!(state(&data, &fast), "SUCCEEDED");
+        advance(&data, Duration::from_millis(1)).await;
+        let status = &query_execution(&data, &slow)["Status"];
+        assert_eq!(status["State"], "FAILED");
+        assert_eq!(
+            status["StateChangeReason"],
+            "Query exhausted resources at this scale factor"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn the_timeout_message_is_configurable() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .process_interval(Duration::from_secs(60))
+                .query_timeout(Duration::from_secs(10))
+                .query_timeout_message("Query timeout"),
+        );
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        advance(&data, Duration::from_secs(10)).await;
+        let status = &query_execution(&data, &query_execution_id)["Status"];
+        assert_eq!(status["State"], "FAILED");
+        assert_eq!(status["StateChangeReason"], "Query timeout");
+    }
+
+    #[test]
+    fn a_named_query_is_updated_and_deleted() {
+        let data = app_data(crate::MinervaServer::builder());
+        let named_query_id = create_named_query(&data, "users");
+        let (status, body) = call(
+            &data,
+            "UpdateNamedQuery",
+            json!({
+                "NamedQueryId": named_query_id,
+                "Name": "items",
+                "Description": "all the items",
+                "QueryString": "SELECT * FROM items",
+            }),
+        );
+        assert_eq!(status, StatusCode::OK, "{:}", body);
+        let (_, body) = call(
+            &data,
+            "GetNamedQuery",
+            json!({ "NamedQueryId": named_query_id }),
+        );
+        assert_eq!(body["NamedQuery"]["Name"], "items");
+        assert_eq!(body["NamedQuery"]["Description"], "all the items");
+        assert_eq!(body["NamedQuery"]["QueryString"], "SELECT * FROM items");
+        assert_eq!(body["NamedQuery"]["Database"], "default");
+
+        for _ in 0..2 {
+            let (status, body) = call(
+                &data,
+                "DeleteNamedQuery",
+                json!({ "NamedQueryId": named_query_id }),
+            );
+            assert_eq!(status, StatusCode::OK, "{:}", body);
+        }
+        let (_, body) = call(&data, "ListNamedQueries", json!({}));
+        assert_eq!(body["NamedQueryIds"], json!([]));
+
+        let (status, body) = call(
+            &data,
+            "UpdateNamedQuery",
+            json!({ "NamedQueryId": named_query_id, "Name": "items", "QueryString": "SELECT * FROM items" }),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+        assert_eq!(
+            body["Message"],
+            format!("NamedQuery {:} was not found", named_query_id)
+        );
+    }
+
+    #[test]
+    fn batch_get_named_query_reports_each_unknown_id() {
+        let data = app_data(crate::MinervaServer::builder());
+        let users = create_named_query(&data, "users");
+        let items = create_named_query(&data, "items");
+        let (status, body) = call(
+            &data,
+            "BatchGetNamedQuery",
+            json!({ "NamedQueryIds": [users, "foo", items, "bar"] }),
+        );
+        assert_eq!(status, StatusCode::OK, "{:}", body);
+        let names = body["NamedQueries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["Name"].clone())
+            .collect::<serde_json::Value>();
+        assert_eq!(names, json!(["users", "items"]));
+        assert_eq!(
+            body["UnprocessedNamedQueryIds"],
+            json!([
+                {
+                    "NamedQueryId": "foo",
+                    "ErrorCode": "NAMED_QUERY_NOT_FOUND",
+                    "ErrorMessage": "NamedQuery foo was not found",
+                },
+                {
+                    "NamedQueryId": "bar",
+                    "ErrorCode": "NAMED_QUERY_NOT_FOUND",
+                    "ErrorMessage": "NamedQuery bar was not found",
+                },
+            ])
+        );
+
+        let named_query_ids = (0..51).map(|_| users.clone()).collect::<Vec<_>>();
+        let (status, body) = call(
+            &data,
+            "BatchGetNamedQuery",
+            json!({ "NamedQueryIds": named_query_ids[..50] }),
+        );
+        assert_eq!(status, StatusCode::OK, "{:}", body);
+        let (status, body) = call(
+            &data,
+            "BatchGetNamedQuery",
+            json!({ "NamedQueryIds": named_query_ids }),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["__type"], INVALID_REQUEST_EXCEPTION);
+        assert_eq!(
+            body["Message"],
+            "NamedQueryIds must contain between 1 and 50 ids"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn queries_past_max_running_queries_stay_queued_until_a_slot_frees() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .queued_duration(Duration::from_secs(1))
+                .running_duration(Duration::from_secs(10))
+                .max_running_queries(2),
+        );
+        let query_execution_ids = (0..3)
+            .map(|_| start(&data, "SELECT * FROM users"))
+            .collect::<Vec<_>>();
+        let states = |data: &crate::model::AppData| {
+            query_execution_ids
+                .iter()
+                .map(|v| state(data, v))
+                .collect::<Vec<_>>()
+        };
+        advance(&data, Duration::from_secs(1)).await;
+        assert_eq!(states(&data), ["RUNNING", "RUNNING", "QUEUED"]);
+        advance(&data, Duration::from_secs(5)).await;
+        assert_eq!(states(&data), ["RUNNING", "RUNNING", "QUEUED"]);
+
+        advance(&data, Duration::from_secs(5)).await;
+        time::delay_for(RUNNING_SLOT_POLL_INTERVAL * 2).await;
+        assert_eq!(states(&data), ["SUCCEEDED", "SUCCEEDED", "RUNNING"]);
+        advance(&data, Duration::from_secs(10)).await;
+        assert_eq!(states(&data), ["SUCCEEDED", "SUCCEEDED", "SUCCEEDED"]);
+    }
+
+    #[actix_rt::test]
+    async fn an_immediate_query_waits_for_a_running_slot() {
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .virtual_clock(true)
+                .queued_duration(Duration::from_secs(0))
+                .running_duration(Duration::from_secs(0))
+                .max_running_queries(1),
+        );
+        let slow = start(&data, "SELECT * FROM users -- minerva: running=30s");
+        settle().await;
+        assert_eq!(state(&data, &slow), "RUNNING");
+
+        let immediate = start(&data, "SELECT * FROM users");
+        assert_eq!(state(&data, &immediate), "QUEUED");
+        time::delay_for(RUNNING_SLOT_POLL_INTERVAL * 2).await;
+        assert_eq!(state(&data, &immediate), "QUEUED");
+
+        advance(&data, Duration::from_secs(30)).await;
+        time::delay_for(RUNNING_SLOT_POLL_INTERVAL * 2).await;
+        assert_eq!(state(&data, &slow), "SUCCEEDED");
+        assert_eq!(state(&data, &immediate), "SUCCEEDED");
+    }
 }
@@ -0,0 +1,158 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const S3_SCHEME: &str = "s3://";
+pub const METADATA_SUFFIX: &str = ".metadata";
+const TEMP_SUFFIX: &str = ".tmp";
+
+/// Where the object of `s3://bucket/key` lives, `{output_dir}/bucket/key`.
+pub fn output_path(output_dir: &str, output_location: &str) -> PathBuf {
+    let mut path = PathBuf::from(output_dir);
+    // Note: keeps the files inside the output directory whatever the key is.
+    for segment in output_location
+        .strip_prefix(S3_SCHEME)
+        .unwrap_or(output_location)
+        .split('/')
+        .filter(|v| !matches!(*v, "" | "." | ".."))
+    {
+        path.push(segment);
+    }
+    path
+}
+
+/// Writes the result of a query as Athena does to its OutputLocation, a csv with the header row
+/// and every value quoted but NULLs, which are empty, and optionally a `.metadata` file next to it.
+///
+/// The metadata is the `ResultSetMetadata` of `GetQueryResults` as JSON, not the binary format
+/// of Athena.
+// Note: each file is written next to where it goes and renamed there, so a reader never sees a
+// partial one, and the metadata comes first so it's there once the csv is.
+pub fn write_result(
+    path: &Path,
+    table_name: &str,
+    columns: &[crate::model::Column],
+    records: impl Iterator<Item = io::Result<crate::model::Record>>,
+    metadata: bool,
+) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if metadata {
+        write_atomically(&metadata_path(path), |f| {
+            write_metadata(f, table_name, columns)
+        })?;
+    }
+    write_atomically(path, |f| {
+        let mut writer = BufWriter::new(f);
+        write_csv(&mut writer, columns, records)?;
+        writer.flush()
+    })
+}
+
+/// The csv and, with `metadata`, the `.metadata` of the result of a query as `write_result`
+/// writes them, for uploading them elsewhere.
+#[cfg(feature = "s3")]
+pub fn result_objects(
+    table_name: &str,
+    columns: &[crate::model::Column],
+    records: impl Iterator<Item = io::Result<crate::model::Record>>,
+    metadata: bool,
+) -> io::Result<(Vec<u8>, Option<Vec<u8>>)> {
+    let metadata = if metadata {
+        let mut buf = Vec::new();
+        write_metadata(&mut buf, table_name, columns)?;
+        Some(buf)
+    } else {
+        None
+    };
+    let mut csv = Vec::new();
+    write_csv(&mut csv, columns, records)?;
+    Ok((csv, metadata))
+}
+
+/// Removes the result of a query and its `.metadata` file, if any.
+pub fn remove_result(path: &Path) -> io::Result<()> {
+    for path in [path.to_path_buf(), metadata_path(path)] {
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn metadata_path(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{:}{:}", path.display(), METADATA_SUFFIX))
+}
+
+fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> io::Result<()> {
+    let temp_path = PathBuf::from(format!("{:}{:}", path.display(), TEMP_SUFFIX));
+    let result = File::create(&temp_path)
+        .and_then(|mut f| write(&mut f).and_then(|_| f.sync_all()))
+        .and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn write_metadata(
+    writer: &mut impl Write,
+    table_name: &str,
+    columns: &[crate::model::Column],
+) -> io::Result<()> {
+    serde_json::to_writer(
+        writer,
+        &crate::model::ResultSetMetadata::new(table_name, columns),
+    )
+    .map_err(io::Error::from)
+}
+
+fn write_csv(
+    writer: &mut impl Write,
+    columns: &[crate::model::Column],
+    records: impl Iterator<Item = io::Result<crate::model::Record>>,
+) -> io::Result<()> {
+    write_record(writer, columns.iter().map(|v| Some(v.name.as_str())))?;
+    for record in records {
+        write_record(writer, record?.iter().map(Option::as_deref))?;
+    }
+    Ok(())
+}
+
+// Note: written by hand as the csv writer can't leave some of the fields of a record unquoted.
+fn write_record<'a>(
+    writer: &mut impl Write,
+    values: impl Iterator<Item = Option<&'a str>>,
+) -> io::Result<()> {
+    for (i, value) in values.enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        if let Some(value) = value {
+            write!(writer, "\"{:}\"", value.replace('"', "\"\""))?;
+        }
+    }
+    writer.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_path_mirrors_the_bucket_and_key_inside_the_output_dir() {
+        assert_eq!(
+            output_path("/tmp/out", "s3://bucket/results/id.csv"),
+            PathBuf::from("/tmp/out/bucket/results/id.csv")
+        );
+        assert_eq!(
+            output_path("/tmp/out", "s3://bucket//../../etc/./passwd"),
+            PathBuf::from("/tmp/out/bucket/etc/passwd")
+        );
+    }
+}
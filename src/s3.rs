@@ -0,0 +1,342 @@
+use actix_web::http::header::{self, HttpDate};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const CSV_CONTENT_TYPE: &str = "text/csv";
+const OCTET_STREAM_CONTENT_TYPE: &str = "application/octet-stream";
+const XML_CONTENT_TYPE: &str = "application/xml";
+const RANGE_UNIT: &str = "bytes";
+
+/// Answers a path-style S3 `GetObject`, `GET /{bucket}/{key}`, with a file under `OUTPUT_DIR`.
+pub async fn get_object(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    data: web::Data<crate::model::AppData>,
+) -> Result<HttpResponse> {
+    object(&req, &path, &data, true)
+}
+
+/// Answers a path-style S3 `HeadObject`, `HEAD /{bucket}/{key}`, as `get_object` without the body.
+pub async fn head_object(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    data: web::Data<crate::model::AppData>,
+) -> Result<HttpResponse> {
+    object(&req, &path, &data, false)
+}
+
+fn object(
+    req: &HttpRequest,
+    (bucket, key): &(String, String),
+    data: &crate::model::AppData,
+    with_body: bool,
+) -> Result<HttpResponse> {
+    // Note: authentication is ignored, any signature or none is accepted.
+    let path = match &data.output_dir {
+        Some(output_dir) => {
+            crate::output::output_path(output_dir, &format!("{:}/{:}", bucket, key))
+        }
+        None => return Ok(no_such_key(key, with_body)),
+    };
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound || path.is_dir() => {
+            return Ok(no_such_key(key, with_body))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let total = bytes.len() as u64;
+
+    // Note: a Range header which can't be parsed is ignored, as S3 does.
+    let range = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| byte_range(v, total));
+    let mut response = match range {
+        Some(Ok((start, end))) => {
+            let mut response = HttpResponse::build(StatusCode::PARTIAL_CONTENT);
+            response.header(
+                header::CONTENT_RANGE,
+                format!("{:} {:}-{:}/{:}", RANGE_UNIT, start, end, total),
+            );
+            response
+        }
+        Some(Err(())) => {
+            let mut response = error_response(
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                "InvalidRange",
+                "The requested range is not satisfiable",
+                key,
+                with_body,
+            );
+            if let Ok(value) =
+                header::HeaderValue::from_str(&format!("{:} */{:}", RANGE_UNIT, total))
+            {
+                response.headers_mut().insert(header::CONTENT_RANGE, value);
+            }
+            return Ok(response);
+        }
+        None => HttpResponse::Ok(),
+    };
+    let body = match range {
+        Some(Ok((start, end))) => &bytes[start as usize..=end as usize],
+        _ => &bytes[..],
+    };
+    response
+        .header(header::ACCEPT_RANGES, RANGE_UNIT)
+        .header(header::CONTENT_TYPE, content_type(&path))
+        .header(header::ETAG, format!("\"{:x}\"", md5::compute(&bytes)));
+    if let Ok(modified) = fs::metadata(&path).and_then(|v| v.modified()) {
+        response.header(header::LAST_MODIFIED, HttpDate::from(modified));
+    }
+    // Note: the body of a HEAD response is dropped by actix-web, which keeps its Content-Length.
+    Ok(response.body(body.to_vec()))
+}
+
+/// The inclusive range of a `bytes=start-end`, `bytes=start-` or `bytes=-suffix` Range within an
+/// object of `total` bytes, `Err` when it's outside of the object and `None` when unparsable.
+// Note: only a single range is supported, S3 doesn't support multiple ranges either.
+fn byte_range(range: &str, total: u64) -> Option<Result<(u64, u64), ()>> {
+    let (unit, spec) = range.trim().split_once('=')?;
+    if unit != RANGE_UNIT || spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.trim().split_once('-')?;
+    let range = match (start.trim(), end.trim()) {
+        ("", "") => return None,
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().ok()?;
+            if suffix == 0 || total == 0 {
+                return Some(Err(()));
+            }
+            (total.saturating_sub(suffix), total - 1)
+        }
+        (start, "") => (start.parse::<u64>().ok()?, total.saturating_sub(1)),
+        (start, end) => {
+            let (start, end) = (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?);
+            if end < start {
+                return None;
+            }
+            (start, end.min(total.saturating_sub(1)))
+        }
+    };
+    if range.0 >= total {
+        return Some(Err(()));
+    }
+    Some(Ok(range))
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|v| v.to_str()) {
+        Some("csv") => CSV_CONTENT_TYPE,
+        _ => OCTET_STREAM_CONTENT_TYPE,
+    }
+}
+
+fn no_such_key(key: &str, with_body: bool) -> HttpResponse {
+    error_response(
+        StatusCode::NOT_FOUND,
+        "NoSuchKey",
+        "The specified key does not exist.",
+        key,
+        with_body,
+    )
+}
+
+// Note: shaped like the XML error body of S3, HEAD responses carry none as on S3.
+fn error_response(
+    status: StatusCode,
+    code: &str,
+    message: &str,
+    key: &str,
+    with_body: bool,
+) -> HttpResponse {
+    let mut response = HttpResponse::build(status);
+    if !with_body {
+        return response.finish();
+    }
+    response.content_type(XML_CONTENT_TYPE).body(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Error><Code>{}</Code><Message>{}</Message><Key>{}</Key></Error>",
+        code,
+        message,
+        xml_escape(key)
+    ))
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::byte_range;
+    use crate::tests::{post, start};
+    use actix_web::client::Client;
+    use actix_web::http::{header, StatusCode};
+    use serde_json::json;
+
+    #[test]
+    fn byte_range_reads_a_single_range_within_the_object() {
+        assert_eq!(byte_range("bytes=0-3", 10), Some(Ok((0, 3))));
+        assert_eq!(byte_range("bytes=4-", 10), Some(Ok((4, 9))));
+        assert_eq!(byte_range("bytes=-4", 10), Some(Ok((6, 9))));
+        assert_eq!(byte_range("bytes=-20", 10), Some(Ok((0, 9))));
+        assert_eq!(byte_range("bytes=8-20", 10), Some(Ok((8, 9))));
+    }
+
+    #[test]
+    fn byte_range_outside_of_the_object_is_not_satisfiable() {
+        assert_eq!(byte_range("bytes=10-", 10), Some(Err(())));
+        assert_eq!(byte_range("bytes=10-20", 10), Some(Err(())));
+        assert_eq!(byte_range("bytes=-0", 10), Some(Err(())));
+        assert_eq!(byte_range("bytes=-4", 0), Some(Err(())));
+        assert_eq!(byte_range("bytes=0-", 0), Some(Err(())));
+    }
+
+    #[test]
+    fn byte_range_which_cannot_be_parsed_is_ignored() {
+        for range in [
+            "",
+            "bytes",
+            "bytes=",
+            "bytes=-",
+            "bytes=1",
+            "bytes=a-3",
+            "bytes=1-b",
+            "bytes=3-1",
+            "bytes=0-1,3-4",
+            "lines=0-1",
+        ] {
+            assert_eq!(byte_range(range, 10), None, "{:}", range);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn the_result_of_a_query_is_served_by_get_and_head_object() {
+        let output_dir = crate::operation::tests::fixture_dir(&[]);
+        let handle = start(
+            crate::MinervaServer::builder()
+                .immediate(true)
+                .csv_fixture_dir(crate::operation::tests::fixture_dir(&[(
+                    "users.csv",
+                    "id,name\n1,foo\n",
+                )]))
+                .output_dir(output_dir),
+        );
+        let (_, body) = post(
+            &handle,
+            "StartQueryExecution",
+            json!({ "QueryString": "SELECT * FROM users" }),
+        )
+        .await;
+        let url = format!(
+            "http://{:}/minerva-results/{:}.csv",
+            handle.addr(),
+            body["QueryExecutionId"].as_str().unwrap()
+        );
+        let object = "\"id\",\"name\"\n\"1\",\"foo\"\n";
+        let header = |response: &actix_web::client::ClientResponse<_>, name| {
+            response
+                .headers()
+                .get(name)
+                .map(|v: &header::HeaderValue| v.to_str().unwrap().to_string())
+        };
+
+        let mut response = Client::default().get(&url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            header(&response, header::CONTENT_TYPE).as_deref(),
+            Some("text/csv")
+        );
+        assert_eq!(
+            header(&response, header::ETAG),
+            Some(format!("\"{:x}\"", md5::compute(object)))
+        );
+        assert_eq!(
+            header(&response, header::ACCEPT_RANGES).as_deref(),
+            Some("bytes")
+        );
+        assert!(header(&response, header::LAST_MODIFIED).is_some());
+        assert_eq!(response.body().await.unwrap(), object.as_bytes());
+
+        let mut response = Client::default()
+            .get(&url)
+            .header(header::RANGE, "bytes=-6")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            header(&response, header::CONTENT_RANGE),
+            Some(format!("bytes 16-21/{:}", object.len()))
+        );
+        assert_eq!(response.body().await.unwrap(), &object.as_bytes()[16..]);
+
+        let mut response = Client::default()
+            .get(&url)
+            .header(header::RANGE, "bytes=0-99")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            header(&response, header::CONTENT_RANGE),
+            Some(format!("bytes 0-21/{:}", object.len()))
+        );
+        assert_eq!(response.body().await.unwrap(), object.as_bytes());
+
+        let mut response = Client::default()
+            .get(&url)
+            .header(header::RANGE, "bytes=99-")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            header(&response, header::CONTENT_RANGE),
+            Some(format!("bytes */{:}", object.len()))
+        );
+        let body = response.body().await.unwrap();
+        assert!(std::str::from_utf8(&body)
+            .unwrap()
+            .contains("<Code>InvalidRange</Code>"));
+
+        let mut response = Client::default()
+            .get(&url)
+            .header(header::RANGE, "lines=0-1")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(header(&response, header::CONTENT_RANGE).is_none());
+        assert_eq!(response.body().await.unwrap(), object.as_bytes());
+
+        let mut response = Client::default().head(&url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            header(&response, header::CONTENT_LENGTH),
+            Some(object.len().to_string())
+        );
+        assert!(response.body().await.unwrap().is_empty());
+
+        let missing = format!("http://{:}/minerva-results/missing.csv", handle.addr());
+        let mut response = Client::default().get(&missing).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.body().await.unwrap();
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains("<Code>NoSuchKey</Code>"), "{:}", body);
+        assert!(body.contains("<Key>missing.csv</Key>"), "{:}", body);
+        let mut response = Client::default().head(&missing).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(response.body().await.unwrap().is_empty());
+        handle.stop().await;
+    }
+}
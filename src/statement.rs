@@ -0,0 +1,147 @@
+use aws_sdk_athena::model::StatementType;
+
+/// What a query string does, told by its leading keywords after comments and whitespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    /// `SELECT`, `WITH`, `VALUES`, ... which return rows.
+    Query,
+    /// `INSERT`, `CREATE TABLE AS`, `UPDATE`, `DELETE`, `MERGE`, which return an `UpdateCount`.
+    Modification,
+    /// `CREATE`, `ALTER`, `DROP`, `MSCK`, ...
+    Definition,
+    /// `SHOW`, `DESCRIBE`, `EXPLAIN`.
+    Utility,
+}
+
+impl StatementKind {
+    pub fn classify(query_string: &str) -> Self {
+        let words = words(query_string);
+        match words.first().map(String::as_str) {
+            Some("INSERT" | "UPDATE" | "DELETE" | "MERGE") => StatementKind::Modification,
+            Some("CREATE") if is_create_table_as(&words) => StatementKind::Modification,
+            Some("CREATE" | "ALTER" | "DROP" | "MSCK" | "REPAIR" | "GRANT" | "REVOKE") => {
+                StatementKind::Definition
+            }
+            Some("SHOW" | "DESCRIBE" | "DESC" | "EXPLAIN") => StatementKind::Utility,
+            _ => StatementKind::Query,
+        }
+    }
+
+    /// Whether the result has rows, DML other than queries and DDL have an empty result.
+    pub fn has_rows(&self) -> bool {
+        matches!(self, StatementKind::Query | StatementKind::Utility)
+    }
+
+    pub fn statement_type(&self) -> StatementType {
+        match self {
+            StatementKind::Query | StatementKind::Modification => StatementType::Dml,
+            StatementKind::Definition => StatementType::Ddl,
+            StatementKind::Utility => StatementType::Utility,
+        }
+    }
+}
+
+// Note: `CREATE TABLE t WITH (format = 'PARQUET') AS SELECT ...`, a view is a DDL statement.
+fn is_create_table_as(words: &[String]) -> bool {
+    words
+        .iter()
+        .take_while(|v| *v != "AS")
+        .any(|v| v == "TABLE")
+        && words.windows(2).any(|v| {
+            v[0] == "AS"
+                && ["SELECT", "WITH", "VALUES", "TABLE"]
+                    .iter()
+                    .any(|keyword| v[1].starts_with(keyword))
+        })
+}
+
+// Note: upper-cased words of the query string without its comments, string literals are
+// not told apart.
+fn words(query_string: &str) -> Vec<String> {
+    tokens(query_string)
+        .into_iter()
+        .map(|v| v.to_uppercase())
+        .collect()
+}
+
+/// Words of the query string as written, without its comments; string literals are not told
+/// apart.
+pub fn tokens(query_string: &str) -> Vec<String> {
+    let mut text = String::new();
+    let mut rest = query_string;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("--") {
+            rest = comment.find('\n').map(|i| &comment[i..]).unwrap_or("");
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.find("*/").map(|i| &comment[i + 2..]).unwrap_or("");
+            text.push(' ');
+        } else {
+            let c = rest.chars().next().unwrap();
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    text.split(|c: char| c.is_whitespace() || c == ';')
+        .map(|v| v.trim_start_matches('(').to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_tells_ddl_apart() {
+        for query_string in [
+            "CREATE EXTERNAL TABLE users (id bigint) LOCATION 's3://bucket/users/'",
+            "DROP TABLE IF EXISTS users",
+            "ALTER TABLE users ADD PARTITION (dt = '2022-01-01')",
+            "CREATE VIEW v AS SELECT * FROM users",
+            "MSCK REPAIR TABLE users",
+        ] {
+            assert_eq!(
+                StatementKind::classify(query_string),
+                StatementKind::Definition,
+                "{:}",
+                query_string
+            );
+        }
+    }
+
+    #[test]
+    fn classify_skips_leading_comments_and_whitespace() {
+        let query_string =
+            "\n  -- migration 42\n\t/* users\n table */ create table users (id bigint)";
+        assert_eq!(
+            StatementKind::classify(query_string),
+            StatementKind::Definition
+        );
+        assert_eq!(
+            StatementKind::classify("/* DROP TABLE users */ SELECT * FROM users"),
+            StatementKind::Query
+        );
+        assert_eq!(
+            StatementKind::classify("-- CREATE TABLE\nINSERT INTO users SELECT * FROM staging"),
+            StatementKind::Modification
+        );
+    }
+
+    #[test]
+    fn classify_tells_a_create_table_as_select_from_ddl() {
+        assert_eq!(
+            StatementKind::classify(
+                "CREATE TABLE t WITH (format = 'PARQUET') AS SELECT * FROM users"
+            ),
+            StatementKind::Modification
+        );
+        assert_eq!(
+            StatementKind::classify("CREATE TABLE t AS (SELECT * FROM users)"),
+            StatementKind::Modification
+        );
+        assert_eq!(
+            StatementKind::Definition.statement_type(),
+            StatementType::Ddl
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use sqlparser::ast::Statement;
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+/// Parses a query string, a syntax error is reported like Athena does, e.g. `line 1:8: ...`.
+pub fn parse(query_string: &str) -> Result<Vec<Statement>, String> {
+    let dialect = GenericDialect {};
+    let tokens = Tokenizer::new(&dialect, query_string)
+        .tokenize()
+        .map_err(|e| format!("line {:}:{:}: {:}", e.line, e.col, e.message))?;
+    let positions = positions(&tokens);
+    let mut parser = Parser::new(tokens, &dialect);
+    parse_statements(&mut parser).map_err(|e| {
+        let message = match e {
+            ParserError::TokenizerError(message) | ParserError::ParserError(message) => message,
+        };
+        let mut remaining = 0;
+        while parser.next_token() != Token::EOF {
+            remaining += 1;
+        }
+        // Note: the parser either consumed the offending token or only peeked at it.
+        let next = positions.len() - 1 - remaining.min(positions.len() - 1);
+        let (line, col) = match next.checked_sub(1).and_then(|i| positions.get(i)) {
+            Some((token, position)) if message.ends_with(&format!("found: {:}", token)) => {
+                *position
+            }
+            _ => positions[next].1,
+        };
+        format!("line {:}:{:}: {:}", line, col, message)
+    })
+}
+
+// Note: same as `Parser::parse_sql`, which doesn't give back the parser to locate the error.
+fn parse_statements(parser: &mut Parser) -> Result<Vec<Statement>, ParserError> {
+    let mut statements = Vec::new();
+    let mut expecting_statement_delimiter = false;
+    loop {
+        while parser.consume_token(&Token::SemiColon) {
+            expecting_statement_delimiter = false;
+        }
+        if parser.peek_token() == Token::EOF {
+            break;
+        }
+        if expecting_statement_delimiter {
+            return Err(ParserError::ParserError(format!(
+                "Expected end of statement, found: {:}",
+                parser.peek_token()
+            )));
+        }
+        statements.push(parser.parse_statement()?);
+        expecting_statement_delimiter = true;
+    }
+    Ok(statements)
+}
+
+// Note: line and column of each non-whitespace token, followed by the end of the query.
+fn positions(tokens: &[Token]) -> Vec<(String, (u64, u64))> {
+    let (mut line, mut col) = (1, 1);
+    let mut positions = Vec::new();
+    for token in tokens {
+        let text = token.to_string();
+        if !matches!(token, Token::Whitespace(_)) {
+            positions.push((text.clone(), (line, col)));
+        }
+        for c in text.chars() {
+            if c == '\n' {
+                line += 1;
+                col = 1;
+            } else {
+                col += 1;
+            }
+        }
+    }
+    positions.push((Token::EOF.to_string(), (line, col)));
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reports_where_a_consumed_token_is() {
+        assert_eq!(
+            parse("SELEC * FROM users").unwrap_err(),
+            "line 1:1: Expected an SQL statement, found: SELEC"
+        );
+        assert_eq!(
+            parse("SELECT *\nFROM users\nWHERE id = = 1").unwrap_err(),
+            "line 3:12: Expected an expression:, found: ="
+        );
+    }
+
+    #[test]
+    fn parse_reports_where_a_peeked_token_is() {
+        assert_eq!(
+            parse("SELECT * FROM users u v").unwrap_err(),
+            "line 1:23: Expected end of statement, found: v"
+        );
+        assert_eq!(
+            parse("SELECT *\n  FROM users\n  WHERE").unwrap_err(),
+            "line 3:8: Expected an expression:, found: EOF"
+        );
+    }
+
+    #[test]
+    fn parse_reports_where_a_token_cannot_be_read() {
+        assert_eq!(
+            parse("SELECT 'foo").unwrap_err(),
+            "line 1:8: Unterminated string literal"
+        );
+    }
+
+    #[test]
+    fn parse_reads_several_statements() {
+        assert_eq!(parse("SELECT 1; SELECT 2;").unwrap().len(), 2);
+        assert_eq!(
+            parse("SELECT 1; SELECT * FROM").unwrap_err(),
+            "line 1:24: Expected identifier, found: EOF"
+        );
+    }
+}
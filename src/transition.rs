@@ -0,0 +1,216 @@
+use aws_sdk_athena::model::QueryExecutionState;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// What a `TransitionPlan` knows about the execution it drives.
+pub struct TransitionContext<'a> {
+    pub state: &'a QueryExecutionState,
+    /// Time since the execution was submitted, when it entered `state`.
+    pub elapsed: Duration,
+    /// Final state decided when the query was started, e.g. `FAILED` by `FAIL_QUERY_MARKER`.
+    pub outcome: &'a QueryExecutionState,
+    /// Durations of `QUEUED` and `RUNNING`, after the query hints are applied.
+    pub queued: Duration,
+    pub running: Duration,
+}
+
+/// Decides the lifecycle of query executions.
+pub trait TransitionPlan: Send + Sync {
+    /// Returns the state which follows `context.state` and how long `context.state` is held
+    /// before entering it, or `None` when the execution doesn't change anymore.
+    fn next(&self, context: &TransitionContext) -> Option<(QueryExecutionState, Duration)>;
+}
+
+/// `QUEUED` → `RUNNING` → the outcome of the query, same as Athena.
+pub struct DefaultTransitionPlan;
+
+impl TransitionPlan for DefaultTransitionPlan {
+    fn next(&self, context: &TransitionContext) -> Option<(QueryExecutionState, Duration)> {
+        match context.state {
+            QueryExecutionState::Queued => Some((QueryExecutionState::Running, context.queued)),
+            QueryExecutionState::Running => Some((context.outcome.clone(), context.running)),
+            _ => None,
+        }
+    }
+}
+
+/// A fixed sequence of states, e.g. `QUEUED:1s,RUNNING:5s,QUEUED:1s,RUNNING:5s,FAILED`.
+///
+/// Every state but the last one needs a duration. When the last state isn't a final one,
+/// the execution ends in the outcome of the query after it. Executions always start in
+/// `QUEUED`, so a plan beginning with another state leaves `QUEUED` right away.
+#[derive(Debug)]
+pub struct LinearTransitionPlan {
+    steps: Vec<(QueryExecutionState, Duration)>,
+    last: Option<QueryExecutionState>,
+}
+
+impl LinearTransitionPlan {
+    // Note: the same state may occur more than once, so the step is found by when it began.
+    fn step(&self, context: &TransitionContext) -> Option<usize> {
+        let mut begin = Duration::from_secs(0);
+        let mut step = None;
+        for (i, (state, duration)) in self.steps.iter().enumerate() {
+            if begin > context.elapsed && step.is_some() {
+                break;
+            }
+            if state == context.state {
+                step = Some(i);
+            }
+            begin += *duration;
+        }
+        step
+    }
+}
+
+impl TransitionPlan for LinearTransitionPlan {
+    fn next(&self, context: &TransitionContext) -> Option<(QueryExecutionState, Duration)> {
+        let step = self.step(context)?;
+        let next_state = match self.steps.get(step + 1) {
+            Some((state, _)) => state.clone(),
+            None => self.last.clone().unwrap_or_else(|| context.outcome.clone()),
+        };
+        Some((next_state, self.steps[step].1))
+    }
+}
+
+impl FromStr for LinearTransitionPlan {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut steps = Vec::new();
+        let mut last = None;
+        for step in s.split(',').map(str::trim) {
+            if last.is_some() {
+                return Err(format!("no state can follow a final state: {:}", step));
+            }
+            let (state, duration) = match step.split_once(':') {
+                Some((state, duration)) => (
+                    state,
+                    Some(
+                        crate::hint::parse_duration(duration)
+                            .ok_or_else(|| format!("invalid duration: {:}", step))?,
+                    ),
+                ),
+                None => (step, None),
+            };
+            match (
+                QueryExecutionState::from(state.to_uppercase().as_ref()),
+                duration,
+            ) {
+                (
+                    state @ (QueryExecutionState::Queued | QueryExecutionState::Running),
+                    Some(duration),
+                ) => steps.push((state, duration)),
+                (
+                    state @ (QueryExecutionState::Succeeded
+                    | QueryExecutionState::Failed
+                    | QueryExecutionState::Cancelled),
+                    None,
+                ) => last = Some(state),
+                _ => return Err(format!("invalid step: {:}", step)),
+            }
+        }
+        if steps.first().map(|(state, _)| state) != Some(&QueryExecutionState::Queued) {
+            steps.insert(0, (QueryExecutionState::Queued, Duration::from_secs(0)));
+        }
+        Ok(LinearTransitionPlan { steps, last })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Note: the states an execution goes through from QUEUED, each with how long it's held.
+    fn walk(
+        plan: &dyn TransitionPlan,
+        outcome: QueryExecutionState,
+    ) -> Vec<(QueryExecutionState, Option<Duration>)> {
+        let mut states = Vec::new();
+        let (mut state, mut elapsed) = (QueryExecutionState::Queued, Duration::from_secs(0));
+        while let Some((next_state, duration)) = plan.next(&TransitionContext {
+            state: &state,
+            elapsed,
+            outcome: &outcome,
+            queued: Duration::from_secs(1),
+            running: Duration::from_secs(2),
+        }) {
+            states.push((state, Some(duration)));
+            elapsed += duration;
+            state = next_state;
+        }
+        states.push((state, None));
+        states
+    }
+
+    fn secs(v: u64) -> Option<Duration> {
+        Some(Duration::from_secs(v))
+    }
+
+    #[test]
+    fn default_plan_goes_through_running_to_the_outcome() {
+        assert_eq!(
+            walk(&DefaultTransitionPlan, QueryExecutionState::Failed),
+            [
+                (QueryExecutionState::Queued, secs(1)),
+                (QueryExecutionState::Running, secs(2)),
+                (QueryExecutionState::Failed, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn linear_plan_repeats_states_in_order() {
+        let plan = "QUEUED:1s,RUNNING:5s,QUEUED:1s,RUNNING:5s,FAILED"
+            .parse::<LinearTransitionPlan>()
+            .unwrap();
+        assert_eq!(
+            walk(&plan, QueryExecutionState::Succeeded),
+            [
+                (QueryExecutionState::Queued, secs(1)),
+                (QueryExecutionState::Running, secs(5)),
+                (QueryExecutionState::Queued, secs(1)),
+                (QueryExecutionState::Running, secs(5)),
+                (QueryExecutionState::Failed, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn linear_plan_starts_in_queued_and_ends_in_the_outcome() {
+        let plan = "running:500ms".parse::<LinearTransitionPlan>().unwrap();
+        assert_eq!(
+            walk(&plan, QueryExecutionState::Cancelled),
+            [
+                (QueryExecutionState::Queued, secs(0)),
+                (
+                    QueryExecutionState::Running,
+                    Some(Duration::from_millis(500))
+                ),
+                (QueryExecutionState::Cancelled, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn linear_plan_rejects_malformed_steps() {
+        for (plan, message) in [
+            (
+                "QUEUED:1s,SUCCEEDED,RUNNING:1s",
+                "no state can follow a final state: RUNNING:1s",
+            ),
+            ("QUEUED:1s,RUNNING", "invalid step: RUNNING"),
+            ("QUEUED:soon,RUNNING:1s", "invalid duration: QUEUED:soon"),
+            ("QUEUED:1s,SUCCEEDED:1s", "invalid step: SUCCEEDED:1s"),
+            ("PENDING:1s", "invalid step: PENDING:1s"),
+        ] {
+            assert_eq!(
+                plan.parse::<LinearTransitionPlan>().unwrap_err(),
+                message,
+                "{:}",
+                plan
+            );
+        }
+    }
+}
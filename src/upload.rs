@@ -0,0 +1,261 @@
+use aws_sdk_s3::types::ByteStream;
+use aws_sdk_s3::{Client, Endpoint, Region};
+use std::env;
+use std::io;
+
+const S3_SCHEME: &str = "s3://";
+const CSV_CONTENT_TYPE: &str = "text/csv";
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// Uploads the results of queries to an S3-compatible endpoint, e.g. localstack or minio, as
+/// Athena writes them to their OutputLocation.
+pub struct ResultUploader {
+    client: Client,
+    bucket: Option<String>,
+    // Note: the SDK runs on tokio 1, which actix-rt doesn't provide.
+    runtime: tokio::runtime::Runtime,
+}
+
+impl ResultUploader {
+    /// Credentials are read from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN`, the region from `AWS_REGION` or `AWS_DEFAULT_REGION`.
+    pub fn new(endpoint: &str, bucket: Option<String>) -> io::Result<Self> {
+        let endpoint = endpoint.parse().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid RESULT_S3_ENDPOINT '{:}': {:}", endpoint, e),
+            )
+        })?;
+        let region = env::var("AWS_REGION")
+            .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| DEFAULT_REGION.to_string());
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        // Note: loaded for the sleep implementation of the retries, region and credentials are
+        // given so nothing is looked up beyond the env vars.
+        let sdk_config = runtime.block_on(
+            aws_config::from_env()
+                .region(Region::new(region))
+                .credentials_provider(
+                    aws_config::environment::credentials::EnvironmentVariableCredentialsProvider::new(),
+                )
+                .load(),
+        );
+        let config = aws_sdk_s3::config::Builder::from(&sdk_config)
+            .endpoint_resolver(Endpoint::immutable(endpoint))
+            .build();
+        Ok(ResultUploader {
+            client: Client::from_conf(config),
+            bucket,
+            runtime,
+        })
+    }
+
+    /// The object of `output_location` on the endpoint, in `RESULT_S3_BUCKET` when it's set.
+    pub fn output_location(&self, output_location: &str) -> String {
+        match &self.bucket {
+            Some(bucket) => {
+                let (_, key) = bucket_and_key(output_location);
+                format!("{:}{:}/{:}", S3_SCHEME, bucket, key)
+            }
+            None => output_location.to_string(),
+        }
+    }
+
+    /// Puts the result csv of a query to `output_location` and its `.metadata` next to it, the
+    /// metadata first as `crate::output::write_result` does.
+    pub async fn upload(
+        &self,
+        output_location: &str,
+        csv: Vec<u8>,
+        metadata: Option<Vec<u8>>,
+    ) -> Result<(), String> {
+        let (bucket, key) = bucket_and_key(output_location);
+        let (bucket, key) = (bucket.to_string(), key.to_string());
+        let client = self.client.clone();
+        self.runtime
+            .spawn(async move {
+                if let Some(metadata) = metadata {
+                    client
+                        .put_object()
+                        .bucket(&bucket)
+                        .key(format!("{:}{:}", key, crate::output::METADATA_SUFFIX))
+                        .body(ByteStream::from(metadata))
+                        .send()
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+                client
+                    .put_object()
+                    .bucket(&bucket)
+                    .key(&key)
+                    .content_type(CSV_CONTENT_TYPE)
+                    .body(ByteStream::from(csv))
+                    .send()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(())
+            })
+            .await
+            .map_err(|e| e.to_string())?
+    }
+}
+
+fn bucket_and_key(output_location: &str) -> (&str, &str) {
+    let location = output_location
+        .strip_prefix(S3_SCHEME)
+        .unwrap_or(output_location);
+    location.split_once('/').unwrap_or((location, ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::operation::tests::{app_data, fixture_dir, query_execution, start};
+    use actix_web::rt::time;
+    use std::time::Duration;
+
+    // Note: the uploads go through the network, so the state is polled in real time.
+    async fn finished_query_execution(
+        data: &crate::model::AppData,
+        query_execution_id: &str,
+    ) -> serde_json::Value {
+        for _ in 0..600 {
+            let query_execution = query_execution(data, query_execution_id);
+            if ["SUCCEEDED", "FAILED", "CANCELLED"]
+                .contains(&query_execution["Status"]["State"].as_str().unwrap())
+            {
+                return query_execution;
+            }
+            time::delay_for(Duration::from_millis(50)).await;
+        }
+        panic!("{:} didn't finish", query_execution_id);
+    }
+
+    #[test]
+    fn output_location_is_moved_to_the_result_bucket() {
+        let uploader = super::ResultUploader::new("http://127.0.0.1:9000", None).unwrap();
+        assert_eq!(
+            uploader.output_location("s3://foo/bar/baz.csv"),
+            "s3://foo/bar/baz.csv"
+        );
+        let uploader =
+            super::ResultUploader::new("http://127.0.0.1:9000", Some("results".to_string()))
+                .unwrap();
+        assert_eq!(
+            uploader.output_location("s3://foo/bar/baz.csv"),
+            "s3://results/bar/baz.csv"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn a_query_whose_result_cannot_be_uploaded_is_failed() {
+        // Note: so the upload gets as far as connecting.
+        for name in ["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY"] {
+            if std::env::var(name).is_err() {
+                std::env::set_var(name, "minerva");
+            }
+        }
+        // Note: a port nothing listens on once the listener is dropped.
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .queued_duration(Duration::from_secs(0))
+                .running_duration(Duration::from_secs(0))
+                .csv_fixture_dir(fixture_dir(&[("users.csv", "id\n1\n")]))
+                .result_s3_endpoint(format!("http://{:}", addr))
+                .result_s3_bucket("results"),
+        );
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        let query_execution = finished_query_execution(&data, &query_execution_id).await;
+
+        let output_location = format!("s3://results/{:}.csv", query_execution_id);
+        assert_eq!(
+            query_execution["ResultConfiguration"]["OutputLocation"],
+            output_location.as_str()
+        );
+        assert_eq!(query_execution["Status"]["State"], "FAILED");
+        let reason = query_execution["Status"]["StateChangeReason"]
+            .as_str()
+            .unwrap();
+        assert!(
+            reason.starts_with(&format!(
+                "Unable to write the query result to the output location {:}: ",
+                output_location
+            )),
+            "{:}",
+            reason
+        );
+        assert!(reason.contains("connect"), "{:}", reason);
+    }
+
+    // Note: runs against minio (or another S3-compatible endpoint) only when
+    // MINERVA_TEST_S3_ENDPOINT is set, e.g. `http://localhost:9000`, with the credentials of
+    // AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY; the bucket of MINERVA_TEST_S3_BUCKET
+    // (`minerva-test` by default) is created when it isn't there.
+    #[actix_rt::test]
+    async fn the_result_of_a_succeeded_query_is_uploaded_to_minio() {
+        let endpoint = match std::env::var("MINERVA_TEST_S3_ENDPOINT") {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+        let bucket =
+            std::env::var("MINERVA_TEST_S3_BUCKET").unwrap_or_else(|_| "minerva-test".to_string());
+        let uploader = super::ResultUploader::new(&endpoint, None).unwrap();
+        let client = uploader.client.clone();
+        match uploader
+            .runtime
+            .block_on(client.create_bucket().bucket(&bucket).send())
+        {
+            Ok(_) => {}
+            Err(aws_sdk_s3::types::SdkError::ServiceError { err, .. })
+                if err.is_bucket_already_owned_by_you() || err.is_bucket_already_exists() => {}
+            Err(e) => panic!("failed to create {:}: {:}", bucket, e),
+        }
+
+        let data = app_data(
+            crate::MinervaServer::builder()
+                .queued_duration(Duration::from_secs(0))
+                .running_duration(Duration::from_secs(0))
+                .csv_fixture_dir(fixture_dir(&[("users.csv", "id,name\n1,foo\n2,\n")]))
+                .result_s3_endpoint(endpoint)
+                .result_s3_bucket(bucket.clone()),
+        );
+        let query_execution_id = start(&data, "SELECT * FROM users");
+        let query_execution = finished_query_execution(&data, &query_execution_id).await;
+        assert_eq!(
+            query_execution["Status"]["State"], "SUCCEEDED",
+            "{:}",
+            query_execution
+        );
+        assert_eq!(
+            query_execution["ResultConfiguration"]["OutputLocation"],
+            format!("s3://{:}/{:}.csv", bucket, query_execution_id)
+        );
+
+        let get_object = |key: String| {
+            let request = client.get_object().bucket(&bucket).key(key).send();
+            uploader.runtime.block_on(async {
+                let output = request.await.unwrap();
+                (
+                    output.content_type,
+                    output.body.collect().await.unwrap().into_bytes().to_vec(),
+                )
+            })
+        };
+        let (content_type, csv) = get_object(format!("{:}.csv", query_execution_id));
+        assert_eq!(content_type.as_deref(), Some(super::CSV_CONTENT_TYPE));
+        assert_eq!(csv, b"\"id\",\"name\"\n\"1\",\"foo\"\n\"2\",\n");
+        let (_, metadata) = get_object(format!(
+            "{:}.csv{:}",
+            query_execution_id,
+            crate::output::METADATA_SUFFIX
+        ));
+        let metadata: serde_json::Value = serde_json::from_slice(&metadata).unwrap();
+        assert_eq!(metadata["ColumnInfo"][1]["Name"], "name");
+    }
+}
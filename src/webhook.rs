@@ -0,0 +1,170 @@
+use actix_rt::{spawn, time};
+use actix_web::client::Client;
+use aws_sdk_athena::model::QueryExecutionState;
+use aws_smithy_types::date_time::Format;
+use aws_smithy_types::DateTime;
+use std::time::Duration;
+use uuid::Uuid;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+const WEBHOOK_RETRIES: u32 = 2;
+const WEBHOOK_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+// Note: placeholders for the fields EventBridge fills in from the AWS environment.
+const EVENT_ACCOUNT: &str = "123456789012";
+const EVENT_REGION: &str = "us-east-1";
+
+/// Shaped like the detail of the `Athena Query State Change` EventBridge event.
+#[derive(serde::Serialize)]
+struct StateChangeEvent {
+    version: String,
+    id: String,
+    #[serde(rename = "detail-type")]
+    detail_type: String,
+    source: String,
+    account: String,
+    time: String,
+    region: String,
+    resources: Vec<String>,
+    detail: StateChangeDetail,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StateChangeDetail {
+    current_state: String,
+    previous_state: String,
+    query_execution_id: String,
+    statement_type: String,
+    version_id: String,
+    workgroup_name: String,
+    query: String,
+    submission_date_time: String,
+    completion_date_time: Option<String>,
+    total_execution_time_in_millis: Option<u64>,
+}
+
+/// Posts the final state of a query to `url`, without waiting for the delivery.
+pub fn notify_completion(
+    url: &str,
+    query_execution_id: &str,
+    previous_state: QueryExecutionState,
+    query_process: &crate::model::QueryProcess,
+) {
+    let event = StateChangeEvent {
+        version: "0".to_string(),
+        id: Uuid::new_v4().to_string(),
+        detail_type: "Athena Query State Change".to_string(),
+        source: "aws.athena".to_string(),
+        account: EVENT_ACCOUNT.to_string(),
+        time: rfc3339(
+            query_process
+                .completion_date_time
+                .unwrap_or(query_process.submission_date_time),
+        ),
+        region: EVENT_REGION.to_string(),
+        resources: Vec::new(),
+        detail: StateChangeDetail {
+            current_state: query_process.state.clone(),
+            previous_state: previous_state.as_str().to_string(),
+            query_execution_id: query_execution_id.to_string(),
+            statement_type: query_process
+                .statement_kind
+                .statement_type()
+                .as_str()
+                .to_string(),
+            version_id: "0".to_string(),
+            workgroup_name: query_process.work_group.clone(),
+            query: query_process.query_string.clone(),
+            submission_date_time: rfc3339(query_process.submission_date_time),
+            completion_date_time: query_process.completion_date_time.map(rfc3339),
+            total_execution_time_in_millis: query_process
+                .completion_date_time
+                .map(|v| v.saturating_sub(query_process.submission_date_time)),
+        },
+    };
+    let url = url.to_string();
+    spawn(async move {
+        for attempt in 0..=WEBHOOK_RETRIES {
+            if attempt > 0 {
+                time::delay_for(WEBHOOK_RETRY_INTERVAL).await;
+            }
+            match Client::default()
+                .post(&url)
+                .timeout(WEBHOOK_TIMEOUT)
+                .send_json(&event)
+                .await
+            {
+                Ok(response) if response.status().is_success() => return,
+                Ok(response) => tracing::warn!(
+                    "completion webhook for {:} responded {:}",
+                    event.detail.query_execution_id,
+                    response.status()
+                ),
+                Err(e) => tracing::warn!(
+                    "completion webhook for {:} failed: {:}",
+                    event.detail.query_execution_id,
+                    e
+                ),
+            }
+        }
+    })
+}
+
+fn rfc3339(millis: u64) -> String {
+    DateTime::from_millis(millis as i64)
+        .fmt(Format::DateTime)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+
+    #[actix_rt::test]
+    async fn a_finished_query_is_posted_to_the_webhook() {
+        let (sender, mut receiver) = mpsc::unbounded::<serde_json::Value>();
+        let server = HttpServer::new(move || {
+            let sender = sender.clone();
+            App::new().route(
+                "/",
+                web::post().to(move |body: web::Json<serde_json::Value>| {
+                    sender.unbounded_send(body.into_inner()).unwrap();
+                    HttpResponse::Ok().finish()
+                }),
+            )
+        })
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        let server = server.run();
+
+        let data = crate::operation::tests::app_data(
+            crate::MinervaServer::builder()
+                .immediate(true)
+                .completion_webhook_url(format!("http://{:}/", addr)),
+        );
+        let query_string = "SELECT * FROM users";
+        let query_execution_id = crate::operation::tests::start(&data, query_string);
+        let event = time::timeout(Duration::from_secs(5), receiver.next())
+            .await
+            .unwrap()
+            .unwrap();
+        server.stop(false).await;
+
+        assert_eq!(event["detail-type"], "Athena Query State Change");
+        assert_eq!(event["source"], "aws.athena");
+        let detail = &event["detail"];
+        assert_eq!(detail["queryExecutionId"], query_execution_id.as_str());
+        assert_eq!(detail["currentState"], "SUCCEEDED");
+        assert_eq!(detail["previousState"], "QUEUED");
+        assert_eq!(detail["statementType"], "DML");
+        assert_eq!(detail["workgroupName"], "primary");
+        assert_eq!(detail["query"], query_string);
+        assert_eq!(detail["totalExecutionTimeInMillis"], 0);
+        assert_eq!(detail["submissionDateTime"], detail["completionDateTime"]);
+    }
+}